@@ -0,0 +1,113 @@
+//             DO WHAT THE FUCK YOU WANT TO PUBLIC LICENSE
+//                    Version 2, December 2004
+//
+// Copyright (C) 2018 Thomas Bailleux <thomas@bailleux.me>
+//
+// Everyone is permitted to copy and distribute verbatim or modified
+// copies of this license document, and changing it is allowed as long
+// as the name is changed.
+//
+//            DO WHAT THE FUCK YOU WANT TO PUBLIC LICENSE
+//   TERMS AND CONDITIONS FOR COPYING, DISTRIBUTION AND MODIFICATION
+//
+//  0. You just DO WHAT THE FUCK YOU WANT TO.
+//
+// Author: zadig <thomas chr(0x40) bailleux.me>
+
+//! Throughput of stream extraction, on large streams.
+//!
+//! Run with `cargo bench`.
+
+use std::io::{BufRead, Cursor, Read};
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use ole::{Reader, Version, Writer};
+
+/// Size of the extracted stream.
+const STREAM_SIZE: usize = 32 << 20;
+
+/// Returns a file holding one large stream.
+fn large_file(version: Version) -> Vec<u8> {
+    let data: Vec<u8> = (0..STREAM_SIZE).map(|i| (i % 251) as u8).collect();
+    let mut writer = Writer::new(version);
+    writer.create_stream("Large", &data).unwrap();
+    let mut buf = Vec::new();
+    writer.write_to(&mut buf).unwrap();
+    buf
+}
+
+fn read_to_end(c: &mut Criterion) {
+    let mut group = c.benchmark_group("read_to_end");
+    group.throughput(Throughput::Bytes(STREAM_SIZE as u64));
+    group.sample_size(20);
+    for (name, version) in [("v3", Version::V3), ("v4", Version::V4)] {
+        let file = large_file(version);
+        let ole = Reader::new(&file[..]).unwrap();
+        group.bench_function(format!("memory/{name}"), |b| {
+            let mut out = Vec::with_capacity(STREAM_SIZE);
+            b.iter(|| {
+                out.clear();
+                ole.open_stream("Large")
+                    .unwrap()
+                    .read_to_end(&mut out)
+                    .unwrap()
+            })
+        });
+
+        let ole = Reader::from_seekable(Cursor::new(&file[..])).unwrap();
+        group.bench_function(format!("seekable/{name}"), |b| {
+            let mut out = Vec::with_capacity(STREAM_SIZE);
+            b.iter(|| {
+                out.clear();
+                ole.open_stream("Large")
+                    .unwrap()
+                    .read_to_end(&mut out)
+                    .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn copy(c: &mut Criterion) {
+    let mut group = c.benchmark_group("copy");
+    group.throughput(Throughput::Bytes(STREAM_SIZE as u64));
+    group.sample_size(20);
+    let file = large_file(Version::V3);
+    let ole = Reader::new(&file[..]).unwrap();
+    group.bench_function("io_copy", |b| {
+        b.iter_batched(
+            || ole.open_stream("Large").unwrap(),
+            |mut slice| std::io::copy(&mut slice, &mut std::io::sink()).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("buf_read", |b| {
+        let mut out = Vec::with_capacity(STREAM_SIZE);
+        b.iter(|| {
+            out.clear();
+            let mut slice = ole.open_stream("Large").unwrap();
+            loop {
+                let buf = slice.fill_buf().unwrap();
+                if buf.is_empty() {
+                    break;
+                }
+                out.extend_from_slice(buf);
+                let n = buf.len();
+                slice.consume(n);
+            }
+        })
+    });
+    group.finish();
+}
+
+fn open_stream(c: &mut Criterion) {
+    let file = large_file(Version::V3);
+    let ole = Reader::new(&file[..]).unwrap();
+    c.bench_function("open_stream", |b| {
+        b.iter(|| ole.open_stream("Large").unwrap().len())
+    });
+}
+
+criterion_group!(benches, read_to_end, copy, open_stream);
+criterion_main!(benches);
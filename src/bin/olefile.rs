@@ -0,0 +1,378 @@
+//             DO WHAT THE FUCK YOU WANT TO PUBLIC LICENSE
+//                    Version 2, December 2004
+//
+// Copyright (C) 2018 Thomas Bailleux <thomas@bailleux.me>
+//
+// Everyone is permitted to copy and distribute verbatim or modified
+// copies of this license document, and changing it is allowed as long
+// as the name is changed.
+//
+//            DO WHAT THE FUCK YOU WANT TO PUBLIC LICENSE
+//   TERMS AND CONDITIONS FOR COPYING, DISTRIBUTION AND MODIFICATION
+//
+//  0. You just DO WHAT THE FUCK YOU WANT TO.
+//
+// Author: zadig <thomas chr(0x40) bailleux.me>
+
+//! Command-line tool to inspect compound files.
+//!
+//! Requires the `cli` feature.
+
+use std::{
+    error::Error,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::{Parser, Subcommand};
+use ole::{Entry, EntryType, FileTime, Reader};
+
+/// Inspects Microsoft compound files (.doc, .xls, .msi, Thumbs.db, ...).
+#[derive(Parser)]
+#[command(name = "olefile", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Lists the storages and streams, with their sizes, CLSIDs and
+    /// modification times.
+    Ls { file: PathBuf },
+
+    /// Writes the stream at PATH to the standard output.
+    Cat { file: PathBuf, path: String },
+
+    /// Recreates the storages and streams as directories and files in DIR.
+    /// A `~N` suffix is added to the names of the files which already
+    /// exist.
+    Extract { file: PathBuf, dir: PathBuf },
+
+    /// Prints the fields of the header.
+    Info { file: PathBuf },
+
+    /// Prints the summary information of the document.
+    Meta { file: PathBuf },
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse().command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("olefile: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: Command) -> Result<(), Box<dyn Error>> {
+    let mut out = io::stdout().lock();
+    match command {
+        Command::Ls { file } => ls(&open(&file)?, &mut out)?,
+        Command::Cat { file, path } => {
+            let reader = open(&file)?;
+            let entry = reader.entry_by_path(&path)?;
+            if entry._type() != EntryType::UserStream {
+                return Err(ole::Error::NotAStream(path).into());
+            }
+            copy_stream(&reader, entry, &mut out)?;
+        }
+        Command::Extract { file, dir } => extract(&open(&file)?, &dir)?,
+        Command::Info { file } => info(&open(&file)?, &mut out)?,
+        Command::Meta { file } => meta(&open(&file)?, &mut out)?,
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Opens `path`, reading the streams on demand.
+fn open(path: &Path) -> Result<Reader<'static>, Box<dyn Error>> {
+    Ok(Reader::from_seekable(File::open(path)?)?)
+}
+
+/// Writes the content of the stream `entry` to `out`.
+fn copy_stream(reader: &Reader, entry: &Entry, out: &mut impl Write) -> Result<(), Box<dyn Error>> {
+    // Empty streams have no slice.
+    if !entry.is_empty() {
+        io::copy(&mut reader.get_entry_slice(entry)?, out)?;
+    }
+    Ok(())
+}
+
+/// Returns `name` with the control characters escaped.
+fn display_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_control() {
+                c.escape_unicode().to_string()
+            } else {
+                c.to_string()
+            }
+        })
+        .collect()
+}
+
+fn ls(reader: &Reader, out: &mut impl Write) -> io::Result<()> {
+    writeln!(
+        out,
+        "{:>10}  {:<23}  {:<38}  NAME",
+        "SIZE", "MODIFIED", "CLSID"
+    )?;
+    for (_, depth, entry) in reader.walk() {
+        let (size, suffix) = match entry._type() {
+            EntryType::UserStream => (entry.len().to_string(), ""),
+            _ => (String::new(), "/"),
+        };
+        let clsid = if entry.clsid().is_nil() {
+            String::new()
+        } else {
+            entry.clsid().to_string()
+        };
+        writeln!(
+            out,
+            "{:>10}  {:<23}  {:<38}  {}{}{}",
+            size,
+            entry.modified().to_string(),
+            clsid,
+            "  ".repeat(depth),
+            display_name(entry.name()),
+            suffix
+        )?;
+    }
+    Ok(())
+}
+
+/// Returns `name` as a file name: the characters which can't be used in file
+/// names on common systems are replaced with `_`.
+fn file_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_control() || "/\\:*?\"<>|".contains(c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    match name.as_str() {
+        "" | "." | ".." => format!("_{name}"),
+        _ => name,
+    }
+}
+
+fn extract(reader: &Reader, dir: &Path) -> Result<(), Box<dyn Error>> {
+    // Directories of the storages being extracted, by depth.
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    for (_, depth, entry) in reader.walk() {
+        dirs.truncate(depth);
+        let Some(parent) = dirs.last() else {
+            fs::create_dir_all(dir)?;
+            dirs.push(dir.to_path_buf());
+            continue;
+        };
+        let name = file_name(entry.name());
+        if entry._type() == EntryType::UserStream {
+            let (_, mut file) = create_new(parent, &name, |path| {
+                OpenOptions::new().write(true).create_new(true).open(path)
+            })?;
+            copy_stream(reader, entry, &mut file)?;
+        } else {
+            let (path, ()) = create_new(parent, &name, |path| fs::create_dir(path))?;
+            dirs.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Creates `name` in `parent` with `create`, which fails if it already
+/// exists, or `name~1`, `name~2`... as entries with different names can have
+/// the same file name.
+fn create_new<T>(
+    parent: &Path,
+    name: &str,
+    create: impl Fn(&Path) -> io::Result<T>,
+) -> io::Result<(PathBuf, T)> {
+    let mut path = parent.join(name);
+    let mut n = 0;
+    loop {
+        match create(&path) {
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists => {
+                n += 1;
+                path = parent.join(format!("{name}~{n}"));
+            }
+            result => return result.map(|created| (path, created)),
+        }
+    }
+}
+
+fn info(reader: &Reader, out: &mut impl Write) -> io::Result<()> {
+    let header = reader.header();
+    let fields: [(&str, String); 16] = [
+        ("CLSID", header.clsid().to_string()),
+        ("Minor version", format!("{:#06x}", header.minor_version())),
+        ("Major version", header.major_version().to_string()),
+        ("Byte order", format!("{:?}", header.byte_order())),
+        ("Sector size", header.sector_size().to_string()),
+        ("Mini sector size", header.mini_sector_size().to_string()),
+        (
+            "Directory sectors",
+            header.directory_sector_count().to_string(),
+        ),
+        ("FAT sectors", header.fat_sector_count().to_string()),
+        (
+            "First directory sector",
+            format!("{:#x}", header.first_directory_sector()),
+        ),
+        (
+            "Transaction signature",
+            header.transaction_signature().to_string(),
+        ),
+        (
+            "Mini stream cutoff",
+            header.mini_stream_cutoff().to_string(),
+        ),
+        (
+            "First mini FAT sector",
+            format!("{:#x}", header.first_mini_fat_sector()),
+        ),
+        (
+            "Mini FAT sectors",
+            header.mini_fat_sector_count().to_string(),
+        ),
+        (
+            "First DIFAT sector",
+            format!("{:#x}", header.first_difat_sector()),
+        ),
+        ("DIFAT sectors", header.difat_sector_count().to_string()),
+        ("Entries", reader.iterate().count().to_string()),
+    ];
+    for (name, value) in fields {
+        writeln!(out, "{name:<24}{value}")?;
+    }
+    Ok(())
+}
+
+fn meta(reader: &Reader, out: &mut impl Write) -> Result<(), Box<dyn Error>> {
+    let summary = reader
+        .exists("\u{5}SummaryInformation")
+        .then(|| reader.summary_information())
+        .transpose()?;
+    let document = reader
+        .exists("\u{5}DocumentSummaryInformation")
+        .then(|| reader.document_summary_information())
+        .transpose()?;
+
+    let text = |value: Option<&str>| value.map(str::to_string);
+    let number = |value: Option<i32>| value.map(|n| n.to_string());
+    let time = |value: Option<FileTime>| value.map(|t| t.to_string());
+    let mut fields = Vec::new();
+    if let Some(s) = &summary {
+        fields.extend([
+            ("Title", text(s.title())),
+            ("Subject", text(s.subject())),
+            ("Author", text(s.author())),
+            ("Keywords", text(s.keywords())),
+            ("Comments", text(s.comments())),
+            ("Template", text(s.template())),
+            ("Last saved by", text(s.last_saved_by())),
+            ("Revision", text(s.revision())),
+            ("Application", text(s.application_name())),
+            (
+                "Edit time",
+                s.edit_time().map(|d| format!("{}s", d.as_secs())),
+            ),
+            ("Last printed", time(s.last_printed())),
+            ("Created", time(s.created())),
+            ("Last saved", time(s.last_saved())),
+            ("Pages", number(s.page_count())),
+            ("Words", number(s.word_count())),
+            ("Characters", number(s.char_count())),
+        ]);
+    }
+    if let Some(d) = &document {
+        fields.extend([
+            ("Category", text(d.category())),
+            ("Manager", text(d.manager())),
+            ("Company", text(d.company())),
+        ]);
+    }
+    if summary.is_none() && document.is_none() {
+        return Err("no summary information".into());
+    }
+    for (name, value) in fields {
+        if let Some(value) = value.filter(|value| !value.is_empty()) {
+            writeln!(out, "{name:<16}{value}")?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{extract, file_name, ls};
+    use ole::{Reader, Version, Writer};
+    use std::fs;
+
+    #[test]
+    fn file_names() {
+        assert_eq!(file_name("\u{5}SummaryInformation"), "_SummaryInformation");
+        assert_eq!(file_name("a:b*c"), "a_b_c");
+        assert_eq!(file_name(".."), "_..");
+    }
+
+    #[test]
+    fn list_and_extract() {
+        let mut writer = Writer::new(Version::V3);
+        writer.create_storage("Dir").unwrap();
+        writer.create_stream("Dir/Big", &[1u8; 5000]).unwrap();
+        writer.create_stream("Dir/Empty", &[]).unwrap();
+        writer.create_stream("Small", b"small").unwrap();
+        let mut buf = Vec::new();
+        writer.write_to(&mut buf).unwrap();
+        let reader = Reader::new(&buf[..]).unwrap();
+
+        let mut out = Vec::new();
+        ls(&reader, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let names: Vec<&str> = out.lines().skip(1).map(|l| l[77..].trim()).collect();
+        assert_eq!(names, ["Root Entry/", "Dir/", "Big", "Empty", "Small"]);
+
+        let dir = std::env::temp_dir().join(format!("olefile-extract-{}", std::process::id()));
+        extract(&reader, &dir).unwrap();
+        assert_eq!(fs::read(dir.join("Dir/Big")).unwrap(), [1u8; 5000]);
+        assert_eq!(fs::read(dir.join("Dir/Empty")).unwrap(), []);
+        assert_eq!(fs::read(dir.join("Small")).unwrap(), b"small");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn extract_colliding_names() {
+        let mut writer = Writer::new(Version::V3);
+        writer.create_stream("a\u{1}b", b"control").unwrap();
+        writer.create_stream("a*b", b"star").unwrap();
+        writer.create_storage("c?").unwrap();
+        writer.create_storage("c|").unwrap();
+        writer.create_stream("c?/s", b"question").unwrap();
+        writer.create_stream("c|/s", b"pipe").unwrap();
+        let mut buf = Vec::new();
+        writer.write_to(&mut buf).unwrap();
+        let reader = Reader::new(&buf[..]).unwrap();
+
+        let dir = std::env::temp_dir().join(format!("olefile-collide-{}", std::process::id()));
+        extract(&reader, &dir).unwrap();
+        let mut streams = vec![
+            fs::read(dir.join("a_b")).unwrap(),
+            fs::read(dir.join("a_b~1")).unwrap(),
+            fs::read(dir.join("c_/s")).unwrap(),
+            fs::read(dir.join("c_~1/s")).unwrap(),
+        ];
+        streams.sort();
+        let expected: [&[u8]; 4] = [b"control", b"pipe", b"question", b"star"];
+        assert_eq!(streams, expected);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
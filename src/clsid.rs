@@ -0,0 +1,268 @@
+//             DO WHAT THE FUCK YOU WANT TO PUBLIC LICENSE
+//                    Version 2, December 2004
+//
+// Copyright (C) 2018 Thomas Bailleux <thomas@bailleux.me>
+//
+// Everyone is permitted to copy and distribute verbatim or modified
+// copies of this license document, and changing it is allowed as long
+// as the name is changed.
+//
+//            DO WHAT THE FUCK YOU WANT TO PUBLIC LICENSE
+//   TERMS AND CONDITIONS FOR COPYING, DISTRIBUTION AND MODIFICATION
+//
+//  0. You just DO WHAT THE FUCK YOU WANT TO.
+//
+// Author: zadig <thomas chr(0x40) bailleux.me>
+
+use std::{fmt, str::FromStr};
+
+use super::error::Error;
+
+/// A class identifier, i.e. a GUID stored in its binary form: the first
+/// three fields are little-endian, the last eight bytes are stored as is.
+///
+/// The CLSID of the root entry usually identifies the application that
+/// produced the file.
+///
+/// # Examples
+///
+/// ```
+/// use ole::Clsid;
+///
+/// let clsid: Clsid = "{00020906-0000-0000-C000-000000000046}".parse().unwrap();
+/// assert_eq!(clsid, Clsid::WORD_DOCUMENT);
+/// assert_eq!(clsid.to_string(), "00020906-0000-0000-C000-000000000046");
+/// assert_eq!(clsid.application(), Some("Microsoft Word 97-2003 Document"));
+/// ```
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Clsid([u8; 16]);
+
+impl Clsid {
+    /// The nil CLSID, used by entries which have no class.
+    pub const NIL: Clsid = Clsid([0u8; 16]);
+
+    /// Word 6.0 - 95 document.
+    pub const WORD_6_DOCUMENT: Clsid =
+        Clsid::from_fields(0x0002_0900, 0, 0, [0xC0, 0, 0, 0, 0, 0, 0, 0x46]);
+    /// Word 97 - 2003 document.
+    pub const WORD_DOCUMENT: Clsid =
+        Clsid::from_fields(0x0002_0906, 0, 0, [0xC0, 0, 0, 0, 0, 0, 0, 0x46]);
+    /// Excel 5.0 - 95 workbook.
+    pub const EXCEL_5_WORKBOOK: Clsid =
+        Clsid::from_fields(0x0002_0810, 0, 0, [0xC0, 0, 0, 0, 0, 0, 0, 0x46]);
+    /// Excel 97 - 2003 workbook.
+    pub const EXCEL_WORKBOOK: Clsid =
+        Clsid::from_fields(0x0002_0820, 0, 0, [0xC0, 0, 0, 0, 0, 0, 0, 0x46]);
+    /// Excel 97 - 2003 chart.
+    pub const EXCEL_CHART: Clsid =
+        Clsid::from_fields(0x0002_0821, 0, 0, [0xC0, 0, 0, 0, 0, 0, 0, 0x46]);
+    /// PowerPoint 95 presentation.
+    pub const POWERPOINT_95_PRESENTATION: Clsid = Clsid::from_fields(
+        0xEA7B_AE70,
+        0xFB3B,
+        0x11CD,
+        [0xA9, 0x03, 0x00, 0xAA, 0x00, 0x51, 0x0E, 0xA3],
+    );
+    /// PowerPoint 97 - 2003 presentation.
+    pub const POWERPOINT_PRESENTATION: Clsid = Clsid::from_fields(
+        0x6481_8D10,
+        0x4F9B,
+        0x11CF,
+        [0x86, 0xEA, 0x00, 0xAA, 0x00, 0xB9, 0x29, 0xE8],
+    );
+    /// Windows Installer package (`.msi`).
+    pub const MSI_PACKAGE: Clsid =
+        Clsid::from_fields(0x000C_1084, 0, 0, [0xC0, 0, 0, 0, 0, 0, 0, 0x46]);
+    /// Windows Installer transform (`.mst`).
+    pub const MSI_TRANSFORM: Clsid =
+        Clsid::from_fields(0x000C_1082, 0, 0, [0xC0, 0, 0, 0, 0, 0, 0, 0x46]);
+    /// Windows Installer patch (`.msp`).
+    pub const MSI_PATCH: Clsid =
+        Clsid::from_fields(0x000C_1086, 0, 0, [0xC0, 0, 0, 0, 0, 0, 0, 0x46]);
+    /// Visio 2003 - 2010 drawing.
+    pub const VISIO_DRAWING: Clsid =
+        Clsid::from_fields(0x0002_1A14, 0, 0, [0xC0, 0, 0, 0, 0, 0, 0, 0x46]);
+    /// Equation Editor 3.0 object.
+    pub const EQUATION_3: Clsid =
+        Clsid::from_fields(0x0002_CE02, 0, 0, [0xC0, 0, 0, 0, 0, 0, 0, 0x46]);
+    /// OLE package, embedding an arbitrary file.
+    pub const OLE_PACKAGE: Clsid =
+        Clsid::from_fields(0x0003_000C, 0, 0, [0xC0, 0, 0, 0, 0, 0, 0, 0x46]);
+
+    /// Constructs a `Clsid` from its binary form.
+    pub const fn from_bytes(bytes: [u8; 16]) -> Clsid {
+        Clsid(bytes)
+    }
+
+    /// Constructs a `Clsid` from the fields of the GUID, as written in
+    /// `{data1-data2-data3-data4}`.
+    pub const fn from_fields(data1: u32, data2: u16, data3: u16, data4: [u8; 8]) -> Clsid {
+        let d1 = data1.to_le_bytes();
+        let d2 = data2.to_le_bytes();
+        let d3 = data3.to_le_bytes();
+        Clsid([
+            d1[0], d1[1], d1[2], d1[3], d2[0], d2[1], d3[0], d3[1], data4[0], data4[1], data4[2],
+            data4[3], data4[4], data4[5], data4[6], data4[7],
+        ])
+    }
+
+    /// Returns the binary form of the CLSID.
+    pub fn as_bytes(&self) -> &[u8; 16] {
+        &self.0
+    }
+
+    /// Returns whether this is the nil CLSID.
+    pub fn is_nil(&self) -> bool {
+        self.0 == [0u8; 16]
+    }
+
+    /// Returns a description of the class, if it is a well-known one.
+    pub fn application(&self) -> Option<&'static str> {
+        KNOWN_CLSIDS
+            .iter()
+            .find(|(clsid, _)| clsid == self)
+            .map(|&(_, name)| name)
+    }
+}
+
+/// Well-known CLSIDs, with a description.
+const KNOWN_CLSIDS: &[(Clsid, &str)] = &[
+    (Clsid::WORD_6_DOCUMENT, "Microsoft Word 6.0-95 Document"),
+    (Clsid::WORD_DOCUMENT, "Microsoft Word 97-2003 Document"),
+    (Clsid::EXCEL_5_WORKBOOK, "Microsoft Excel 5.0-95 Workbook"),
+    (Clsid::EXCEL_WORKBOOK, "Microsoft Excel 97-2003 Workbook"),
+    (Clsid::EXCEL_CHART, "Microsoft Excel 97-2003 Chart"),
+    (
+        Clsid::POWERPOINT_95_PRESENTATION,
+        "Microsoft PowerPoint 95 Presentation",
+    ),
+    (
+        Clsid::POWERPOINT_PRESENTATION,
+        "Microsoft PowerPoint 97-2003 Presentation",
+    ),
+    (Clsid::MSI_PACKAGE, "Windows Installer Package"),
+    (Clsid::MSI_TRANSFORM, "Windows Installer Transform"),
+    (Clsid::MSI_PATCH, "Windows Installer Patch"),
+    (Clsid::VISIO_DRAWING, "Microsoft Visio Drawing"),
+    (Clsid::EQUATION_3, "Microsoft Equation 3.0"),
+    (Clsid::OLE_PACKAGE, "OLE Package"),
+];
+
+impl From<[u8; 16]> for Clsid {
+    fn from(bytes: [u8; 16]) -> Clsid {
+        Clsid(bytes)
+    }
+}
+
+/// Formats the CLSID as `XXXXXXXX-XXXX-XXXX-XXXX-XXXXXXXXXXXX`.
+impl fmt::Display for Clsid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let b = &self.0;
+        write!(
+            f,
+            "{:08X}-{:04X}-{:04X}-{:02X}{:02X}-",
+            u32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            u16::from_le_bytes([b[4], b[5]]),
+            u16::from_le_bytes([b[6], b[7]]),
+            b[8],
+            b[9]
+        )?;
+        b[10..].iter().try_for_each(|byte| write!(f, "{byte:02X}"))
+    }
+}
+
+impl fmt::Debug for Clsid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Clsid({self})")
+    }
+}
+
+/// Parses `XXXXXXXX-XXXX-XXXX-XXXX-XXXXXXXXXXXX`, optionally surrounded by
+/// braces.
+impl FromStr for Clsid {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Clsid, Error> {
+        let invalid = || Error::InvalidClsid(s.to_string());
+        let guid = s
+            .strip_prefix('{')
+            .and_then(|g| g.strip_suffix('}'))
+            .unwrap_or(s);
+        let groups: Vec<&str> = guid.split('-').collect();
+        let lengths: Vec<usize> = groups.iter().map(|g| g.len()).collect();
+        if lengths != [8, 4, 4, 4, 12] || !guid.chars().all(|c| c == '-' || c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+
+        let hex = groups.concat();
+        let byte = |i: usize| u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).map_err(|_| invalid());
+        let mut data4 = [0u8; 8];
+        for (i, b) in data4.iter_mut().enumerate() {
+            *b = byte(8 + i)?;
+        }
+        Ok(Clsid::from_fields(
+            u32::from_str_radix(groups[0], 16).map_err(|_| invalid())?,
+            u16::from_str_radix(groups[1], 16).map_err(|_| invalid())?,
+            u16::from_str_radix(groups[2], 16).map_err(|_| invalid())?,
+            data4,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Clsid;
+
+    #[test]
+    fn format_and_parse() {
+        let clsid = Clsid::POWERPOINT_PRESENTATION;
+        assert_eq!(clsid.to_string(), "64818D10-4F9B-11CF-86EA-00AA00B929E8");
+        assert_eq!(
+            clsid.as_bytes(),
+            &[
+                0x10, 0x8D, 0x81, 0x64, 0x9B, 0x4F, 0xCF, 0x11, 0x86, 0xEA, 0x00, 0xAA, 0x00, 0xB9,
+                0x29, 0xE8
+            ]
+        );
+        assert_eq!(
+            "64818d10-4f9b-11cf-86ea-00aa00b929e8"
+                .parse::<Clsid>()
+                .unwrap(),
+            clsid
+        );
+        assert_eq!(
+            "{64818D10-4F9B-11CF-86EA-00AA00B929E8}"
+                .parse::<Clsid>()
+                .unwrap(),
+            clsid
+        );
+        assert_eq!(
+            format!("{:?}", Clsid::NIL),
+            "Clsid(00000000-0000-0000-0000-000000000000)"
+        );
+        for bad in [
+            "",
+            "64818D10-4F9B-11CF-86EA",
+            "64818D104F9B11CF86EA00AA00B929E8",
+            "{64818D10-4F9B-11CF-86EA-00AA00B929E8",
+            "64818D10-4F9B-11CF-86EA-00AA00B929EG",
+            "+4818D10-4F9B-11CF-86EA-00AA00B929E8",
+        ] {
+            assert!(bad.parse::<Clsid>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn well_known() {
+        assert!(Clsid::NIL.is_nil());
+        assert_eq!(Clsid::NIL.application(), None);
+        assert_eq!(
+            Clsid::MSI_PACKAGE.to_string(),
+            "000C1084-0000-0000-C000-000000000046"
+        );
+        assert_eq!(
+            Clsid::MSI_PACKAGE.application(),
+            Some("Windows Installer Package")
+        );
+    }
+}
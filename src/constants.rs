@@ -22,14 +22,24 @@ pub(crate) const UID_SIZE: usize = 16;
 pub(crate) const LITTLE_ENDIAN_IDENTIFIER: [u8; 2] = [0xFE, 0xFF];
 pub(crate) const BIG_ENDIAN_IDENTIFIER: [u8; 2] = [0xFF, 0xFE];
 
-pub(crate) const FREE_SECID_ARRAY_U8: [u8; 4] = [0xFF, 0xFF, 0xFF, 0xFF];
 pub(crate) const SECID_FREE_SECTOR: u32 = 0xFFFF_FFFF;
 pub(crate) const SECID_END_OF_CHAIN: u32 = 0xFFFF_FFFE;
 pub(crate) const SECID_FAT_SECTOR: u32 = 0xFFFF_FFFD;
 pub(crate) const SECID_MAX: u32 = 0xFFFF_FFF9;
 pub const SECID_DIFAT_SECTOR: u32 = 0xFFFF_FFFC;
 
+pub(crate) const NO_STREAM: u32 = 0xFFFF_FFFF;
+
+pub(crate) const MINOR_VERSION: u16 = 0x003E;
+pub(crate) const DIFAT_IN_HEADER: usize = 109;
+pub(crate) const MINI_SECTOR_SHIFT: u16 = 6;
+pub(crate) const MINI_STREAM_CUTOFF: usize = 4096;
+
 pub(crate) const U32_SIZE: usize = std::mem::size_of::<u32>();
 pub(crate) const U16_SIZE: usize = std::mem::size_of::<u16>();
 
 pub(crate) const DIRECTORY_ENTRY_SIZE: usize = 128;
+
+/// Size of the buffer of `EntrySlice::fill_buf`, when the file isn't in
+/// memory.
+pub(crate) const SLICE_BUFFER_SIZE: usize = 64 * 1024;
@@ -0,0 +1,786 @@
+//             DO WHAT THE FUCK YOU WANT TO PUBLIC LICENSE
+//                    Version 2, December 2004
+//
+// Copyright (C) 2018 Thomas Bailleux <thomas@bailleux.me>
+//
+// Everyone is permitted to copy and distribute verbatim or modified
+// copies of this license document, and changing it is allowed as long
+// as the name is changed.
+//
+//            DO WHAT THE FUCK YOU WANT TO PUBLIC LICENSE
+//   TERMS AND CONDITIONS FOR COPYING, DISTRIBUTION AND MODIFICATION
+//
+//  0. You just DO WHAT THE FUCK YOU WANT TO.
+//
+// Author: zadig <thomas chr(0x40) bailleux.me>
+
+use std::{collections::BTreeSet, io::Write, vec::Vec};
+
+use super::{
+    constants,
+    entry::{build_sibling_tree, check_name, compare_names, Entry, EntryType},
+    error::Error,
+    ole::Reader,
+    path::{find_child, find_path},
+    util::{ByteOrder, FromSlice},
+};
+
+/// An OLE file editor.
+///
+/// The editor modifies an existing file in place: only the sectors holding
+/// modified streams, the directory entries which changed and the
+/// allocation tables are rewritten. Every other byte of the original file,
+/// including CLSIDs, state bits and timestamps, is kept as is.
+///
+/// Freed sectors are reused before the file grows, and streams move
+/// between the mini stream and regular sectors when their size crosses the
+/// mini stream cutoff.
+///
+/// # Basic Example
+///
+/// ```
+/// use ole::{Editor, Reader};
+///
+/// let reader = Reader::from_path("assets/sample.doc").unwrap();
+/// let mut editor = Editor::from_reader(reader).unwrap();
+/// editor.write_stream("WordDocument", b"Hello").unwrap();
+/// editor.create_stream("Notes", b"Edited").unwrap();
+///
+/// let mut buf = Vec::new();
+/// editor.write_to(&mut buf).unwrap();
+/// ```
+pub struct Editor {
+    /// The whole file, header included.
+    body: Vec<u8>,
+
+    /// Size of one sector.
+    sec_size: usize,
+
+    /// Size of one short sector.
+    short_sec_size: usize,
+
+    /// Minimum size of a standard stream size.
+    minimum_standard_stream_size: u64,
+
+    /// Sector Allocation Table.
+    sat: Vec<u32>,
+
+    /// SecIDs of the sectors holding the SAT.
+    sat_sectors: Vec<u32>,
+
+    /// SecIDs of the sectors holding the DIFAT.
+    difat_sectors: Vec<u32>,
+
+    /// Short Sector Allocation Table.
+    ssat: Vec<u32>,
+
+    /// SecIDs of the sectors holding the SSAT.
+    ssat_sectors: Vec<u32>,
+
+    /// Directory Sector Allocation Table.
+    dir_sat: Vec<u32>,
+
+    /// Directory entries.
+    entries: Vec<Entry>,
+
+    /// DirIDs of the entries which have to be written back.
+    dirty_entries: BTreeSet<u32>,
+}
+
+impl Editor {
+    /// Constructs a new `Editor` from a parsed file.
+    ///
+    /// Big-endian files can't be edited yet: `Error::NotImplementedYet` is
+    /// returned for them.
+    pub fn from_reader(reader: Reader) -> Result<Editor, Error> {
+        let mut reader = reader;
+        if reader.byte_order != ByteOrder::LittleEndian {
+            return Err(Error::NotImplementedYet);
+        }
+        let entries = reader.entries.take().ok_or(Error::InvalidOLEFile)?;
+        let sec_size = reader.sec_size;
+
+        let ssat_sectors = std::mem::take(&mut reader.ssat_sectors);
+        let difat_sectors = std::mem::take(&mut reader.difat_sectors);
+
+        Ok(Editor {
+            body: reader.source.into_vec()?,
+            sec_size,
+            short_sec_size: reader.short_sec_size,
+            minimum_standard_stream_size: reader.minimum_standard_stream_size,
+            sat: reader.sat,
+            sat_sectors: reader.main_sat,
+            difat_sectors,
+            ssat: reader.ssat,
+            ssat_sectors,
+            dir_sat: reader.dir_sat,
+            entries,
+            dirty_entries: BTreeSet::new(),
+        })
+    }
+
+    /// Constructs a new `Editor` from a file.
+    pub fn from_path(path: &str) -> Result<Editor, Error> {
+        Editor::from_reader(Reader::from_path(path)?)
+    }
+
+    /// Returns an iterator over the directory entries.
+    pub fn iterate(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.iter()
+    }
+
+    /// Returns the content of the stream at `path`.
+    pub fn read_stream(&self, path: &str) -> Result<Vec<u8>, Error> {
+        let id = self.stream_id(path)?;
+        let entry = &self.entries[id as usize];
+        // The whole file is in memory, so is the stream.
+        let size =
+            usize::try_from(entry.size).map_err(|_| Error::BadSizeValue("Stream is too large"))?;
+        let mut data = Vec::with_capacity(size);
+        for &sector_id in &entry.sec_id_chain {
+            let offset = if entry.size < self.minimum_standard_stream_size {
+                self.short_sector_offset(sector_id)?
+            } else {
+                self.sector_offset(sector_id)
+            };
+            let chunk_size = std::cmp::min(self.chunk_size(entry.size), size - data.len());
+            let chunk = self
+                .body
+                .get(offset..offset + chunk_size)
+                .ok_or(Error::BadSizeValue("File is too short"))?;
+            data.extend_from_slice(chunk);
+        }
+        Ok(data)
+    }
+
+    /// Replaces the content of the stream at `path`.
+    pub fn write_stream(&mut self, path: &str, data: &[u8]) -> Result<(), Error> {
+        let id = self.stream_id(path)?;
+        self.set_stream_data(id, data)
+    }
+
+    /// Appends `data` at the end of the stream at `path`.
+    pub fn append_stream(&mut self, path: &str, data: &[u8]) -> Result<(), Error> {
+        let mut content = self.read_stream(path)?;
+        content.extend_from_slice(data);
+        self.write_stream(path, &content)
+    }
+
+    /// Truncates the stream at `path` to `len` bytes. Does nothing if the
+    /// stream is already shorter.
+    pub fn truncate_stream(&mut self, path: &str, len: usize) -> Result<(), Error> {
+        let mut content = self.read_stream(path)?;
+        if len < content.len() {
+            content.truncate(len);
+            self.write_stream(path, &content)?;
+        }
+        Ok(())
+    }
+
+    /// Creates a stream holding `data`. Its parent storage must already
+    /// exist.
+    pub fn create_stream(&mut self, path: &str, data: &[u8]) -> Result<(), Error> {
+        let id = self.create_entry(path, EntryType::UserStream)?;
+        self.set_stream_data(id, data)
+    }
+
+    /// Creates an empty storage. Its parent storage must already exist.
+    pub fn create_storage(&mut self, path: &str) -> Result<(), Error> {
+        let id = self.create_entry(path, EntryType::UserStorage)?;
+        // Storages have no data, their start sector must be zero.
+        self.entries[id as usize].sec_id_chain.push(0);
+        Ok(())
+    }
+
+    /// Removes the stream or the storage at `path`, with all its children.
+    pub fn remove(&mut self, path: &str) -> Result<(), Error> {
+        let id = find_path(&self.entries, 0, path)?;
+        let parent = self.entries[id as usize]
+            .parent_node
+            .ok_or_else(|| Error::InvalidEntryName(path.to_string()))?;
+
+        let mut to_remove = vec![id];
+        while let Some(id) = to_remove.pop() {
+            let entry = &self.entries[id as usize];
+            to_remove.extend_from_slice(&entry.children_nodes);
+            if entry.entry_type == EntryType::UserStream {
+                self.free_stream(id);
+            }
+            self.entries[id as usize] = Entry::new(id, "", EntryType::Empty);
+            self.dirty_entries.insert(id);
+        }
+
+        self.entries[parent as usize]
+            .children_nodes
+            .retain(|&c| c != id);
+        self.link_children(parent);
+        Ok(())
+    }
+
+    /// Writes the modified file into `path`.
+    pub fn save(&mut self, path: &str) -> Result<(), Error> {
+        let file = std::fs::File::create(path)?;
+        self.write_to(std::io::BufWriter::new(file))
+    }
+
+    /// Updates the allocation tables and the directory, and writes the
+    /// modified file.
+    pub fn write_to<W: Write>(&mut self, mut out: W) -> Result<(), Error> {
+        self.flush();
+        out.write_all(&self.body)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    fn stream_id(&self, path: &str) -> Result<u32, Error> {
+        let id = find_path(&self.entries, 0, path)?;
+        if self.entries[id as usize].entry_type != EntryType::UserStream {
+            return Err(Error::NotAStream(path.to_string()));
+        }
+        Ok(id)
+    }
+
+    fn create_entry(&mut self, path: &str, entry_type: EntryType) -> Result<u32, Error> {
+        let (parent_path, name) = match path.trim_end_matches('/').rsplit_once('/') {
+            Some((parent_path, name)) => (parent_path, name),
+            None => ("", path),
+        };
+        check_name(name)?;
+
+        let parent = find_path(&self.entries, 0, parent_path)?;
+        let parent_type = self.entries[parent as usize].entry_type;
+        if parent_type != EntryType::RootStorage && parent_type != EntryType::UserStorage {
+            return Err(Error::NotAStorage(parent_path.to_string()));
+        }
+        if find_child(&self.entries, parent, name).is_some() {
+            return Err(Error::EntryAlreadyExists(path.to_string()));
+        }
+
+        // Reuse the first empty directory entry, if any.
+        let id = match self
+            .entries
+            .iter()
+            .skip(1)
+            .position(|e| e.entry_type == EntryType::Empty)
+        {
+            Some(position) => position as u32 + 1,
+            None => {
+                self.entries.push(Entry::new(0, "", EntryType::Empty));
+                self.entries.len() as u32 - 1
+            }
+        };
+        let mut entry = Entry::new(id, name, entry_type);
+        entry.parent_node = Some(parent);
+        self.entries[id as usize] = entry;
+        self.entries[parent as usize].children_nodes.push(id);
+        self.link_children(parent);
+        Ok(id)
+    }
+
+    /// Rebuilds the red-black tree holding the children of `parent`.
+    fn link_children(&mut self, parent: u32) {
+        let mut children = self.entries[parent as usize].children_nodes.clone();
+        children.sort_by(|&a, &b| {
+            compare_names(
+                &self.entries[a as usize].name,
+                &self.entries[b as usize].name,
+            )
+        });
+        self.entries[parent as usize].root_node = build_sibling_tree(&mut self.entries, &children);
+        self.dirty_entries.insert(parent);
+        self.dirty_entries.extend(children);
+    }
+
+    fn set_stream_data(&mut self, id: u32, data: &[u8]) -> Result<(), Error> {
+        self.free_stream(id);
+
+        let chain = if data.is_empty() {
+            Vec::new()
+        } else if (data.len() as u64) < self.minimum_standard_stream_size {
+            let chain = allocate(&mut self.ssat, data.len().div_ceil(self.short_sec_size));
+            self.grow_mini_stream(&chain)?;
+            for (&sector_id, chunk) in chain.iter().zip(data.chunks(self.short_sec_size)) {
+                let offset = self.short_sector_offset(sector_id)?;
+                self.write_chunk(offset, chunk, self.short_sec_size);
+            }
+            chain
+        } else {
+            let chain = allocate(&mut self.sat, data.len().div_ceil(self.sec_size));
+            for (&sector_id, chunk) in chain.iter().zip(data.chunks(self.sec_size)) {
+                let offset = self.sector_offset(sector_id);
+                self.write_chunk(offset, chunk, self.sec_size);
+            }
+            chain
+        };
+
+        let entry = &mut self.entries[id as usize];
+        entry.size = data.len() as u64;
+        entry.sec_id_chain = chain;
+        self.dirty_entries.insert(id);
+        Ok(())
+    }
+
+    /// Releases the sectors of the stream `id`, and zeroes them so that the
+    /// removed data doesn't stay in the file.
+    fn free_stream(&mut self, id: u32) {
+        let size = self.entries[id as usize].size;
+        let short = size < self.minimum_standard_stream_size;
+        let sector_size = self.chunk_size(size);
+        let entry = &mut self.entries[id as usize];
+        let chain = std::mem::take(&mut entry.sec_id_chain);
+        entry.size = 0;
+        for sector_id in chain {
+            let offset = if short {
+                self.short_sector_offset(sector_id).ok()
+            } else {
+                Some(self.sector_offset(sector_id))
+            };
+            if let Some(sector) =
+                offset.and_then(|offset| self.body.get_mut(offset..offset + sector_size))
+            {
+                sector.fill(0);
+            }
+            let table = if short { &mut self.ssat } else { &mut self.sat };
+            if let Some(next) = table.get_mut(sector_id as usize) {
+                *next = constants::SECID_FREE_SECTOR;
+            }
+        }
+    }
+
+    /// Makes the mini stream large enough to hold the short sectors of
+    /// `chain`.
+    fn grow_mini_stream(&mut self, chain: &[u32]) -> Result<(), Error> {
+        let end = match chain.iter().max() {
+            Some(&last) => (last as usize + 1) * self.short_sec_size,
+            None => return Ok(()),
+        };
+        let root = &self.entries[0];
+        if end as u64 <= root.size {
+            return Ok(());
+        }
+
+        let mut root_chain = root.sec_id_chain.clone();
+        extend_chain(&mut self.sat, &mut root_chain, end.div_ceil(self.sec_size));
+        let root = &mut self.entries[0];
+        root.sec_id_chain = root_chain;
+        root.size = end as u64;
+        self.dirty_entries.insert(0);
+        Ok(())
+    }
+
+    /// Writes `chunk` at `offset`, and zeroes the rest of the sector.
+    fn write_chunk(&mut self, offset: usize, chunk: &[u8], sector_size: usize) {
+        let end = offset + sector_size;
+        if self.body.len() < end {
+            self.body.resize(end, 0);
+        }
+        self.body[offset..offset + chunk.len()].copy_from_slice(chunk);
+        self.body[offset + chunk.len()..end].fill(0);
+    }
+
+    fn sector_offset(&self, sector_id: u32) -> usize {
+        self.sec_size + self.sec_size * sector_id as usize
+    }
+
+    fn short_sector_offset(&self, short_sector_id: u32) -> Result<usize, Error> {
+        let position = short_sector_id as usize * self.short_sec_size;
+        let sector_id = self.entries[0]
+            .sec_id_chain
+            .get(position / self.sec_size)
+            .ok_or(Error::BadSizeValue("Mini stream is too short"))?;
+        Ok(self.sector_offset(*sector_id) + position % self.sec_size)
+    }
+
+    fn chunk_size(&self, size: u64) -> usize {
+        if size < self.minimum_standard_stream_size {
+            self.short_sec_size
+        } else {
+            self.sec_size
+        }
+    }
+
+    /// Writes the directory, the SSAT, the SAT and the header into `body`.
+    fn flush(&mut self) {
+        let ids_per_sector = self.sec_size / constants::U32_SIZE;
+        let entries_per_sector = self.sec_size / constants::DIRECTORY_ENTRY_SIZE;
+
+        // Directory
+        let dir_sectors = self.entries.len().div_ceil(entries_per_sector);
+        extend_chain(&mut self.sat, &mut self.dir_sat, dir_sectors);
+        while self.entries.len() < self.dir_sat.len() * entries_per_sector {
+            let id = self.entries.len() as u32;
+            self.entries.push(Entry::new(id, "", EntryType::Empty));
+            self.dirty_entries.insert(id);
+        }
+        for id in std::mem::take(&mut self.dirty_entries) {
+            let position = id as usize * constants::DIRECTORY_ENTRY_SIZE;
+            let offset = self.sector_offset(self.dir_sat[position / self.sec_size])
+                + position % self.sec_size;
+            self.ensure_sector(offset);
+            self.entries[id as usize]
+                .write_to(&mut self.body[offset..offset + constants::DIRECTORY_ENTRY_SIZE]);
+        }
+
+        // SSAT
+        let ssat_sectors = self.ssat.len().div_ceil(ids_per_sector);
+        extend_chain(&mut self.sat, &mut self.ssat_sectors, ssat_sectors);
+        self.ssat.resize(
+            self.ssat_sectors.len() * ids_per_sector,
+            constants::SECID_FREE_SECTOR,
+        );
+        write_table(
+            &mut self.body,
+            self.sec_size,
+            &self.ssat_sectors,
+            &self.ssat,
+        );
+
+        // SAT and DIFAT: adding a sector to hold them may require another
+        // one, so loop until everything fits.
+        loop {
+            let sat_sectors = self.sat.len().div_ceil(ids_per_sector);
+            let difat_sectors = sat_sectors
+                .saturating_sub(constants::DIFAT_IN_HEADER)
+                .div_ceil(ids_per_sector - 1);
+            if self.sat_sectors.len() < sat_sectors {
+                let sector_id = allocate(&mut self.sat, 1)[0];
+                self.sat[sector_id as usize] = constants::SECID_FAT_SECTOR;
+                self.sat_sectors.push(sector_id);
+            } else if self.difat_sectors.len() < difat_sectors {
+                let sector_id = allocate(&mut self.sat, 1)[0];
+                self.sat[sector_id as usize] = constants::SECID_DIFAT_SECTOR;
+                self.difat_sectors.push(sector_id);
+            } else {
+                break;
+            }
+        }
+        self.sat.resize(
+            self.sat_sectors.len() * ids_per_sector,
+            constants::SECID_FREE_SECTOR,
+        );
+        write_table(&mut self.body, self.sec_size, &self.sat_sectors, &self.sat);
+
+        let extra_sat_sectors = self
+            .sat_sectors
+            .get(constants::DIFAT_IN_HEADER..)
+            .unwrap_or_default();
+        let mut difat = Vec::with_capacity(self.difat_sectors.len() * ids_per_sector);
+        for (k, chunk) in extra_sat_sectors.chunks(ids_per_sector - 1).enumerate() {
+            difat.extend_from_slice(chunk);
+            difat.resize((k + 1) * ids_per_sector - 1, constants::SECID_FREE_SECTOR);
+            difat.push(
+                self.difat_sectors
+                    .get(k + 1)
+                    .copied()
+                    .unwrap_or(constants::SECID_END_OF_CHAIN),
+            );
+        }
+        write_table(&mut self.body, self.sec_size, &self.difat_sectors, &difat);
+
+        // Header
+        let first = |chain: &[u32]| {
+            chain
+                .first()
+                .copied()
+                .unwrap_or(constants::SECID_END_OF_CHAIN)
+        };
+        let header = &mut self.body[..constants::HEADER_SIZE];
+        if u16::from_slice(&header[26..28]) == 4 {
+            header[40..44].copy_from_slice(&(self.dir_sat.len() as u32).to_le_bytes());
+        }
+        header[44..48].copy_from_slice(&(self.sat_sectors.len() as u32).to_le_bytes());
+        header[48..52].copy_from_slice(&first(&self.dir_sat).to_le_bytes());
+        header[60..64].copy_from_slice(&first(&self.ssat_sectors).to_le_bytes());
+        header[64..68].copy_from_slice(&(self.ssat_sectors.len() as u32).to_le_bytes());
+        header[68..72].copy_from_slice(&first(&self.difat_sectors).to_le_bytes());
+        header[72..76].copy_from_slice(&(self.difat_sectors.len() as u32).to_le_bytes());
+        for (k, dst) in header[76..]
+            .chunks_exact_mut(constants::U32_SIZE)
+            .enumerate()
+        {
+            let sector_id = self
+                .sat_sectors
+                .get(k)
+                .copied()
+                .unwrap_or(constants::SECID_FREE_SECTOR);
+            dst.copy_from_slice(&sector_id.to_le_bytes());
+        }
+    }
+
+    /// Grows `body` so that the sector containing `offset` exists.
+    fn ensure_sector(&mut self, offset: usize) {
+        let end = (offset / self.sec_size + 1) * self.sec_size;
+        if self.body.len() < end {
+            self.body.resize(end, 0);
+        }
+    }
+}
+
+/// Writes `table` into the sectors `sector_ids` of `body`.
+fn write_table(body: &mut Vec<u8>, sec_size: usize, sector_ids: &[u32], table: &[u32]) {
+    for (&sector_id, ids) in sector_ids
+        .iter()
+        .zip(table.chunks(sec_size / constants::U32_SIZE))
+    {
+        let offset = sec_size + sec_size * sector_id as usize;
+        if body.len() < offset + sec_size {
+            body.resize(offset + sec_size, 0);
+        }
+        for (dst, id) in body[offset..offset + sec_size]
+            .chunks_exact_mut(constants::U32_SIZE)
+            .zip(ids)
+        {
+            dst.copy_from_slice(&id.to_le_bytes());
+        }
+    }
+}
+
+/// Allocates a chain of `n` sectors in `table`, reusing free sectors first.
+fn allocate(table: &mut Vec<u32>, n: usize) -> Vec<u32> {
+    let mut chain = Vec::with_capacity(n);
+    let mut candidate = 0usize;
+    while chain.len() < n {
+        if candidate == table.len() {
+            table.push(constants::SECID_FREE_SECTOR);
+        }
+        if table[candidate] == constants::SECID_FREE_SECTOR {
+            table[candidate] = constants::SECID_END_OF_CHAIN;
+            chain.push(candidate as u32);
+        }
+        candidate += 1;
+    }
+    for link in chain.windows(2) {
+        table[link[0] as usize] = link[1];
+    }
+    chain
+}
+
+/// Extends `chain` to `n` sectors, allocating them in `table`.
+fn extend_chain(table: &mut Vec<u32>, chain: &mut Vec<u32>, n: usize) {
+    if chain.len() >= n {
+        return;
+    }
+    let extra = allocate(table, n - chain.len());
+    if let Some(&last) = chain.last() {
+        table[last as usize] = extra[0];
+    }
+    chain.extend(extra);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Editor;
+    use crate::{Clsid, EntryType, Error, Reader, Version, Writer};
+
+    fn edit(buf: &[u8]) -> Editor {
+        Editor::from_reader(Reader::new(buf).unwrap()).unwrap()
+    }
+
+    fn save(editor: &mut Editor) -> Vec<u8> {
+        let mut buf = Vec::new();
+        editor.write_to(&mut buf).unwrap();
+        buf
+    }
+
+    fn sample() -> Vec<u8> {
+        let mut writer = Writer::new(Version::V3);
+        writer.create_storage("Storage").unwrap();
+        writer.create_stream("Storage/Small", &[1u8; 100]).unwrap();
+        writer.create_stream("Big", &[2u8; 10_000]).unwrap();
+        writer.create_stream("Other", &[3u8; 5_000]).unwrap();
+        writer
+            .set_clsid("Storage", Clsid::from_bytes([7u8; 16]))
+            .unwrap();
+        writer.set_times("Storage", 42, 43).unwrap();
+        let mut buf = Vec::new();
+        writer.write_to(&mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn replace_stream() {
+        let original = sample();
+        let mut editor = edit(&original);
+        editor.write_stream("Big", &[4u8; 9_000]).unwrap();
+        editor.write_stream("Storage/Small", &[5u8; 60]).unwrap();
+        let buf = save(&mut editor);
+
+        // The file didn't grow, freed sectors have been reused.
+        assert_eq!(buf.len(), original.len());
+
+        let editor = edit(&buf);
+        assert_eq!(editor.read_stream("Big").unwrap(), vec![4u8; 9_000]);
+        assert_eq!(editor.read_stream("Storage/Small").unwrap(), vec![5u8; 60]);
+        assert_eq!(editor.read_stream("Other").unwrap(), vec![3u8; 5_000]);
+    }
+
+    #[test]
+    fn untouched_data_is_preserved() {
+        let original = sample();
+        let reader = Reader::new(&original[..]).unwrap();
+        let other = reader.iterate().find(|e| e.name() == "Other").unwrap();
+        let other_chain = other.sec_id_chain.clone();
+
+        let mut editor = edit(&original);
+        editor.append_stream("Big", &[9u8; 2_000]).unwrap();
+        let buf = save(&mut editor);
+
+        for sector_id in other_chain {
+            let offset = 512 + 512 * sector_id as usize;
+            assert_eq!(buf[offset..offset + 512], original[offset..offset + 512]);
+        }
+        let reader = Reader::new(&buf[..]).unwrap();
+        let storage = reader.iterate().find(|e| e.name() == "Storage").unwrap();
+        assert_eq!(storage.clsid(), Clsid::from_bytes([7u8; 16]));
+        assert_eq!(storage.creation_time(), 42);
+        assert_eq!(storage.last_modification_time(), 43);
+    }
+
+    #[test]
+    fn migrate_streams() {
+        let mut editor = edit(&sample());
+        editor.truncate_stream("Big", 10).unwrap();
+        editor
+            .append_stream("Storage/Small", &[6u8; 8_000])
+            .unwrap();
+        let buf = save(&mut editor);
+
+        let editor = edit(&buf);
+        assert_eq!(editor.read_stream("Big").unwrap(), vec![2u8; 10]);
+        let mut small = vec![1u8; 100];
+        small.extend_from_slice(&[6u8; 8_000]);
+        assert_eq!(editor.read_stream("Storage/Small").unwrap(), small);
+    }
+
+    #[test]
+    fn create_and_remove() {
+        let mut editor = edit(&sample());
+        editor.remove("Storage").unwrap();
+        editor.create_storage("New").unwrap();
+        for i in 0..20 {
+            editor
+                .create_stream(&format!("New/Stream {i}"), &vec![i as u8; 100 * i])
+                .unwrap();
+        }
+        assert!(matches!(
+            editor.create_stream("new", &[]),
+            Err(Error::EntryAlreadyExists(_))
+        ));
+        assert!(matches!(
+            editor.read_stream("Storage/Small"),
+            Err(Error::EntryNotFound(_))
+        ));
+        let buf = save(&mut editor);
+
+        let reader = Reader::new(&buf[..]).unwrap();
+        assert!(reader.iterate().all(|e| e.name() != "Small"));
+        let new = reader.iterate().find(|e| e.name() == "New").unwrap();
+        assert_eq!(new._type(), EntryType::UserStorage);
+        assert_eq!(new.children_nodes().len(), 20);
+
+        let editor = edit(&buf);
+        for i in 0..20 {
+            assert_eq!(
+                editor.read_stream(&format!("New/Stream {i}")).unwrap(),
+                vec![i as u8; 100 * i]
+            );
+        }
+    }
+
+    #[test]
+    fn removed_entries_are_unused() {
+        let original = sample();
+        let ids: Vec<u32> = {
+            let reader = Reader::new(&original[..]).unwrap();
+            ["Storage", "Storage/Small"]
+                .iter()
+                .map(|path| reader.entry_by_path(path).unwrap().id())
+                .collect()
+        };
+        let mut editor = edit(&original);
+        editor.remove("Storage").unwrap();
+        let buf = save(&mut editor);
+
+        let reader = Reader::new(&buf[..]).unwrap();
+        let mut free = [0u8; 128];
+        free[68..80].fill(0xFF);
+        for id in ids {
+            let offset = reader.entry_offset(id).unwrap();
+            assert_eq!(buf[offset..offset + 128], free);
+        }
+    }
+
+    #[test]
+    fn freed_sectors_are_zeroed() {
+        let mut data = vec![1u8; 200];
+        data[150..156].copy_from_slice(b"SECRET");
+        let mut big = vec![2u8; 5000];
+        big[4000..4006].copy_from_slice(b"SECRET");
+        let mut writer = Writer::new(Version::V3);
+        writer.create_stream("S", &data).unwrap();
+        writer.create_stream("B", &big).unwrap();
+        let mut buf = Vec::new();
+        writer.write_to(&mut buf).unwrap();
+        let contains_secret = |buf: &[u8]| buf.windows(6).any(|w| w == b"SECRET");
+
+        let mut editor = edit(&buf);
+        editor.write_stream("S", &data[..100]).unwrap();
+        editor.write_stream("B", &big[..100]).unwrap();
+        assert!(!contains_secret(&save(&mut editor)));
+
+        let mut editor = edit(&buf);
+        editor.remove("S").unwrap();
+        editor.remove("B").unwrap();
+        assert!(!contains_secret(&save(&mut editor)));
+    }
+
+    #[test]
+    fn grow_allocation_tables() {
+        let mut editor = edit(&sample());
+        let big: Vec<u8> = (0..300_000u32).map(|i| (i % 253) as u8).collect();
+        editor.create_stream("Huge", &big).unwrap();
+        let buf = save(&mut editor);
+
+        let reader = Reader::new(&buf[..]).unwrap();
+        assert!(reader.main_sat.len() > 1);
+        let editor = edit(&buf);
+        assert_eq!(editor.read_stream("Huge").unwrap(), big);
+        assert_eq!(editor.read_stream("Other").unwrap(), vec![3u8; 5_000]);
+    }
+
+    #[test]
+    fn empty_stream_at_sector_0() {
+        let mut writer = Writer::new(Version::V3);
+        writer.create_stream("A", &[1u8; 100]).unwrap();
+        writer.create_stream("E", &[]).unwrap();
+        let mut buf = Vec::new();
+        writer.write_to(&mut buf).unwrap();
+        // `E` starts in mini sector 0, which belongs to `A`.
+        let offset = {
+            let reader = Reader::new(&buf[..]).unwrap();
+            let e = reader.entry_by_path("E").unwrap();
+            assert!(e.sec_id_chain.is_empty());
+            reader.entry_offset(e.id()).unwrap()
+        };
+        buf[offset + 116..offset + 120].copy_from_slice(&0u32.to_le_bytes());
+
+        let mut editor = edit(&buf);
+        editor.write_stream("E", b"hello").unwrap();
+        let buf = save(&mut editor);
+
+        let editor = edit(&buf);
+        assert_eq!(editor.read_stream("A").unwrap(), vec![1u8; 100]);
+        assert_eq!(editor.read_stream("E").unwrap(), b"hello");
+    }
+
+    #[test]
+    fn edit_real_file() {
+        let reader = Reader::from_path("./assets/sample.doc").unwrap();
+        let mut editor = Editor::from_reader(reader).unwrap();
+        editor.write_stream("WordDocument", b"Hello").unwrap();
+        let buf = save(&mut editor);
+
+        let editor = edit(&buf);
+        assert_eq!(editor.read_stream("WordDocument").unwrap(), b"Hello");
+    }
+}
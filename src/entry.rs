@@ -14,10 +14,18 @@
 //
 // Author: zadig <thomas chr(0x40) bailleux.me>
 
-use super::{constants, error::Error, ole::Reader};
-use std::{fmt, string::String, vec::Vec};
-
-#[derive(Debug)]
+use super::{
+    clsid::Clsid,
+    constants,
+    error::Error,
+    filetime::FileTime,
+    ole::Reader,
+    source::BlockSource,
+    util::{filetime_from_slice, ByteOrder, FromSlice},
+};
+use std::{borrow::Cow, cmp::Ordering, fmt, io::IoSliceMut, string::String, vec::Vec};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub(crate) enum NodeColour {
     Red,
     Black,
@@ -31,6 +39,13 @@ impl NodeColour {
             _ => Err(Error::NodeTypeUnknown),
         }
     }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            NodeColour::Red => 0,
+            NodeColour::Black => 1,
+        }
+    }
 }
 
 impl fmt::Display for NodeColour {
@@ -75,6 +90,17 @@ impl EntryType {
             _ => Err(Error::NodeTypeUnknown),
         }
     }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            EntryType::Empty => 0,
+            EntryType::UserStorage => 1,
+            EntryType::UserStream => 2,
+            EntryType::LockBytes => 3,
+            EntryType::Property => 4,
+            EntryType::RootStorage => 5,
+        }
+    }
 }
 
 impl fmt::Display for EntryType {
@@ -108,78 +134,117 @@ impl fmt::Display for EntryType {
 /// println!("Type of the entry: {}", entry._type());
 /// println!("Size of the entry: {}", entry.len());
 /// ```
-
 pub struct Entry {
     /// ID of the entry.
-    id: u32,
+    pub(crate) id: u32,
 
     /// Name of the stream or the storage.
-    name: String,
+    pub(crate) name: String,
 
     /// Type of the entry.
-    entry_type: EntryType,
+    pub(crate) entry_type: EntryType,
 
     /// Color of the entry (see <https://en.wikipedia.org/wiki/Red%E2%80%93black_tree>)
-    color: NodeColour,
+    pub(crate) color: NodeColour,
 
     /// ID of the left child entry.
-    left_child_node: u32,
+    pub(crate) left_child_node: u32,
 
     /// ID of the right child entry.
-    right_child_node: u32,
+    pub(crate) right_child_node: u32,
 
     /// ID of the root node
-    root_node: u32,
+    pub(crate) root_node: u32,
 
     /// UID of the entry.
-    identifier: [u8; 16], // 16 bytes
+    pub(crate) identifier: [u8; 16], // 16 bytes
 
     /// Flags of the entry.
-    flags: u32, // 4 bytes
+    pub(crate) flags: u32, // 4 bytes
 
     /// Creation time.
-    creation_time: u64,
+    pub(crate) creation_time: u64,
 
     /// Last modification time.
-    last_modification_time: u64,
+    pub(crate) last_modification_time: u64,
 
     /// Chain of secID which hold the stream or the storage
-    sec_id_chain: Vec<u32>,
+    pub(crate) sec_id_chain: Vec<u32>,
 
     /// Size of the entry.
-    size: usize,
+    pub(crate) size: u64,
 
     /// Array of the children's DirIDs
-    children_nodes: Vec<u32>,
+    pub(crate) children_nodes: Vec<u32>,
 
     /// DirID of the parent
-    parent_node: Option<u32>,
+    pub(crate) parent_node: Option<u32>,
 }
 
 impl Entry {
-    fn from_slice(sector: &[u8], dir_id: u32) -> Result<Entry, Error> {
-        use crate::util::FromSlice;
+    /// Creates a detached entry, with no siblings, no child and no data.
+    pub(crate) fn new(id: u32, name: &str, entry_type: EntryType) -> Entry {
+        Entry {
+            id,
+            name: name.to_string(),
+            entry_type,
+            color: NodeColour::Black,
+            left_child_node: constants::NO_STREAM,
+            right_child_node: constants::NO_STREAM,
+            root_node: constants::NO_STREAM,
+            identifier: [0u8; 16],
+            flags: 0,
+            creation_time: 0,
+            last_modification_time: 0,
+            sec_id_chain: Vec::new(),
+            size: 0,
+            children_nodes: Vec::new(),
+            parent_node: None,
+        }
+    }
+
+    /// Parses the directory entry `dir_id`, found at `offset` in a file of
+    /// the major version `version`.
+    fn from_slice(
+        sector: &[u8],
+        dir_id: u32,
+        offset: usize,
+        order: ByteOrder,
+        version: u16,
+    ) -> Result<Entry, Error> {
+        let invalid = |reason| Error::InvalidEntry {
+            entry_id: dir_id,
+            offset,
+            reason,
+        };
         let entry = Entry {
             id: dir_id,
             name: {
-                let name_len = u16::from_slice(&sector[64..66]);
-                Entry::build_name(name_len, &sector[0..64])?
+                let name_len = u16::from_slice_with(&sector[64..66], order);
+                Entry::build_name(name_len, &sector[0..64], order)
+                    .map_err(|_| invalid("Invalid name"))?
             },
-            entry_type: EntryType::from(sector[66])?,
-            color: NodeColour::from(sector[67])?,
-            left_child_node: u32::from_slice(&sector[68..72]),
-            right_child_node: u32::from_slice(&sector[72..76]),
-            root_node: u32::from_slice(&sector[76..80]),
-            identifier: {
-                let mut identifier = [0u8; 16];
-                identifier.clone_from_slice(&sector[80..96]);
-                identifier
+            entry_type: EntryType::from(sector[66]).map_err(|_| invalid("Unknown entry type"))?,
+            color: NodeColour::from(sector[67]).map_err(|_| invalid("Unknown node colour"))?,
+            left_child_node: u32::from_slice_with(&sector[68..72], order),
+            right_child_node: u32::from_slice_with(&sector[72..76], order),
+            root_node: u32::from_slice_with(&sector[76..80], order),
+            identifier: *Clsid::from_slice_with(&sector[80..96], order).as_bytes(),
+            flags: u32::from_slice_with(&sector[96..100], order),
+            creation_time: filetime_from_slice(&sector[100..108], order),
+            last_modification_time: filetime_from_slice(&sector[108..116], order),
+            sec_id_chain: vec![u32::from_slice_with(&sector[116..120], order)],
+            size: {
+                let low = u64::from(u32::from_slice_with(&sector[120..124], order));
+                let high = u64::from(u32::from_slice_with(&sector[124..128], order));
+                // The high DWORD of the size must be ignored in version 3
+                // files, some writers leave garbage in it.
+                if version == 3 {
+                    low
+                } else {
+                    high << 32 | low
+                }
             },
-            flags: u32::from_slice(&sector[96..100]),
-            creation_time: u64::from_slice(&sector[100..108]),
-            last_modification_time: u64::from_slice(&sector[108..116]),
-            sec_id_chain: vec![u32::from_slice(&sector[116..120])],
-            size: usize::from_slice(&sector[120..124]),
             children_nodes: Vec::new(),
             parent_node: None,
         };
@@ -187,20 +252,73 @@ impl Entry {
         Ok(entry)
     }
 
-    fn build_name(text_len: u16, array: &[u8]) -> Result<String, Error> {
+    fn build_name(text_len: u16, array: &[u8], order: ByteOrder) -> Result<String, Error> {
         if text_len & 1 == 1 || !(0..=64).contains(&text_len) {
             return Err(Error::UTF16Error);
         }
 
-        let mut buf = Vec::<u16>::with_capacity(usize::from(text_len / 2));
+        // `text_len` counts the bytes of the name, including the
+        // terminating null character.
+        let n_chars = usize::from(text_len / 2).saturating_sub(1);
+        let mut buf = Vec::<u16>::with_capacity(n_chars);
 
-        for d in array.chunks_exact(constants::U16_SIZE) {
-            buf.push(u16::from_le_bytes(d.try_into().unwrap()));
+        for d in array.chunks_exact(constants::U16_SIZE).take(n_chars) {
+            buf.push(u16::from_slice_with(d, order));
         }
 
-        String::from_utf16(&buf).map_err(|_e| {
-            Error::UTF16Error
-        })
+        String::from_utf16(&buf).map_err(|_e| Error::UTF16Error)
+    }
+
+    /// Serializes the entry into a 128 bytes directory entry.
+    ///
+    /// The start sector is the first sector of `sec_id_chain`, or
+    /// `SECID_END_OF_CHAIN` if the chain is empty.
+    /// Empty entries are written as unused ones, by `write_free`.
+    pub(crate) fn write_to(&self, buf: &mut [u8]) {
+        if self.entry_type == EntryType::Empty {
+            return Entry::write_free(buf);
+        }
+        let buf = &mut buf[..constants::DIRECTORY_ENTRY_SIZE];
+        buf.fill(0);
+
+        let mut name_len = 0usize;
+        for (unit, dst) in self
+            .name
+            .encode_utf16()
+            .zip(buf[0..62].chunks_exact_mut(constants::U16_SIZE))
+        {
+            dst.copy_from_slice(&unit.to_le_bytes());
+            name_len += constants::U16_SIZE;
+        }
+        if name_len > 0 {
+            // The length includes the terminating null character.
+            name_len += constants::U16_SIZE;
+        }
+        buf[64..66].copy_from_slice(&(name_len as u16).to_le_bytes());
+        buf[66] = self.entry_type.to_u8();
+        buf[67] = self.color.to_u8();
+        buf[68..72].copy_from_slice(&self.left_child_node.to_le_bytes());
+        buf[72..76].copy_from_slice(&self.right_child_node.to_le_bytes());
+        buf[76..80].copy_from_slice(&self.root_node.to_le_bytes());
+        buf[80..96].copy_from_slice(&self.identifier);
+        buf[96..100].copy_from_slice(&self.flags.to_le_bytes());
+        buf[100..108].copy_from_slice(&self.creation_time.to_le_bytes());
+        buf[108..116].copy_from_slice(&self.last_modification_time.to_le_bytes());
+        let start = self
+            .sec_id_chain
+            .first()
+            .copied()
+            .unwrap_or(constants::SECID_END_OF_CHAIN);
+        buf[116..120].copy_from_slice(&start.to_le_bytes());
+        buf[120..128].copy_from_slice(&self.size.to_le_bytes());
+    }
+
+    /// Serializes an unused directory entry: all zeros, except the ids of
+    /// the siblings and of the child, which are `NO_STREAM`.
+    pub(crate) fn write_free(buf: &mut [u8]) {
+        let buf = &mut buf[..constants::DIRECTORY_ENTRY_SIZE];
+        buf.fill(0);
+        buf[68..80].fill(0xFF);
     }
 
     /// Returns the ID of the entry.
@@ -218,6 +336,48 @@ impl Entry {
         self.last_modification_time
     }
 
+    /// Returns the CLSID of the entry. The CLSID of the root entry usually
+    /// identifies the application which produced the file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ole::{Clsid, Reader};
+    /// let parser = Reader::from_path("assets/sample.doc").unwrap();
+    ///
+    /// let root = parser.entry_by_path("/").unwrap();
+    /// assert_eq!(root.clsid(), Clsid::WORD_DOCUMENT);
+    /// ```
+    pub fn clsid(&self) -> Clsid {
+        Clsid::from_bytes(self.identifier)
+    }
+
+    /// Returns the user-defined state bits of the entry.
+    pub fn state_bits(&self) -> u32 {
+        self.flags
+    }
+
+    /// Returns the creation time of the entry.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ole;
+    /// let parser = ole::Reader::from_path("assets/sample.doc").unwrap();
+    ///
+    /// let storage = parser.entry_by_path("ObjectPool").unwrap();
+    /// println!("created on {}", storage.created());
+    /// let created = storage.created().to_system_time();
+    /// ```
+    pub fn created(&self) -> FileTime {
+        FileTime::new(self.creation_time)
+    }
+
+    /// Returns the last modification time of the entry.
+    pub fn modified(&self) -> FileTime {
+        FileTime::new(self.last_modification_time)
+    }
+
     /// Returns the name of the entry.
     pub fn name(&self) -> &str {
         &self.name
@@ -229,7 +389,7 @@ impl Entry {
     }
 
     /// Returns the size of the entry
-    pub fn len(&self) -> usize {
+    pub fn len(&self) -> u64 {
         self.size
     }
 
@@ -264,24 +424,97 @@ impl fmt::Display for Entry {
         write!(
             f,
             "Entry #{}. Type: {}, Color: {}, Name: '{}',
-      Size: {}. SecID chain: {:?} ParentNode: {:?}",
+      Size: {}. SecID chain: {:?} ParentNode: {:?}
+      Created: {}, Modified: {}",
             self.id,
             self.entry_type,
             self.color,
             &self.name,
             self.size,
             self.sec_id_chain,
-            self.parent_node
+            self.parent_node,
+            self.created(),
+            self.modified()
         )
     }
 }
 
+/// Compares two entry names the way the compound file format does:
+/// shorter names come first, then names of the same length are compared
+/// code unit by code unit, in upper case.
+pub(crate) fn compare_names(a: &str, b: &str) -> Ordering {
+    fn upper(name: &str) -> Vec<u16> {
+        name.chars()
+            .map(|c| {
+                let mut upper = c.to_uppercase();
+                match (upper.next(), upper.next()) {
+                    (Some(u), None) => u,
+                    _ => c,
+                }
+            })
+            .collect::<String>()
+            .encode_utf16()
+            .collect()
+    }
+
+    let (a, b) = (upper(a), upper(b));
+    a.len().cmp(&b.len()).then_with(|| a.cmp(&b))
+}
+
+/// Checks that `name` can be used for a storage or a stream: at most 31
+/// UTF-16 characters, none of them being `/`, `\\`, `:` or `!`.
+pub(crate) fn check_name(name: &str) -> Result<(), Error> {
+    if name.is_empty() || name.encode_utf16().count() > 31 || name.contains(['/', '\\', ':', '!']) {
+        Err(Error::InvalidEntryName(name.to_string()))
+    } else {
+        Ok(())
+    }
+}
+
+/// Links the `siblings` DirIDs, which must be sorted with
+/// `compare_names`, into a balanced red-black tree and returns the DirID
+/// of its root, or `NO_STREAM` if there is no sibling.
+///
+/// Splitting on the middle element keeps every leaf on the last two
+/// levels, so colouring the nodes of the last level red (when it is not
+/// full) gives a valid red-black tree.
+pub(crate) fn build_sibling_tree(entries: &mut [Entry], siblings: &[u32]) -> u32 {
+    fn link(entries: &mut [Entry], siblings: &[u32], depth: u32, red_depth: u32) -> u32 {
+        if siblings.is_empty() {
+            return constants::NO_STREAM;
+        }
+        let middle = siblings.len() / 2;
+        let left = link(entries, &siblings[..middle], depth + 1, red_depth);
+        let right = link(entries, &siblings[middle + 1..], depth + 1, red_depth);
+        let entry = &mut entries[siblings[middle] as usize];
+        entry.left_child_node = left;
+        entry.right_child_node = right;
+        entry.color = if depth == red_depth {
+            NodeColour::Red
+        } else {
+            NodeColour::Black
+        };
+        siblings[middle]
+    }
+
+    let n = siblings.len() as u32;
+    let red_depth = if n == 0 || (n + 1).is_power_of_two() {
+        u32::MAX
+    } else {
+        n.ilog2()
+    };
+    link(entries, siblings, 0, red_depth)
+}
+
 /// Slice of the content of the entry.
 ///
 /// This is not an ordinary slice, because OLE files are like FAT system:
 /// they are based on sector and SAT. Therefore, a stream can be fragmented
 /// through the file.
 ///
+/// The sectors are read when the slice is read, not when it is built.
+/// Sectors which follow each other in the file are read at once.
+///
 /// # Basic example
 ///
 /// ```
@@ -295,227 +528,603 @@ impl fmt::Display for Entry {
 /// // Read the first 42 bytes of the entry;
 /// let mut buf = [0u8; 42];
 /// let nread = slice.read(&mut buf).unwrap();
+/// // Then the last 42 bytes.
+/// slice.seek(std::io::SeekFrom::End(-42)).unwrap();
+/// let nread = slice.read(&mut buf).unwrap();
 ///
 /// ```
 pub struct EntrySlice<'s> {
-    /// Chunk size, i.e. size of the sector.
-    max_chunk_size: usize,
+    /// Where the chunks are read from.
+    source: &'s (dyn BlockSource + 's),
+
+    /// Where the bytes of the slice are in the file.
+    layout: StreamLayout,
+
+    /// Position of the next byte to read.
+    position: u64,
+
+    /// Bytes returned by `BufRead::fill_buf`, starting at `buffer_position`.
+    buffer: Cow<'s, [u8]>,
+
+    /// Position of the first byte of `buffer` in the slice.
+    buffer_position: u64,
+}
 
-    /// List of slices.
-    chunks: Vec<&'s [u8]>,
+/// Where the bytes of a stream are in the file, as a list of chunks.
+pub(crate) struct StreamLayout {
+    /// Size of the sectors holding the chunks.
+    block_size: usize,
 
-    /// How many bytes which have been already read.
-    read: usize,
+    /// List of chunks, in the order of the stream.
+    chunks: Vec<Chunk>,
 
     /// Total size of slice.
-    total_size: usize,
+    total_size: u64,
 
     /// Real size of all chunks
-    real_size: usize,
+    real_size: u64,
+
+    /// Whether the chunks stop before the end of the chain, because it goes
+    /// beyond the end of the file, in lenient mode.
+    truncated: bool,
 }
 
-impl<'s> EntrySlice<'s> {
-    fn new(max_chunk_size: usize, size: usize) -> EntrySlice<'s> {
-        EntrySlice {
-            max_chunk_size,
+/// A run of bytes of a stream which are contiguous in the file.
+struct Chunk {
+    /// Position of the chunk in the stream.
+    position: u64,
+
+    /// Offset of the chunk in the file.
+    offset: usize,
+
+    /// Length of the chunk.
+    len: usize,
+}
+
+impl StreamLayout {
+    fn new(block_size: usize, size: u64) -> StreamLayout {
+        StreamLayout {
+            block_size,
             chunks: Vec::new(),
-            read: 0usize,
             total_size: size,
             real_size: 0,
+            truncated: false,
         }
     }
 
-    fn add_chunk(&mut self, chunk: &'s [u8]) {
-        self.real_size += chunk.len();
-        self.chunks.push(chunk);
+    /// Appends the `len` bytes at `offset` in the file to the stream, growing
+    /// the last chunk if they follow it in the file.
+    fn push(&mut self, offset: usize, len: usize) {
+        match self.chunks.last_mut() {
+            Some(last) if last.offset + last.len == offset => last.len += len,
+            _ => self.chunks.push(Chunk {
+                position: self.real_size,
+                offset,
+                len,
+            }),
+        }
+        self.real_size += len as u64;
     }
 
-    /// Returns the length of the slice, therefore the length of the entry.
-    pub fn len(&self) -> usize {
+    /// Returns the chunk holding the byte at `position`.
+    fn chunk_at(&self, position: u64) -> Option<&Chunk> {
+        let index = self
+            .chunks
+            .partition_point(|chunk| chunk.position + chunk.len as u64 <= position);
+        self.chunks.get(index)
+    }
+
+    pub(crate) fn len(&self) -> u64 {
         self.total_size
     }
 
+    pub(crate) fn real_len(&self) -> u64 {
+        self.real_size
+    }
+
+    /// Reads the bytes of the stream starting at `offset` from `source` into
+    /// `buf`, and returns how many bytes were read.
+    pub(crate) fn read_at(
+        &self,
+        source: &dyn BlockSource,
+        offset: u64,
+        buf: &mut [u8],
+    ) -> Result<usize, std::io::Error> {
+        let remaining = self.total_size.saturating_sub(offset);
+        let to_read = usize::try_from(remaining).map_or(buf.len(), |n| n.min(buf.len()));
+        let mut read = 0;
+        while read != to_read {
+            let position = offset + read as u64;
+            let Some(chunk) = self.chunk_at(position) else {
+                break;
+            };
+            let local_offset = (position - chunk.position) as usize;
+            let len = std::cmp::min(chunk.len - local_offset, to_read - read);
+            source
+                .read_exact_at(
+                    chunk.offset + local_offset,
+                    &mut buf[read..read + len],
+                    self.block_size,
+                )
+                .map_err(std::io::Error::other)?;
+            read += len;
+        }
+
+        Ok(read)
+    }
+
+    /// Returns the position `pos` leads to from `position`. Seeking beyond
+    /// the end of the stream is allowed, reads then return no byte.
+    pub(crate) fn seek(
+        &self,
+        position: u64,
+        pos: std::io::SeekFrom,
+    ) -> Result<u64, std::io::Error> {
+        let position = match pos {
+            std::io::SeekFrom::Start(offset) => Some(offset),
+            std::io::SeekFrom::End(delta) => self.total_size.checked_add_signed(delta),
+            std::io::SeekFrom::Current(delta) => position.checked_add_signed(delta),
+        };
+        position.ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })
+    }
+}
+
+impl<'s> EntrySlice<'s> {
+    fn new(source: &'s (dyn BlockSource + 's), layout: StreamLayout) -> EntrySlice<'s> {
+        EntrySlice {
+            source,
+            layout,
+            position: 0,
+            buffer: Cow::Borrowed(&[]),
+            buffer_position: 0,
+        }
+    }
+
+    /// Returns the length of the slice, therefore the length of the entry.
+    pub fn len(&self) -> u64 {
+        self.layout.len()
+    }
+
     pub fn is_empty(&self) -> bool {
-        self.total_size == 0
+        self.layout.len() == 0
     }
 
     /// Returns the real length of all chunks
-    pub fn real_len(&self) -> usize {
-        self.real_size
+    pub fn real_len(&self) -> u64 {
+        self.layout.real_len()
+    }
+
+    /// Reads the bytes of the slice starting at `offset` into `buf`, and
+    /// returns how many bytes were read. The position of the slice, used by
+    /// `Read`, isn't changed.
+    ///
+    /// Only the sectors holding the requested bytes are read: the sector
+    /// holding `offset` is found directly, without reading the ones before.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ole::Reader;
+    /// let parser = Reader::from_path("assets/sample.doc").unwrap();
+    ///
+    /// let slice = parser.open_stream("WordDocument").unwrap();
+    /// // The magic number of the FIB.
+    /// let mut w_ident = [0u8; 2];
+    /// slice.read_at(0, &mut w_ident).unwrap();
+    /// assert_eq!(u16::from_le_bytes(w_ident), 0xA5EC);
+    /// ```
+    pub fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<usize, std::io::Error> {
+        self.layout.read_at(self.source, offset, buf)
     }
 }
 
 impl<'s> std::io::Read for EntrySlice<'s> {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize, std::io::Error> {
-        let to_read = std::cmp::min(buf.len(), self.total_size - self.read);
-        let result: Result<usize, std::io::Error>;
-        if to_read == 0 {
-            result = Ok(0usize);
-        } else {
-            let mut offset = self.read;
-            let mut read = 0;
-            while read != to_read {
-                let chunk_index = offset / self.max_chunk_size;
-                if chunk_index >= self.chunks.len() {
-                    break;
-                }
-                let chunk = &self.chunks[chunk_index];
-                let local_offset = offset % self.max_chunk_size;
-                let end = std::cmp::min(local_offset + to_read - read, self.max_chunk_size);
-                let slice = &chunk[local_offset..end];
-                for u in slice {
-                    buf[read] = *u;
-                    read += 1;
-                    self.read += 1;
-                }
-                offset = self.read;
+        let read = self.read_at(self.position, buf)?;
+        self.position += read as u64;
+        Ok(read)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> Result<usize, std::io::Error> {
+        let mut read = 0;
+        for buf in bufs {
+            let n = self.read(buf)?;
+            read += n;
+            if n < buf.len() {
+                break;
             }
-            result = Ok(read);
         }
+        Ok(read)
+    }
+}
 
-        result
+impl<'s> std::io::BufRead for EntrySlice<'s> {
+    /// Returns the bytes from the position of the slice to the end of its
+    /// chunk. They are borrowed from the file when it is in memory or
+    /// memory-mapped, and read in a buffer of at most
+    /// `constants::SLICE_BUFFER_SIZE` bytes otherwise.
+    fn fill_buf(&mut self) -> Result<&[u8], std::io::Error> {
+        let buffered = self
+            .position
+            .checked_sub(self.buffer_position)
+            .and_then(|start| usize::try_from(start).ok())
+            .filter(|&start| start < self.buffer.len());
+        let start = match buffered {
+            Some(start) => start,
+            None => {
+                let total_size = self.layout.total_size;
+                let (offset, len) = match self.layout.chunk_at(self.position) {
+                    Some(chunk) if self.position < total_size => {
+                        let local_offset = (self.position - chunk.position) as usize;
+                        let remaining = (total_size - self.position)
+                            .min((chunk.len - local_offset) as u64)
+                            as usize;
+                        (chunk.offset + local_offset, remaining)
+                    }
+                    _ => return Ok(&[]),
+                };
+                self.buffer = if self.source.is_borrowed() {
+                    self.source
+                        .block(offset, len)
+                        .map_err(std::io::Error::other)?
+                } else {
+                    // The buffer is reused from one call to the next.
+                    let mut buffer = std::mem::take(&mut self.buffer).into_owned();
+                    buffer.resize(len.min(constants::SLICE_BUFFER_SIZE), 0);
+                    self.source
+                        .read_exact_at(offset, &mut buffer, self.layout.block_size)
+                        .map_err(std::io::Error::other)?;
+                    Cow::Owned(buffer)
+                };
+                self.buffer_position = self.position;
+                0
+            }
+        };
+        Ok(&self.buffer[start..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.position += amt as u64;
+    }
+}
+
+impl<'s> std::io::Seek for EntrySlice<'s> {
+    /// Moves the position of the slice. Seeking beyond the end of the slice
+    /// is allowed, reads then return no byte.
+    fn seek(&mut self, pos: std::io::SeekFrom) -> Result<u64, std::io::Error> {
+        self.position = self.layout.seek(self.position, pos)?;
+        Ok(self.position)
     }
 }
 
 impl<'ole> Reader<'ole> {
     /// Returns the slice for the entry.
-    pub fn get_entry_slice(&self, entry: &Entry) -> Result<EntrySlice, Error> {
-        let entry_slice: EntrySlice;
+    pub fn get_entry_slice(&self, entry: &Entry) -> Result<EntrySlice<'_>, Error> {
+        Ok(EntrySlice::new(&self.source, self.stream_layout(entry)?))
+    }
+
+    /// Returns where the bytes of the entry are in the file.
+    pub(crate) fn stream_layout(&self, entry: &Entry) -> Result<StreamLayout, Error> {
         let size = entry.size;
         if size == 0 {
             Err(Error::EmptyEntry)
+        } else if size > self.options.max_stream_size as u64 {
+            Err(Error::LimitExceeded {
+                limit: "stream size",
+                max: self.options.max_stream_size,
+            })
+        } else if size < self.minimum_standard_stream_size {
+            self.get_short_stream_slices(&entry.sec_id_chain, size)
         } else {
-            if size < self.minimum_standard_stream_size {
-                entry_slice = self.get_short_stream_slices(&entry.sec_id_chain, size)?;
-            } else {
-                entry_slice = self.get_stream_slices(&entry.sec_id_chain, size)?;
-            }
-            Ok(entry_slice)
+            self.get_stream_slices(&entry.sec_id_chain, size)
         }
     }
 
     pub(crate) fn build_directory_entries(&mut self) -> Result<(), Error> {
         let n_entry_by_sector = self.sec_size / constants::DIRECTORY_ENTRY_SIZE;
+        if self.dir_sat.len().saturating_mul(n_entry_by_sector) > self.options.max_entries {
+            return Err(Error::LimitExceeded {
+                limit: "directory entries",
+                max: self.options.max_entries,
+            });
+        }
         let mut entries = Vec::<Entry>::with_capacity(self.dir_sat.len() * n_entry_by_sector);
 
         let mut k = 0u32;
         for i in 0..self.dir_sat.len() {
             let sector_index = self.dir_sat[i];
-            let sector = self.read_sector(sector_index)?;
-
-            // self.dump_difat_sector(&sector);
-
-            let loc = i * constants::U32_SIZE;
-            if sector[loc..loc + 4] == constants::FREE_SECID_ARRAY_U8 {
-                break;
-            }
-
-            for l in 0..n_entry_by_sector {
-                let entry = Entry::from_slice(
-                    &sector[l * constants::DIRECTORY_ENTRY_SIZE
-                        ..(l + 1) * constants::DIRECTORY_ENTRY_SIZE],
+            let sector = match self.read_sector(sector_index) {
+                Ok(sector) => sector.into_owned(),
+                Err(error) => {
+                    // The directory is cut before the sector which can't be
+                    // read.
+                    self.recover(error)?;
+                    self.dir_sat.truncate(i);
+                    break;
+                }
+            };
+            let sector_offset = self.sector_offset(sector_index)?;
+
+            for (l, data) in sector
+                .chunks_exact(constants::DIRECTORY_ENTRY_SIZE)
+                .enumerate()
+            {
+                let offset = sector_offset + l * constants::DIRECTORY_ENTRY_SIZE;
+                let entry = match Entry::from_slice(
+                    data,
                     k,
-                )?;
+                    offset,
+                    self.byte_order,
+                    self.version_number,
+                ) {
+                    Ok(entry) => entry,
+                    Err(error) => {
+                        self.recover(error)?;
+                        Entry::new(k, "", EntryType::Empty)
+                    }
+                };
                 entries.push(entry);
                 k += 1;
             }
         }
+        if entries.is_empty() {
+            return Err(Error::InvalidSectorId {
+                sector_id: self.header.first_directory_sector(),
+                offset: 48,
+            });
+        }
         let stream_size = self.minimum_standard_stream_size;
 
         for (i, entry) in (0_u32..).zip(&mut entries.iter_mut()) {
-            match entry.entry_type {
+            // Offset of the start sector of the entry
+            let offset = self.entry_offset(i)? + 116;
+            let sector_size = match entry.entry_type {
+                // Empty streams are sometimes written with a start sector of
+                // 0 instead of `SECID_END_OF_CHAIN`: they have no chain,
+                // whatever their start sector.
+                EntryType::UserStream if entry.size == 0 => {
+                    entry.sec_id_chain.clear();
+                    continue;
+                }
                 EntryType::UserStream => {
-                    let start_index = entry.sec_id_chain.pop().expect("No sectors in cahin!");
-                    entry.sec_id_chain = if entry.size < stream_size {
-                        self.build_chain_from_ssat(start_index)
+                    let start_index = entry.sec_id_chain[0];
+                    if entry.size < stream_size {
+                        entry.sec_id_chain = self.build_chain_from_ssat(start_index, offset)?;
+                        self.short_sec_size
                     } else {
-                        self.build_chain_from_sat(start_index)
-                    };
+                        entry.sec_id_chain = self.build_chain_from_sat(start_index, offset)?;
+                        self.sec_size
+                    }
                 }
                 EntryType::RootStorage => {
                     self.root_entry = Some(i);
-                    let start_index = entry.sec_id_chain.pop().expect("No sectors in cahin!");
-                    entry.sec_id_chain = self.build_chain_from_sat(start_index);
+                    let start_index = entry.sec_id_chain[0];
+                    entry.sec_id_chain = self.build_chain_from_sat(start_index, offset)?;
+                    self.sec_size
                 }
-                _ => {}
+                _ => continue,
+            };
+
+            // The same goes for a root entry without mini stream.
+            if entry.size != 0
+                && entry.size.div_ceil(sector_size as u64) != entry.sec_id_chain.len() as u64
+            {
+                self.recover(Error::InvalidEntry {
+                    entry_id: i,
+                    offset: offset + 4,
+                    reason: "Stream size doesn't match the length of its chain",
+                })?;
             }
         }
         self.entries = Some(entries);
-        self.build_entry_tree(0, None);
-        Ok(())
+        self.build_entry_tree(0)?;
+        self.check_stream_layouts()
     }
 
-    fn get_short_stream_slices(&self, chain: &Vec<u32>, size: usize) -> Result<EntrySlice, Error> {
-        let short_sector_size = self.short_sec_size;
-        let mut entry_slice = EntrySlice::new(short_sector_size, size);
-        let short_stream_chain = &self.entries.as_ref().unwrap()[0].sec_id_chain.clone();
-        let n_per_sector = self.sec_size / short_sector_size;
-        let mut total_read = 0;
-        for short_sec_id in chain {
-            let sector_index = short_stream_chain[*short_sec_id as usize / n_per_sector];
-            let sector = self.read_sector(sector_index)?;
-            let short_sector_index = *short_sec_id as usize % n_per_sector;
-            let start = short_sector_index as usize * short_sector_size;
-            let end = start + std::cmp::min(short_sector_size, size - total_read);
-            entry_slice.add_chunk(&sector[start..end]);
-            total_read += end - start;
+    /// In lenient mode, records a warning for every stream cut because it
+    /// goes beyond the end of the file or of the mini stream.
+    fn check_stream_layouts(&mut self) -> Result<(), Error> {
+        if !self.options.lenient {
+            return Ok(());
         }
-        Ok(entry_slice)
-    }
-
-    fn get_stream_slices(&self, chain: &Vec<u32>, size: usize) -> Result<EntrySlice, Error> {
-        let sector_size = self.sec_size;
-        let mut entry_slice = EntrySlice::new(sector_size, size);
-        let mut total_read = 0;
-        for sector_id in chain {
-            let sector = self.read_sector(*sector_id)?;
-            let start = 0usize;
-            let end = std::cmp::min(sector_size, size - total_read);
-            entry_slice.add_chunk(&sector[start..end]);
-            total_read += end - start;
+        let truncated: Vec<Error> = self
+            .entries
+            .iter()
+            .flatten()
+            .filter(|entry| entry.entry_type == EntryType::UserStream)
+            .filter_map(|entry| {
+                let layout = self.stream_layout(entry).ok()?;
+                layout.truncated.then(|| Error::TruncatedStream {
+                    entry_id: entry.id,
+                    len: layout.len(),
+                    real_len: layout.real_len(),
+                })
+            })
+            .collect();
+        for error in truncated {
+            self.recover(error)?;
         }
-        Ok(entry_slice)
+        Ok(())
     }
 
-    fn build_entry_tree(&mut self, id: u32, parent_id: Option<u32>) {
-        // println!("build_entry_tree 0x{id:8x}");
-        if id != constants::SECID_FREE_SECTOR {
-            let (child, node_type, n) = {
-                let entries = self.entries.as_mut().expect("Valid entry");
-
-                let n = u32::try_from(entries.len()).expect("length to big to fit in u32");
-
-                // if id >= n {
-                //     panic!("read out of bouned.");
-                // }
+    /// Returns the offset in the file of the directory entry `id`.
+    pub(crate) fn entry_offset(&self, id: u32) -> Result<usize, Error> {
+        let n_entry_by_sector = self.sec_size / constants::DIRECTORY_ENTRY_SIZE;
+        let sector_index = self.dir_sat[id as usize / n_entry_by_sector];
+        Ok(self.sector_offset(sector_index)?
+            + id as usize % n_entry_by_sector * constants::DIRECTORY_ENTRY_SIZE)
+    }
 
-                // Register as child
-                if let Some(parent_id) = parent_id {
-                    entries[parent_id as usize].children_nodes.push(id);
+    fn get_short_stream_slices(&self, chain: &[u32], size: u64) -> Result<StreamLayout, Error> {
+        let mut layout = StreamLayout::new(self.sec_size, size);
+        for &short_sec_id in chain {
+            // In lenient mode, the stream is cut where it leaves the file.
+            let offset = match self.mini_sector_offset(short_sec_id) {
+                Err(_) if self.options.lenient => {
+                    layout.truncated = true;
+                    break;
                 }
+                offset => offset?,
+            };
+            let len = (self.short_sec_size as u64).min(size - layout.real_size);
+            layout.push(offset, len as usize);
+        }
+        Ok(layout)
+    }
 
-                let entry = &mut entries[id as usize];
+    fn get_stream_slices(&self, chain: &[u32], size: u64) -> Result<StreamLayout, Error> {
+        let mut layout = StreamLayout::new(self.sec_size, size);
+        for &sector_id in chain {
+            // In lenient mode, the stream is cut where it leaves the file.
+            let offset = match self.checked_sector_offset(sector_id) {
+                Err(_) if self.options.lenient => {
+                    layout.truncated = true;
+                    break;
+                }
+                offset => offset?,
+            };
+            let len = (self.sec_size as u64).min(size - layout.real_size);
+            layout.push(offset, len as usize);
+        }
+        Ok(layout)
+    }
 
-                // Register the parent id for the current node
-                entry.parent_node = parent_id;
+    /// Links the entries of the red-black trees starting at `root` to their
+    /// parent storage, filling `children_nodes` and `parent_node`.
+    ///
+    /// The trees are walked with an explicit stack, so deep trees can't
+    /// overflow the call stack. An entry reached twice means the directory
+    /// has a cycle.
+    fn build_entry_tree(&mut self, root: u32) -> Result<(), Error> {
+        let mut entries = self.entries.take().ok_or(Error::InvalidOLEFile)?;
+        let result = self.link_entries(&mut entries, root);
+        self.entries = Some(entries);
+        result
+    }
 
-                (entry.root_node, entry._type(), n)
-            };
+    /// Links `entries`, taken out of the reader, for `build_entry_tree`.
+    ///
+    /// In lenient mode, the links to invalid entries, or to entries already
+    /// visited, are ignored.
+    fn link_entries(&mut self, entries: &mut [Entry], root: u32) -> Result<(), Error> {
+        let mut visited = vec![false; entries.len()];
+
+        // (id, parent id, offset the id was read at, depth)
+        let mut stack = vec![(root, None, self.entry_offset(root)?, 0)];
+        while let Some((id, parent_id, read_at, depth)) = stack.pop() {
+            if id == constants::NO_STREAM {
+                continue;
+            }
+            if id as usize >= entries.len() {
+                self.recover(Error::InvalidEntryId {
+                    entry_id: id,
+                    offset: read_at,
+                })?;
+                continue;
+            }
+            if std::mem::replace(&mut visited[id as usize], true) {
+                self.recover(Error::DirectoryCycle {
+                    entry_id: id,
+                    offset: read_at,
+                })?;
+                continue;
+            }
 
-            if node_type == EntryType::RootStorage || node_type == EntryType::UserStorage {
-                self.build_entry_tree(child, Some(id));
+            // Register as child
+            if let Some(parent_id) = parent_id {
+                entries[parent_id as usize].children_nodes.push(id);
             }
 
-            let entry = &mut self.entries.as_mut().unwrap()[id as usize];
+            let entry = &mut entries[id as usize];
+
+            // Register the parent id for the current node
+            entry.parent_node = parent_id;
+
+            // Visit the children of a storage first, then the left and the
+            // right siblings.
+            let offset = self.entry_offset(id)?;
+            stack.push((entry.right_child_node, parent_id, offset + 72, depth));
+            stack.push((entry.left_child_node, parent_id, offset + 68, depth));
+            if entry.entry_type == EntryType::RootStorage
+                || entry.entry_type == EntryType::UserStorage
+            {
+                if entry.root_node != constants::NO_STREAM && depth == self.options.max_depth {
+                    return Err(Error::LimitExceeded {
+                        limit: "storage depth",
+                        max: self.options.max_depth,
+                    });
+                }
+                stack.push((entry.root_node, Some(id), offset + 76, depth + 1));
+            }
+        }
+        Ok(())
+    }
+}
 
-            let left_child = entry.left_child_node();
-            let right_child = entry.right_child_node();
+#[cfg(test)]
+mod tests {
+    use crate::{Reader, Version, Writer};
+    use std::io::{BufRead, IoSliceMut, Read};
+
+    #[test]
+    fn coalesced_chunks() {
+        let mut writer = Writer::new(Version::V3);
+        writer.create_stream("Big", &[1u8; 5000]).unwrap();
+        writer.create_stream("Small", &[2u8; 300]).unwrap();
+        let mut buf = Vec::new();
+        writer.write_to(&mut buf).unwrap();
+        let ole = Reader::new(&buf[..]).unwrap();
+
+        // Both streams are stored contiguously.
+        for (path, len) in [("Big", 5000), ("Small", 300)] {
+            let slice = ole.open_stream(path).unwrap();
+            assert_eq!(slice.layout.chunks.len(), 1);
+            assert_eq!(slice.layout.chunks[0].len, len);
+        }
+    }
 
-            if left_child < n {
-                self.build_entry_tree(left_child, parent_id);
-            }
-            if right_child < n {
-                self.build_entry_tree(right_child, parent_id);
+    #[test]
+    fn buf_read_and_vectored() {
+        let data = std::fs::read("./assets/sample.ppt").unwrap();
+        let memory = Reader::new(&data[..]).unwrap();
+        let uncached = Reader::from_seekable(std::io::Cursor::new(&data[..])).unwrap();
+        let cached = Reader::from_seekable_with_cache(std::io::Cursor::new(&data[..]), 16).unwrap();
+        for ole in [memory, uncached, cached] {
+            for entry in ole.iterate().filter(|e| !e.is_empty()) {
+                let mut expected = Vec::new();
+                let mut slice = ole.get_entry_slice(entry).unwrap();
+                slice.read_to_end(&mut expected).unwrap();
+
+                let mut slice = ole.get_entry_slice(entry).unwrap();
+                let mut buffered = Vec::new();
+                loop {
+                    let buf = slice.fill_buf().unwrap();
+                    if buf.is_empty() {
+                        break;
+                    }
+                    // Consume part of the buffer only.
+                    let n = buf.len().min(700);
+                    buffered.extend_from_slice(&buf[..n]);
+                    slice.consume(n);
+                }
+                assert_eq!(buffered, expected);
+
+                let mut slice = ole.get_entry_slice(entry).unwrap();
+                let (mut a, mut b, mut c) = ([0u8; 10], [0u8; 1000], vec![0u8; expected.len()]);
+                let mut bufs = [
+                    IoSliceMut::new(&mut a),
+                    IoSliceMut::new(&mut b),
+                    IoSliceMut::new(&mut c),
+                ];
+                let read = slice.read_vectored(&mut bufs).unwrap();
+                assert_eq!(read, expected.len());
+                let vectored = [&a[..], &b[..], &c[..]].concat();
+                assert_eq!(vectored[..read], expected);
             }
         }
     }
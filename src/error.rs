@@ -66,4 +66,94 @@ pub enum Error {
     /// User query an empty entry
     #[error("Invalid UTF16 Name")]
     UTF16Error,
+
+    /// No entry exists at the given path.
+    #[error("Entry not found: {0}")]
+    EntryNotFound(String),
+
+    /// An entry with the same name already exists in the storage.
+    #[error("Entry already exists: {0}")]
+    EntryAlreadyExists(String),
+
+    /// The name can't be used for a storage or a stream.
+    #[error("Invalid entry name: {0}")]
+    InvalidEntryName(String),
+
+    /// The entry is not a storage.
+    #[error("Not a storage: {0}")]
+    NotAStorage(String),
+
+    /// The entry is not a stream.
+    #[error("Not a stream: {0}")]
+    NotAStream(String),
+
+    /// A property set stream is malformed.
+    #[error("Invalid property set: {0}")]
+    InvalidPropertySet(&'static str),
+
+    /// The string is not a valid CLSID.
+    #[error("Invalid CLSID: {0}")]
+    InvalidClsid(String),
+
+    /// The file ends before `len` bytes could be read at `offset`.
+    #[error("Unexpected end of file: {len} bytes expected at offset {offset:#x}")]
+    UnexpectedEof { offset: usize, len: usize },
+
+    /// A field of the header, at `offset`, has an invalid value.
+    #[error("Invalid header field at offset {offset:#x}: {value:#x}")]
+    InvalidHeaderField { offset: usize, value: u32 },
+
+    /// A sector is beyond the end of the file, or a mini sector beyond the
+    /// end of the mini stream. `offset` is where it would start, in the file
+    /// or in the mini stream.
+    #[error("Sector {sector_id:#x} at offset {offset:#x} is beyond the end of the file")]
+    SectorOutOfRange { sector_id: u32, offset: usize },
+
+    /// The sector id read at `offset` doesn't point to a valid sector.
+    #[error("Invalid sector id {sector_id:#x} at offset {offset:#x}")]
+    InvalidSectorId { sector_id: u32, offset: usize },
+
+    /// The directory entry id read at `offset` doesn't point to a valid
+    /// entry.
+    #[error("Invalid directory entry id {entry_id:#x} at offset {offset:#x}")]
+    InvalidEntryId { entry_id: u32, offset: usize },
+
+    /// The directory entry at `offset` is malformed.
+    #[error("Invalid directory entry {entry_id} at offset {offset:#x}: {reason}")]
+    InvalidEntry {
+        entry_id: u32,
+        offset: usize,
+        reason: &'static str,
+    },
+
+    /// A chain of sectors loops: the sector id read at `offset` is already
+    /// in the chain.
+    #[error(
+        "Sector chain cycle: sector {sector_id:#x} at offset {offset:#x} is already in the chain"
+    )]
+    ChainCycle { sector_id: u32, offset: usize },
+
+    /// The directory tree loops: the entry id read at `offset` was already
+    /// visited.
+    #[error("Directory cycle: entry {entry_id:#x} at offset {offset:#x} was already visited")]
+    DirectoryCycle { entry_id: u32, offset: usize },
+
+    /// The stream of the directory entry `entry_id` goes beyond the end of
+    /// the file, or of the mini stream: only `real_len` of its `len` bytes
+    /// can be read. Only reported as a warning, in lenient mode.
+    #[error("Truncated stream {entry_id}: {real_len} of {len} bytes can be read")]
+    TruncatedStream {
+        entry_id: u32,
+        len: u64,
+        real_len: u64,
+    },
+
+    /// The `Reader` reads a seekable reader which may not be `Send`, so it
+    /// can't be turned into a `SharedReader`.
+    #[error("Reader not shareable: it reads a seekable reader which may not be Send")]
+    NotShareable,
+
+    /// A limit set by `ReaderOptions` is exceeded.
+    #[error("Limit exceeded: {limit} is above {max}")]
+    LimitExceeded { limit: &'static str, max: usize },
 }
@@ -0,0 +1,218 @@
+//             DO WHAT THE FUCK YOU WANT TO PUBLIC LICENSE
+//                    Version 2, December 2004
+//
+// Copyright (C) 2018 Thomas Bailleux <thomas@bailleux.me>
+//
+// Everyone is permitted to copy and distribute verbatim or modified
+// copies of this license document, and changing it is allowed as long
+// as the name is changed.
+//
+//            DO WHAT THE FUCK YOU WANT TO PUBLIC LICENSE
+//   TERMS AND CONDITIONS FOR COPYING, DISTRIBUTION AND MODIFICATION
+//
+//  0. You just DO WHAT THE FUCK YOU WANT TO.
+//
+// Author: zadig <thomas chr(0x40) bailleux.me>
+
+use std::{
+    fmt,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Number of 100 nanoseconds intervals in a second.
+const TICKS_PER_SECOND: u64 = 10_000_000;
+
+/// Seconds between January 1, 1601 and January 1, 1970.
+const UNIX_EPOCH_SECONDS: i64 = 11_644_473_600;
+
+/// A Windows FILETIME: the number of 100 nanoseconds intervals since
+/// January 1, 1601 (UTC).
+///
+/// Zero means that the time isn't set. Values above `i64::MAX` are invalid
+/// for Windows, and aren't converted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FileTime(u64);
+
+impl FileTime {
+    /// Constructs a `FileTime` from its raw value.
+    pub fn new(value: u64) -> FileTime {
+        FileTime(value)
+    }
+
+    /// Returns the raw value.
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+
+    /// Returns whether the time is set.
+    pub fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns the seconds and nanoseconds since the Unix epoch, or `None`
+    /// if the time isn't set or is invalid.
+    fn unix(&self) -> Option<(i64, u32)> {
+        if self.0 == 0 || self.0 > i64::MAX as u64 {
+            return None;
+        }
+        let secs = (self.0 / TICKS_PER_SECOND) as i64 - UNIX_EPOCH_SECONDS;
+        let nanos = (self.0 % TICKS_PER_SECOND) as u32 * 100;
+        Some((secs, nanos))
+    }
+
+    /// Converts the time to a `SystemTime`.
+    ///
+    /// Returns `None` if the time isn't set, or can't be represented.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ole::FileTime;
+    /// use std::time::{Duration, UNIX_EPOCH};
+    ///
+    /// let time = FileTime::new(116_444_736_000_000_000).to_system_time();
+    /// assert_eq!(time, Some(UNIX_EPOCH));
+    /// assert_eq!(FileTime::new(0).to_system_time(), None);
+    /// ```
+    pub fn to_system_time(&self) -> Option<SystemTime> {
+        let (secs, nanos) = self.unix()?;
+        if secs >= 0 {
+            UNIX_EPOCH.checked_add(Duration::new(secs as u64, nanos))
+        } else {
+            UNIX_EPOCH
+                .checked_sub(Duration::from_secs(secs.unsigned_abs()))?
+                .checked_add(Duration::from_nanos(u64::from(nanos)))
+        }
+    }
+
+    /// Converts the time to a `chrono::DateTime`.
+    ///
+    /// Returns `None` if the time isn't set, or can't be represented.
+    #[cfg(feature = "chrono")]
+    pub fn to_chrono(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        let (secs, nanos) = self.unix()?;
+        chrono::DateTime::from_timestamp(secs, nanos)
+    }
+
+    /// Converts the time to a `time::OffsetDateTime`, in UTC.
+    ///
+    /// Returns `None` if the time isn't set, or can't be represented.
+    #[cfg(feature = "time")]
+    pub fn to_offset_date_time(&self) -> Option<::time::OffsetDateTime> {
+        let (secs, nanos) = self.unix()?;
+        ::time::OffsetDateTime::from_unix_timestamp_nanos(
+            i128::from(secs) * 1_000_000_000 + i128::from(nanos),
+        )
+        .ok()
+    }
+}
+
+impl From<u64> for FileTime {
+    fn from(value: u64) -> FileTime {
+        FileTime(value)
+    }
+}
+
+/// Formats the time as `YYYY-MM-DD hh:mm:ss UTC`, or `-` if it isn't set.
+impl fmt::Display for FileTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0 == 0 {
+            return write!(f, "-");
+        }
+        let (secs, _) = match self.unix() {
+            Some(unix) => unix,
+            None => return write!(f, "invalid ({:#x})", self.0),
+        };
+        let (days, secs) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+        let (year, month, day) = civil_from_days(days);
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+            year,
+            month,
+            day,
+            secs / 3600,
+            secs / 60 % 60,
+            secs % 60
+        )
+    }
+}
+
+/// Returns the date of the day `days` after January 1, 1970, in the
+/// proleptic Gregorian calendar.
+///
+/// See <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FileTime;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn system_time() {
+        assert_eq!(FileTime::new(0).to_system_time(), None);
+        assert_eq!(FileTime::new(u64::MAX).to_system_time(), None);
+        assert_eq!(
+            FileTime::new(0x01C1_3A42_D17D_8000).to_system_time(),
+            Some(UNIX_EPOCH + Duration::from_secs(1_000_158_720))
+        );
+        assert_eq!(
+            FileTime::new(116_444_736_000_000_001).to_system_time(),
+            Some(UNIX_EPOCH + Duration::from_nanos(100))
+        );
+        assert_eq!(
+            FileTime::new(1).to_system_time(),
+            UNIX_EPOCH
+                .checked_sub(Duration::from_secs(11_644_473_600))
+                .map(|t| t + Duration::from_nanos(100))
+        );
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(FileTime::new(0).to_string(), "-");
+        assert_eq!(
+            FileTime::new(0x01C1_3A42_D17D_8000).to_string(),
+            "2001-09-10 21:52:00 UTC"
+        );
+        assert_eq!(FileTime::new(1).to_string(), "1601-01-01 00:00:00 UTC");
+        assert_eq!(
+            FileTime::new(i64::MAX as u64).to_string(),
+            "30828-09-14 02:48:05 UTC"
+        );
+        assert_eq!(
+            FileTime::new(u64::MAX).to_string(),
+            "invalid (0xffffffffffffffff)"
+        );
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn chrono() {
+        let time = FileTime::new(0x01C1_3A42_D17D_8000).to_chrono().unwrap();
+        assert_eq!(time.timestamp(), 1_000_158_720);
+        assert_eq!(FileTime::new(0).to_chrono(), None);
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn time() {
+        let time = FileTime::new(0x01C1_3A42_D17D_8000)
+            .to_offset_date_time()
+            .unwrap();
+        assert_eq!(time.unix_timestamp(), 1_000_158_720);
+        assert_eq!(FileTime::new(i64::MAX as u64).to_offset_date_time(), None);
+    }
+}
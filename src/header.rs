@@ -14,17 +14,162 @@
 //
 // Author: zadig <thomas chr(0x40) bailleux.me>
 
-use std::vec::Vec;
+use std::{collections::HashSet, vec::Vec};
+
+use super::{
+    clsid::Clsid,
+    constants,
+    error::Error,
+    ole::Reader,
+    source::BlockSource,
+    util::{ByteOrder, FromSlice},
+};
+
+/// The header of a compound file.
+///
+/// # Examples
+///
+/// ```
+/// use ole;
+/// let parser = ole::Reader::from_path("assets/sample.doc").unwrap();
+///
+/// let header = parser.header();
+/// assert_eq!(header.major_version(), 3);
+/// assert_eq!(header.sector_size(), 512);
+/// assert_eq!(header.mini_stream_cutoff(), 4096);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Header {
+    clsid: Clsid,
+    minor_version: u16,
+    major_version: u16,
+    byte_order: ByteOrder,
+    sector_shift: u16,
+    mini_sector_shift: u16,
+    directory_sector_count: u32,
+    fat_sector_count: u32,
+    first_directory_sector: u32,
+    transaction_signature: u32,
+    mini_stream_cutoff: u32,
+    first_mini_fat_sector: u32,
+    mini_fat_sector_count: u32,
+    first_difat_sector: u32,
+    difat_sector_count: u32,
+}
+
+impl Header {
+    /// Reads the fields of the 512 bytes of `header`, without checking them.
+    pub(crate) fn parse(header: &[u8]) -> Header {
+        let order = ByteOrder::from_mark(&header[28..30]).unwrap_or_default();
+        let u16_at = |offset: usize| u16::from_slice_with(&header[offset..offset + 2], order);
+        let u32_at = |offset: usize| u32::from_slice_with(&header[offset..offset + 4], order);
+        Header {
+            clsid: Clsid::from_slice_with(&header[8..24], order),
+            minor_version: u16_at(24),
+            major_version: u16_at(26),
+            byte_order: order,
+            sector_shift: u16_at(30),
+            mini_sector_shift: u16_at(32),
+            directory_sector_count: u32_at(40),
+            fat_sector_count: u32_at(44),
+            first_directory_sector: u32_at(48),
+            transaction_signature: u32_at(52),
+            mini_stream_cutoff: u32_at(56),
+            first_mini_fat_sector: u32_at(60),
+            mini_fat_sector_count: u32_at(64),
+            first_difat_sector: u32_at(68),
+            difat_sector_count: u32_at(72),
+        }
+    }
+
+    /// Returns the CLSID of the header, which should be nil.
+    pub fn clsid(&self) -> Clsid {
+        self.clsid
+    }
+
+    /// Returns the minor version, usually `0x003E`.
+    pub fn minor_version(&self) -> u16 {
+        self.minor_version
+    }
+
+    /// Returns the major version, 3 or 4.
+    pub fn major_version(&self) -> u16 {
+        self.major_version
+    }
 
-use super::{constants, error::Error, ole::Reader, util::FromSlice};
+    /// Returns the byte order of the file.
+    pub fn byte_order(&self) -> ByteOrder {
+        self.byte_order
+    }
+
+    /// Returns the size of a sector, in bytes.
+    pub fn sector_size(&self) -> usize {
+        1 << self.sector_shift
+    }
+
+    /// Returns the size of a mini sector, in bytes.
+    pub fn mini_sector_size(&self) -> usize {
+        1 << self.mini_sector_shift
+    }
+
+    /// Returns the number of directory sectors, always 0 in version 3.
+    pub fn directory_sector_count(&self) -> u32 {
+        self.directory_sector_count
+    }
+
+    /// Returns the number of FAT sectors.
+    pub fn fat_sector_count(&self) -> u32 {
+        self.fat_sector_count
+    }
+
+    /// Returns the first sector of the directory.
+    pub fn first_directory_sector(&self) -> u32 {
+        self.first_directory_sector
+    }
+
+    /// Returns the transaction signature, 0 if transactions aren't used.
+    pub fn transaction_signature(&self) -> u32 {
+        self.transaction_signature
+    }
+
+    /// Returns the size under which streams are stored in the mini stream.
+    pub fn mini_stream_cutoff(&self) -> u32 {
+        self.mini_stream_cutoff
+    }
+
+    /// Returns the first sector of the mini FAT.
+    pub fn first_mini_fat_sector(&self) -> u32 {
+        self.first_mini_fat_sector
+    }
+
+    /// Returns the number of mini FAT sectors.
+    pub fn mini_fat_sector_count(&self) -> u32 {
+        self.mini_fat_sector_count
+    }
+
+    /// Returns the first DIFAT sector.
+    pub fn first_difat_sector(&self) -> u32 {
+        self.first_difat_sector
+    }
+
+    /// Returns the number of DIFAT sectors.
+    pub fn difat_sector_count(&self) -> u32 {
+        self.difat_sector_count
+    }
+}
 
 impl<'ole> Reader<'ole> {
+    /// Returns the header of the file.
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
     pub(crate) fn parse_header(&mut self) -> Result<(), Error> {
         // read the header
         //let mut header_sector_data: std::vec::Vec<u8> = vec![0u8; super::constants::HEADER_SIZE];
         // let s = self.read(&mut header_sector_data)?;
 
-        let header_sector_data = self.body[0..constants::HEADER_SIZE].to_vec();
+        let header_sector_data = self.read_header()?;
 
         // // Check file header size
         // if s != super::constants::HEADER_SIZE {
@@ -36,230 +181,223 @@ impl<'ole> Reader<'ole> {
             return Err(Error::InvalidOLEFile);
         }
 
-        self.dump_header(&header_sector_data);
+        // Byte order, which all the other fields depend on
+        let order =
+            ByteOrder::from_mark(&header_sector_data[28..30]).ok_or(Error::InvalidOLEFile)?;
+        self.byte_order = order;
 
         // UID
         self.uid.copy_from_slice(&header_sector_data[8..24]);
 
         // Revision number & version number
-        let rv_number = u16::from_slice(&header_sector_data[24..26]);
+        let rv_number = u16::from_slice_with(&header_sector_data[24..26], order);
         self.revision_number = rv_number;
 
-        let rv_number = u16::from_slice(&header_sector_data[26..28]);
+        let rv_number = u16::from_slice_with(&header_sector_data[26..28], order);
         self.version_number = rv_number;
 
-        if !(3..4).contains(&rv_number) {
-            return Err(Error::InvalidOLEVersion(self.version_number));
+        if !(3..=4).contains(&rv_number) {
+            self.recover(Error::InvalidOLEVersion(self.version_number))?;
         }
 
         if ![0x003e, 0x003b].contains(&self.revision_number) {
-            return Err(Error::InvalidOLEVersion(self.revision_number));
-        }
-
-        // println!("OLE Version {} {:x}", self.version_number, self.revision_number);
-
-        // Check little-endianness; big endian not yet supported
-        if header_sector_data[28..30] == constants::BIG_ENDIAN_IDENTIFIER {
-            return Err(Error::NotImplementedYet);
-        }
-
-        if header_sector_data[28..30] != constants::LITTLE_ENDIAN_IDENTIFIER {
-            return Err(Error::InvalidOLEFile);
+            self.recover(Error::InvalidOLEVersion(self.revision_number))?;
         }
 
-        // println!("HIER2");
-
         // Sector size or Sector Shift
-        let mut k = u32::from(u16::from_slice(&header_sector_data[30..32]));
+        let mut k = u32::from(u16::from_slice_with(&header_sector_data[30..32], order));
 
         // if k >= 16, it means that the sector size equals 2 ^ k, which
         // is impossible.
-        if k >= 16 {
-            return Err(Error::BadSizeValue("Overflow on sector size"));
-        }
-        if self.revision_number == 0x3 && k != 0x9 {
-            return Err(Error::BadSizeValue("Wrong Sector size!"));
+        let invalid_sector_shift = Error::InvalidHeaderField {
+            offset: 30,
+            value: k,
+        };
+        if !(7..16).contains(&k) {
+            return Err(invalid_sector_shift);
         }
-        if self.revision_number == 0x4 && k != 0xC {
-            return Err(Error::BadSizeValue("Wrong Sector size!"));
+        // Version 3 has 512 bytes sectors, version 4 has 4096 bytes sectors.
+        if (self.version_number == 3 && k != 9) || (self.version_number == 4 && k != 12) {
+            self.recover(invalid_sector_shift)?;
         }
 
         self.sec_size = 2usize.pow(k);
 
         // Short sector size
-        k = u32::from(u16::from_slice(&header_sector_data[32..34]));
-
-        // same for sector size
-        if k >= 16 {
-            return Err(Error::BadSizeValue("Overflow on short sector size"));
-        }
-        if self.revision_number == 0x4 && k != 0x6 {
-            return Err(Error::BadSizeValue("Wrong Mini Sector size!"));
+        k = u32::from(u16::from_slice_with(&header_sector_data[32..34], order));
+
+        // same for sector size, and a short sector can't be bigger than a
+        // sector
+        if k >= 16 || 2usize.pow(k) > self.sec_size || (self.version_number == 4 && k != 6) {
+            return Err(Error::InvalidHeaderField {
+                offset: 32,
+                value: k,
+            });
         }
 
         self.short_sec_size = 2usize.pow(k);
 
         // Get the number of FAT sectors within the file.
-        let alloc_size = usize::from_slice(&header_sector_data[44..48]);
+        let alloc_size = self.sector_count_at(&header_sector_data, 44)?;
 
         // Total number of sectors used for the sector allocation table
-        let total_sector_alloc_table = Vec::<u32>::with_capacity(alloc_size);
+        let total_sector_alloc_table =
+            Vec::<u32>::with_capacity(alloc_size * (self.sec_size / constants::U32_SIZE));
 
         // SecID of the first sector of directory stream and Read DIFAT Start Sector Location
-        let difat_sector_alloc_table: Vec<u32> = vec![u32::from_slice(&header_sector_data[48..52])];
+        let difat_sector_alloc_table: Vec<u32> =
+            vec![u32::from_slice_with(&header_sector_data[48..52], order)];
 
         // Minimum size of a standard stream (bytes)
-        self.minimum_standard_stream_size = usize::from_slice(&header_sector_data[56..60]);
+        self.minimum_standard_stream_size =
+            u64::from(u32::from_slice_with(&header_sector_data[56..60], order));
 
         // standard says that this value has to be greater
         // or equals to 4096
-        if self.minimum_standard_stream_size < 4096usize {
-            return Err(Error::InvalidOLEFile);
+        if self.minimum_standard_stream_size < 4096 {
+            self.recover(Error::InvalidHeaderField {
+                offset: 56,
+                value: self.minimum_standard_stream_size as u32,
+            })?;
+            self.minimum_standard_stream_size = 4096;
         }
 
-        // println!("HIER4");
-
         // secID of the first sector of the SSAT & Total number
         // of sectors used for the short-sector allocation table
         let mut ssat = Vec::with_capacity(
-            usize::from_slice(&header_sector_data[64..68]) * (self.sec_size / 4),
+            self.sector_count_at(&header_sector_data, 64)? * (self.sec_size / 4),
         );
-        ssat.push(u32::from_slice(&header_sector_data[60..64]));
-
-        // secID of first sector of the master sector allocation table
-        // & Total number of sectors used for
-        // the master sector allocation table
-
-        let difat_sectors = usize::from_slice(&header_sector_data[72..76]);
-
-        let msat_size = 109
-            + if difat_sectors == constants::SECID_END_OF_CHAIN as usize {
-                0
-            } else {
-                difat_sectors
-            };
-
-        self.main_sat = Vec::with_capacity(msat_size);
+        ssat.push(u32::from_slice_with(&header_sector_data[60..64], order));
 
         self.sat = total_sector_alloc_table;
         self.dir_sat = difat_sector_alloc_table;
         self.ssat = ssat;
 
-        // println!("HIER5");
-
         // now we build the MSAT
-        self.build_master_sector_allocation_table(&header_sector_data)?;
+        self.build_master_sector_allocation_table(&header_sector_data, alloc_size)?;
+
+        self.header = Header::parse(&header_sector_data);
 
         Ok(())
     }
 
-    /// Dump Header
-    pub fn dump_header(&self, header: &[u8]) {
-        for (e, data) in header.chunks_exact(constants::U32_SIZE).enumerate() {
-            let d = u32::from_slice(data);
-
-            let byte = e * 4;
-            match byte {
-                0 => println!("SIGNATURE"),
-                16 => println!("CLSID"),
-                48 => println!("Dir Start Sector"),
-                68 => {
-                    println!("Fist DIFAT ector Location");
-                    let loc = d.wrapping_mul(512);
-                    print!("\t{e:3} - {byte:3}: 0x{d:8x} [{d}] LOC: 0x{loc:8x} [{loc}] -- ");
-                }
-                72 => {
-                    println!("Number of DIFAT");
-                }
-                76 => println!("DIFAT"),
-                _ => (),
-            }
-
-            println!("\t{e:3} - {byte:3}: 0x{d:8x} [{d}]");
+    /// Returns the number of sectors of the header field at `offset`,
+    /// checking that the file can hold that many sectors. In lenient mode,
+    /// a count too big is lowered to the number of sectors of the file.
+    fn sector_count_at(&mut self, header: &[u8], offset: usize) -> Result<usize, Error> {
+        let count = u32::from_slice_with(&header[offset..offset + 4], self.byte_order);
+        let file_sectors = self.source.len().div_ceil(self.sec_size);
+        if count as usize > file_sectors {
+            self.recover(Error::InvalidHeaderField {
+                offset,
+                value: count,
+            })?;
+            Ok(file_sectors)
+        } else {
+            Ok(count as usize)
         }
     }
 
-    /// Dump sector
-    pub fn dump_fat(&self) {
-        print!("\n\t\tDUMP FAT TABLE");
-        for (e, &sec_id) in self.sat.iter().enumerate() {
-            let newline = (e & 0x3) == 0;
-            if newline {
-                print!("\n {e:4x}: ");
-            }
-
-            if (0..=constants::SECID_MAX).contains(&sec_id) {
-                print!("{sec_id:8x}");
-            } else if sec_id == constants::SECID_FAT_SECTOR {
-                print!("FATSECT ");
-            } else if sec_id == constants::SECID_END_OF_CHAIN {
-                print!("EOFCHAIN");
-            } else if sec_id == constants::SECID_FREE_SECTOR {
-                print!("FREE    ");
-            } else if sec_id == constants::SECID_DIFAT_SECTOR {
-                print!("DIFSECT ");
-            } else {
-                print!("\t{e:3} - UNKNOWN 0x{sec_id:8x}");
-            }
-            print!(" ");
-        }
+    /// Returns the 512 bytes of the header.
+    pub(crate) fn read_header(&self) -> Result<Vec<u8>, Error> {
+        Ok(self.source.block(0, constants::HEADER_SIZE)?.to_vec())
     }
 
     /// Build the Master Sector Allocation Table (MSAT)
-    fn build_master_sector_allocation_table(&mut self, header: &[u8]) -> Result<(), Error> {
+    ///
+    /// In lenient mode, the DIFAT is read up to where it breaks.
+    fn build_master_sector_allocation_table(
+        &mut self,
+        header: &[u8],
+        fat_sectors: usize,
+    ) -> Result<(), Error> {
         self.main_sat.clear();
 
         // First, we build the master sector allocation table from the header
         let mut total_sec_id_read = self.read_sec_ids(&header[76..]);
 
-        let mut difat_sector_id = u32::from_slice(&header[68..72]);
-        let difet_sectors = usize::from_slice(&header[72..76]);
+        let difat_sector_id = u32::from_slice_with(&header[68..72], self.byte_order);
+        let difet_sectors = self.sector_count_at(header, 72)?;
 
         // Check if additional sectors are used for building the msat
         if difat_sector_id != constants::SECID_END_OF_CHAIN && difet_sectors > 0 {
-            // println!("total_sec_id_read {total_sec_id_read}");
-            // return Err(super::error::Error::NotImplementedYet);
-            let sec_size = self.sec_size;
-
-            for _ in 0..difet_sectors {
-                let buffer = self.read_sector(difat_sector_id)?.to_vec();
-
-                assert!(buffer.len() == sec_size);
-
-                let b = &buffer;
-                // self.dump_difat_sector(b);
-                let found = self.read_sec_ids(&b[0..sec_size - 4]);
-
-                total_sec_id_read += found;
-
-                difat_sector_id = u32::from_slice(&b[sec_size - 4..sec_size]);
-
-                if difat_sector_id != constants::SECID_END_OF_CHAIN
-                    && difat_sector_id != constants::SECID_FREE_SECTOR
-                {
-                    panic!("Invalid DIFAT ending!");
-                }
+            if let Err(error) =
+                self.read_difat_sectors(difat_sector_id, difet_sectors, &mut total_sec_id_read)
+            {
+                self.recover(error)?;
             }
         }
 
-        if total_sec_id_read != self.sat.capacity() {
-            println!("Found {total_sec_id_read} Reported {}", self.sat.capacity());
-            return Err(Error::InvalidOLEFile);
+        if total_sec_id_read != fat_sectors {
+            self.recover(Error::InvalidHeaderField {
+                offset: 44,
+                value: u32::from_slice_with(&header[44..48], self.byte_order),
+            })?;
         }
 
         Ok(())
     }
 
+    /// Reads the `count` DIFAT sectors starting at `difat_sector_id`,
+    /// adding the number of FAT sector ids found to `total_sec_id_read`.
+    fn read_difat_sectors(
+        &mut self,
+        mut difat_sector_id: u32,
+        count: usize,
+        total_sec_id_read: &mut usize,
+    ) -> Result<(), Error> {
+        let sec_size = self.sec_size;
+
+        // Offset of the current DIFAT sector id
+        let mut offset = 68;
+        let mut visited = HashSet::new();
+        for i in 0..count {
+            if difat_sector_id > constants::SECID_MAX {
+                return Err(Error::InvalidSectorId {
+                    sector_id: difat_sector_id,
+                    offset,
+                });
+            }
+            if !visited.insert(difat_sector_id) {
+                return Err(Error::ChainCycle {
+                    sector_id: difat_sector_id,
+                    offset,
+                });
+            }
+            let buffer = self.read_sector(difat_sector_id)?.to_vec();
+            self.difat_sectors.push(difat_sector_id);
+
+            let b = &buffer;
+            let found = self.read_sec_ids(&b[0..sec_size - 4]);
+
+            *total_sec_id_read += found;
+
+            offset = self.sector_offset(difat_sector_id)? + sec_size - 4;
+            difat_sector_id = u32::from_slice_with(&b[sec_size - 4..sec_size], self.byte_order);
+
+            // The last DIFAT sector ends the chain.
+            if i + 1 == count
+                && difat_sector_id != constants::SECID_END_OF_CHAIN
+                && difat_sector_id != constants::SECID_FREE_SECTOR
+            {
+                return Err(Error::InvalidSectorId {
+                    sector_id: difat_sector_id,
+                    offset,
+                });
+            }
+        }
+        Ok(())
+    }
+
     /// Reads all the DIFAT entries.
     /// But skip the `FREE_SECID_U32` enties, but don´t assum that a `FREE_SECID_U32` entry is the end of the chain!
     /// Returns: Number of FAT sector entries found
     fn read_sec_ids(&mut self, buffer: &[u8]) -> usize {
         let mut i = 0usize;
-        let max_sec_ids = u32::try_from(self.body.len() / self.sec_size)
-            .expect("Your platform is not supported!");
+        let max_sec_ids = u32::try_from(self.source.len() / self.sec_size).unwrap_or(u32::MAX);
 
         for data in buffer.chunks_exact(constants::U32_SIZE) {
-            let secid = u32::from_slice(data);
+            let secid = u32::from_slice_with(data, self.byte_order);
 
             // Skip the free entries
             if secid == constants::SECID_FREE_SECTOR {
@@ -267,7 +405,7 @@ impl<'ole> Reader<'ole> {
             }
 
             if secid >= max_sec_ids {
-                println!("\t\tsecid oob {secid}/{max_sec_ids}");
+                debug!("FAT sector {secid:#x} is beyond the end of the file, ignored");
                 break;
             }
 
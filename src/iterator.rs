@@ -14,17 +14,24 @@
 //
 // Author: zadig <thomas chr(0x40) bailleux.me>
 
-use super::{entry::Entry, ole::Reader};
+use super::{
+    entry::{compare_names, Entry, EntryType},
+    error::Error,
+    ole::Reader,
+};
 
 /// Iterator for entries inside an OLE file.
 pub struct OLEIterator<'a> {
-    ole: &'a Reader<'a>,
+    entries: &'a [Entry],
     curr: usize,
 }
 
 impl<'a> OLEIterator<'a> {
     pub(crate) fn new(ole: &'a Reader) -> OLEIterator<'a> {
-        OLEIterator { ole, curr: 0 }
+        OLEIterator {
+            entries: ole.entries.as_deref().unwrap_or_default(),
+            curr: 0,
+        }
     }
 }
 
@@ -32,9 +39,8 @@ impl<'a> Iterator for OLEIterator<'a> {
     type Item = &'a Entry;
 
     fn next(&mut self) -> Option<&'a Entry> {
-        let entries = self.ole.entries.as_ref().unwrap();
-        if self.curr < entries.len() {
-            let entry = &entries[self.curr];
+        if self.curr < self.entries.len() {
+            let entry = &self.entries[self.curr];
             self.curr += 1;
             Some(entry)
         } else {
@@ -42,3 +48,157 @@ impl<'a> Iterator for OLEIterator<'a> {
         }
     }
 }
+
+/// Depth-first iterator over the storages and streams of an OLE file.
+///
+/// Yields the path, the depth and the entry, starting with the root
+/// storage (path `/`, depth 0). Children of a storage are visited in the
+/// order the compound file format sorts names.
+pub struct Walk<'a> {
+    entries: &'a [Entry],
+    stack: Vec<(String, usize, u32)>,
+}
+
+impl<'a> Walk<'a> {
+    pub(crate) fn new(ole: &'a Reader) -> Walk<'a> {
+        let entries = ole.entries.as_deref().unwrap_or_default();
+        let root = ole.root_entry.unwrap_or(0);
+        let stack = if entries.is_empty() {
+            Vec::new()
+        } else {
+            vec![(String::from("/"), 0, root)]
+        };
+        Walk { entries, stack }
+    }
+}
+
+impl<'a> Iterator for Walk<'a> {
+    type Item = (String, usize, &'a Entry);
+
+    fn next(&mut self) -> Option<(String, usize, &'a Entry)> {
+        let (path, depth, id) = self.stack.pop()?;
+        let entry = &self.entries[id as usize];
+
+        let prefix = path.trim_end_matches('/');
+        for child in sorted_children(self.entries, entry).into_iter().rev() {
+            let child_path = format!("{prefix}/{}", child.name());
+            self.stack.push((child_path, depth + 1, child.id()));
+        }
+
+        Some((path, depth, entry))
+    }
+}
+
+/// Returns the children of `entry`, sorted the way the compound file format
+/// sorts names.
+fn sorted_children<'a>(entries: &'a [Entry], entry: &Entry) -> Vec<&'a Entry> {
+    let mut children: Vec<&Entry> = entry
+        .children_nodes()
+        .iter()
+        .filter_map(|&id| entries.get(id as usize))
+        .collect();
+    children.sort_by(|a, b| compare_names(a.name(), b.name()));
+    children
+}
+
+impl<'ole> Reader<'ole> {
+    /// Returns a depth-first iterator over the storages and streams, with
+    /// their path and depth.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ole;
+    /// let parser = ole::Reader::from_path("assets/sample.doc").unwrap();
+    ///
+    /// for (path, depth, entry) in parser.walk() {
+    ///   println!("{}{} ({} bytes)", "  ".repeat(depth), path, entry.len());
+    /// }
+    /// ```
+    pub fn walk(&self) -> Walk<'_> {
+        Walk::new(self)
+    }
+
+    /// Returns the direct children of the storage at `path`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ole;
+    /// let parser = ole::Reader::from_path("assets/sample.doc").unwrap();
+    ///
+    /// for entry in parser.read_storage("ObjectPool").unwrap() {
+    ///   println!("{}", entry.name());
+    /// }
+    /// ```
+    pub fn read_storage(&self, path: &str) -> Result<Vec<&Entry>, Error> {
+        let entry = self.entry_by_path(path)?;
+        match entry._type() {
+            EntryType::RootStorage | EntryType::UserStorage => Ok(sorted_children(
+                self.entries.as_deref().unwrap_or_default(),
+                entry,
+            )),
+            _ => Err(Error::NotAStorage(path.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Error, Reader};
+
+    #[test]
+    fn walk() {
+        let ole = Reader::from_path("./assets/sample.doc").unwrap();
+        let walked: Vec<(String, usize)> =
+            ole.walk().map(|(path, depth, _)| (path, depth)).collect();
+
+        // Empty entries are not reachable.
+        assert_eq!(walked.len(), ole.iterate().count());
+        assert_eq!(walked[0], ("/".to_string(), 0));
+        let pool = walked
+            .iter()
+            .position(|(path, _)| path == "/ObjectPool")
+            .unwrap();
+        assert_eq!(walked[pool + 1], ("/ObjectPool/_1106396471".to_string(), 2));
+        assert_eq!(walked[pool + 2].1, 3);
+        for (path, _, entry) in ole.walk() {
+            assert_eq!(ole.entry_by_path(&path).unwrap().id(), entry.id());
+        }
+    }
+
+    #[test]
+    fn walk_skips_empty_entries() {
+        let ole = Reader::from_path("./assets/sample.ppt").unwrap();
+        assert_eq!(ole.walk().count(), 6);
+        assert_eq!(ole.iterate().count(), 8);
+    }
+
+    #[test]
+    fn read_storage() {
+        let ole = Reader::from_path("./assets/sample.doc").unwrap();
+        let names: Vec<&str> = ole
+            .read_storage("ObjectPool/_1106396471")
+            .unwrap()
+            .iter()
+            .map(|e| e.name())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "\u{1}Ole",
+                "\u{3}PRINT",
+                "\u{1}CompObj",
+                "\u{3}ObjInfo",
+                "Workbook",
+                "\u{5}SummaryInformation",
+                "\u{5}DocumentSummaryInformation"
+            ]
+        );
+        assert_eq!(ole.read_storage("/").unwrap().len(), 7);
+        assert!(matches!(
+            ole.read_storage("WordDocument"),
+            Err(Error::NotAStorage(_))
+        ));
+    }
+}
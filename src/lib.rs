@@ -39,11 +39,12 @@
 //! // We're going to extract a file from the OLE storage
 //! let entry = parser.iterate().next().unwrap();
 //! let mut slice = parser.get_entry_slice(entry).unwrap();
-//! let mut buffer = Vec::<u8>::with_capacity(slice.len());
+//! let mut buffer = Vec::<u8>::new();
 //! slice.read_to_end(&mut buffer);
 //!
 //! // Saves the extracted file
-//! let mut extracted_file = std::fs::File::create("./file.bin").unwrap();
+//! let path = std::env::temp_dir().join("file.bin");
+//! let mut extracted_file = std::fs::File::create(path).unwrap();
 //! extracted_file.write_all(&buffer[..]);
 //! ```
 //!
@@ -51,18 +52,68 @@
 //!
 //! The `ole` crate is tested for rust 1.9 or greater.
 
+// Sector ids and sizes are 32 bits values, which are used as `usize`.
+#[cfg(target_pointer_width = "16")]
+compile_error!("16 bits platforms aren't supported");
+
+/// Logs a debug message with `log::debug!`, when the `log` feature is
+/// enabled.
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "log")]
+        log::debug!($($arg)*);
+        #[cfg(not(feature = "log"))]
+        let _ = format_args!($($arg)*);
+    };
+}
+
+/// Logs a warning with `log::warn!`, when the `log` feature is enabled.
+macro_rules! warn {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "log")]
+        log::warn!($($arg)*);
+        #[cfg(not(feature = "log"))]
+        let _ = format_args!($($arg)*);
+    };
+}
+
 mod ole;
 pub use crate::ole::Reader;
+mod options;
+pub use crate::options::ReaderOptions;
 pub(crate) mod iterator;
-pub use crate::iterator::OLEIterator;
+pub use crate::iterator::{OLEIterator, Walk};
 mod error;
 pub use crate::error::Error;
 pub(crate) mod constants;
 pub(crate) mod entry;
 pub(crate) mod header;
+pub(crate) mod orphan;
+pub use crate::header::Header;
+pub(crate) mod path;
 pub(crate) mod sat;
+pub use crate::sat::SectorId;
 pub(crate) mod util;
 pub use crate::entry::Entry;
 pub use crate::entry::EntrySlice;
 pub use crate::entry::EntryType;
+pub use crate::util::ByteOrder;
+mod filetime;
+pub use crate::filetime::FileTime;
+mod clsid;
+pub use crate::clsid::Clsid;
 pub(crate) mod sector;
+mod sector_map;
+pub use crate::sector_map::SectorKind;
+pub(crate) mod source;
+mod validate;
+pub use crate::validate::Violation;
+mod writer;
+pub use crate::writer::{Version, Writer};
+mod editor;
+pub use crate::editor::Editor;
+mod shared;
+pub use crate::shared::{SharedReader, Stream};
+pub mod property_set;
+#[cfg(test)]
+mod sample;
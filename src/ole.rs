@@ -16,11 +16,14 @@
 
 use std::{
     self,
-    io::{BufReader, Read},
+    io::{BufReader, Read, Seek},
     vec::Vec,
 };
 
-use super::{constants, entry::Entry, error::Error, iterator::OLEIterator};
+use super::{
+    constants, entry::Entry, error::Error, header::Header, iterator::OLEIterator,
+    options::ReaderOptions, source::Source, util::ByteOrder,
+};
 
 /// An OLE file reader.
 ///
@@ -40,10 +43,9 @@ use super::{constants, entry::Entry, error::Error, iterator::OLEIterator};
 ///   println!("{}", entry);
 /// }
 /// ```
-
 pub struct Reader<'ole> {
-    /// Buffer for reading from the source.
-    pub(crate) buf_reader: BufReader<Box<dyn Read + 'ole>>,
+    /// Where the bytes of the file come from.
+    pub(crate) source: Source<'ole>,
 
     /// Unique identifier.
     pub(crate) uid: [u8; 16],
@@ -67,27 +69,44 @@ pub struct Reader<'ole> {
     pub(crate) dir_sat: Vec<u32>,
 
     /// Minimum size of a standard stream size.
-    pub(crate) minimum_standard_stream_size: usize,
+    pub(crate) minimum_standard_stream_size: u64,
 
     /// Short Sector Allocation Table.
     pub(crate) ssat: Vec<u32>,
 
+    /// SecIDs of the sectors holding the SSAT.
+    pub(crate) ssat_sectors: Vec<u32>,
+
     /// Master Sector Allocation Table.
     pub(crate) main_sat: Vec<u32>,
 
-    /// Body of the file.
-    pub(crate) body: Vec<u8>,
+    /// SecIDs of the DIFAT sectors.
+    pub(crate) difat_sectors: Vec<u32>,
 
     /// Directory entries.
     pub(crate) entries: Option<Vec<Entry>>,
 
     /// DirID of the root entry.
     pub(crate) root_entry: Option<u32>,
+
+    /// The header, as found in the file.
+    pub(crate) header: Header,
+
+    /// Byte order of the header, the allocation tables and the directory.
+    pub(crate) byte_order: ByteOrder,
+
+    /// Limits enforced while parsing.
+    pub(crate) options: ReaderOptions,
+
+    /// Errors recovered from in lenient mode.
+    pub(crate) warnings: Vec<Error>,
 }
 
 impl<'ole> Reader<'ole> {
     /// Constructs a new `Reader`.
     ///
+    /// `readable` is read into memory, so the `Reader` doesn't borrow it.
+    ///
     /// # Examples
     ///
     /// ```
@@ -95,22 +114,172 @@ impl<'ole> Reader<'ole> {
     /// let mut my_resume = std::fs::File::open("assets/Thumbs.db").unwrap();
     /// let mut parser = ole::Reader::new(my_resume).unwrap();
     /// ```
-    pub fn new<T: 'ole>(readable: T) -> std::result::Result<Reader<'ole>, Error>
+    pub fn new<T>(readable: T) -> std::result::Result<Reader<'ole>, Error>
     where
         T: Read,
     {
-        let mut read: BufReader<Box<dyn Read>> = BufReader::new(Box::new(readable));
+        Reader::with_options(readable, ReaderOptions::default())
+    }
 
-        let mut buf = Vec::<u8>::with_capacity(10_000_000);
+    /// Constructs a new `Reader`, enforcing the limits of `options`.
+    ///
+    /// At most `max_file_size` bytes are read from `readable`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ole::{Reader, ReaderOptions};
+    /// let file = std::fs::File::open("assets/Thumbs.db").unwrap();
+    /// let options = ReaderOptions::default().max_stream_size(1 << 20);
+    /// let mut parser = Reader::with_options(file, options).unwrap();
+    /// ```
+    pub fn with_options<T>(readable: T, options: ReaderOptions) -> Result<Reader<'ole>, Error>
+    where
+        T: Read,
+    {
+        let limit = u64::try_from(options.max_file_size)
+            .unwrap_or(u64::MAX)
+            .saturating_add(1);
+        let mut buf = Vec::new();
+        BufReader::new(readable).take(limit).read_to_end(&mut buf)?;
+        Reader::from_source(Source::Memory(buf), options)
+    }
 
-        let body = read.read_to_end(&mut buf)?;
+    /// Constructs a new `Reader` which reads `seekable` on demand.
+    ///
+    /// Only the header, the allocation tables and the directory are read
+    /// when the `Reader` is built; the content of the streams is read when
+    /// their `EntrySlice` is read. Memory usage stays proportional to what
+    /// is actually read, which makes it suitable for very large files.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ole;
+    /// let file = std::fs::File::open("assets/sample.ppt").unwrap();
+    /// let mut parser = ole::Reader::from_seekable(file).unwrap();
+    /// ```
+    pub fn from_seekable<T>(seekable: T) -> Result<Reader<'ole>, Error>
+    where
+        T: Read + Seek + 'ole,
+    {
+        Reader::from_seekable_with_cache(seekable, 0)
+    }
 
-        println!("Readed file {body}");
+    /// Constructs a new `Reader` which reads `seekable` on demand, and keeps
+    /// the last `cache_sectors` sectors read in memory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ole;
+    /// let file = std::fs::File::open("assets/sample.ppt").unwrap();
+    /// let mut parser = ole::Reader::from_seekable_with_cache(file, 64).unwrap();
+    /// ```
+    pub fn from_seekable_with_cache<T>(
+        seekable: T,
+        cache_sectors: usize,
+    ) -> Result<Reader<'ole>, Error>
+    where
+        T: Read + Seek + 'ole,
+    {
+        Reader::from_seekable_with_options(seekable, cache_sectors, ReaderOptions::default())
+    }
 
-        // panic!("bye");
+    /// Constructs a new `Reader` which reads `seekable` on demand, keeps
+    /// the last `cache_sectors` sectors read in memory, and enforces the
+    /// limits of `options`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ole::{Reader, ReaderOptions};
+    /// let file = std::fs::File::open("assets/sample.ppt").unwrap();
+    /// let options = ReaderOptions::default().max_file_size(1 << 30);
+    /// let mut parser = Reader::from_seekable_with_options(file, 64, options).unwrap();
+    /// ```
+    pub fn from_seekable_with_options<T>(
+        seekable: T,
+        cache_sectors: usize,
+        options: ReaderOptions,
+    ) -> Result<Reader<'ole>, Error>
+    where
+        T: Read + Seek + 'ole,
+    {
+        Reader::from_source(Source::seekable(seekable, cache_sectors)?, options)
+    }
 
+    /// Constructs a new `Reader` from a memory-mapped file.
+    ///
+    /// Sectors and `EntrySlice`s borrow directly from the mapping, the file
+    /// is never copied.
+    ///
+    /// Requires the `mmap` feature.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated, by this process or
+    /// another one, while the `Reader` or any `EntrySlice` is alive: reading
+    /// a mapping whose file changed is undefined behavior. See
+    /// `memmap2::Mmap::map`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ole;
+    /// // SAFETY: the file isn't modified while it is read.
+    /// let mut parser = unsafe { ole::Reader::from_mmap("assets/Thumbs.db") }.unwrap();
+    /// ```
+    #[cfg(feature = "mmap")]
+    pub unsafe fn from_mmap(path: &str) -> Result<Reader<'ole>, Error> {
+        // SAFETY: the caller upholds the contract of `from_mmap`, which is
+        // the one of `from_mmap_with_options`.
+        unsafe { Reader::from_mmap_with_options(path, ReaderOptions::default()) }
+    }
+
+    /// Constructs a new `Reader` from a memory-mapped file, enforcing the
+    /// limits of `options`.
+    ///
+    /// Requires the `mmap` feature.
+    ///
+    /// # Safety
+    ///
+    /// Same as `from_mmap`: the file must not be modified or truncated while
+    /// the `Reader` or any `EntrySlice` is alive.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ole::{Reader, ReaderOptions};
+    /// let options = ReaderOptions::default().max_entries(1024);
+    /// // SAFETY: the file isn't modified while it is read.
+    /// let mut parser =
+    ///     unsafe { Reader::from_mmap_with_options("assets/Thumbs.db", options) }.unwrap();
+    /// ```
+    #[cfg(feature = "mmap")]
+    pub unsafe fn from_mmap_with_options(
+        path: &str,
+        options: ReaderOptions,
+    ) -> Result<Reader<'ole>, Error> {
+        let file = std::fs::File::open(path)?;
+        // SAFETY: the caller guarantees that the file isn't modified while
+        // it is mapped.
+        let mmap = unsafe { memmap2::Mmap::map(&file)? };
+        Reader::from_source(Source::Mmap(mmap), options)
+    }
+
+    pub(crate) fn from_source(
+        source: Source<'ole>,
+        options: ReaderOptions,
+    ) -> Result<Reader<'ole>, Error> {
+        if source.len() > options.max_file_size {
+            return Err(Error::LimitExceeded {
+                limit: "file size",
+                max: options.max_file_size,
+            });
+        }
         let mut t = Reader {
-            buf_reader: read,
+            source,
             uid: [0u8; constants::UID_SIZE],
             revision_number: 0,
             version_number: 0,
@@ -120,23 +289,65 @@ impl<'ole> Reader<'ole> {
             dir_sat: Vec::new(),
             minimum_standard_stream_size: 0,
             ssat: Vec::new(),
+            ssat_sectors: Vec::new(),
             main_sat: vec![constants::SECID_FREE_SECTOR; 109],
-            body: buf,
+            difat_sectors: Vec::new(),
             entries: None,
             root_entry: None,
+            header: Header::default(),
+            byte_order: ByteOrder::LittleEndian,
+            options,
+            warnings: Vec::new(),
         };
-        println!("parse_header");
         t.parse_header()?;
-        println!("build_sat");
+        debug!(
+            "version {}.{:#x}, {} bytes sectors, {} bytes mini sectors",
+            t.version_number, t.revision_number, t.sec_size, t.short_sec_size
+        );
         t.build_sat()?;
-
-        // t.dump_fat();
-        println!("build_dir_entries");
+        debug!(
+            "{} FAT entries, {} mini FAT entries, {} directory sectors",
+            t.sat.len(),
+            t.ssat.len(),
+            t.dir_sat.len()
+        );
         t.build_directory_entries()?;
-        println!("Done");
+        debug!(
+            "{} directory entries",
+            t.entries.as_ref().map_or(0, Vec::len)
+        );
         Ok(t)
     }
 
+    /// Returns the inconsistencies found while parsing a file in lenient
+    /// mode, in the order they were found. Always empty otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ole::{Reader, ReaderOptions};
+    /// let file = std::fs::File::open("assets/Thumbs.db").unwrap();
+    /// let parser = Reader::with_options(file, ReaderOptions::default().lenient(true)).unwrap();
+    /// for warning in parser.warnings() {
+    ///     println!("{warning}");
+    /// }
+    /// ```
+    pub fn warnings(&self) -> &[Error] {
+        &self.warnings
+    }
+
+    /// Records `error` as a warning in lenient mode, so parsing can go on,
+    /// or returns it. Exceeded limits are always returned.
+    pub(crate) fn recover(&mut self, error: Error) -> Result<(), Error> {
+        if self.options.lenient && !matches!(error, Error::LimitExceeded { .. }) {
+            warn!("{error}");
+            self.warnings.push(error);
+            Ok(())
+        } else {
+            Err(error)
+        }
+    }
+
     /// Constructs a new `Reader` from a file.
     ///
     /// # Examples
@@ -145,7 +356,7 @@ impl<'ole> Reader<'ole> {
     /// use ole;
     /// let mut parser = ole::Reader::from_path("assets/Thumbs.db").unwrap();
     /// ```
-    pub fn from_path(path: &str) -> Result<Reader, Error> {
+    pub fn from_path(path: &str) -> Result<Reader<'ole>, Error> {
         let f = std::fs::File::open(path).map_err(Error::IOError)?;
         Reader::new(f)
     }
@@ -162,14 +373,45 @@ impl<'ole> Reader<'ole> {
     ///   println!("Entry {}", entry.name());
     /// }
     /// ```
-    pub fn iterate(&self) -> OLEIterator {
+    pub fn iterate(&self) -> OLEIterator<'_> {
         OLEIterator::new(self)
     }
 }
 
 #[cfg(test)]
+#[allow(
+    clippy::assertions_on_constants,
+    clippy::bool_assert_comparison,
+    clippy::op_ref
+)]
 mod tests {
     use super::{constants, Error, Reader};
+    use crate::{ByteOrder, Clsid, Editor, EntryType, ReaderOptions, Version, Writer};
+    use std::{
+        cell::Cell,
+        io::{Cursor, Read, Seek, SeekFrom},
+        rc::Rc,
+    };
+
+    /// Counts the bytes read from the inner reader.
+    struct CountingReader<T> {
+        inner: T,
+        count: Rc<Cell<usize>>,
+    }
+
+    impl<T: Read> Read for CountingReader<T> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = self.inner.read(buf)?;
+            self.count.set(self.count.get() + n);
+            Ok(n)
+        }
+    }
+
+    impl<T: Seek> Seek for CountingReader<T> {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
 
     #[test]
     fn instance_nok() {
@@ -231,6 +473,27 @@ mod tests {
         assert_eq!(&[0x0u8; 16] == &ole.uid[..], true);
     }
 
+    #[test]
+    fn entry_names() {
+        let ole = Reader::from_path("./assets/Thumbs.db").unwrap();
+        let names: Vec<&str> = ole.iterate().map(|entry| entry.name()).collect();
+        assert_eq!(names[0], "Root Entry");
+        assert!(names.iter().all(|name| !name.contains('\0')));
+    }
+
+    #[test]
+    fn directory_sector_starting_like_a_free_sector_id() {
+        let mut buf = std::fs::read("./assets/Thumbs.db").unwrap();
+        let count = Reader::new(&buf[..]).unwrap().iterate().count();
+        // The name of the root entry, at the start of the first directory
+        // sector, begins with bytes that read as a free sector id.
+        let dir_sector = u32::from_le_bytes(buf[48..52].try_into().unwrap()) as usize;
+        let offset = (dir_sector + 1) * 512;
+        buf[offset..offset + 4].fill(0xFF);
+        let ole = Reader::new(&buf[..]).unwrap();
+        assert_eq!(ole.iterate().count(), count);
+    }
+
     #[test]
     fn bad_sec_size() {
         let mut vec = constants::IDENTIFIER.to_vec();
@@ -325,4 +588,820 @@ mod tests {
             println!("{}", entry);
         }
     }
+
+    /// `sample_v4.doc` was written by `Writer` from the streams of
+    /// `sample.doc`, so only tests that both are read the same way.
+    #[test]
+    fn v4_same_content() {
+        let v3 = Reader::from_path("./assets/sample.doc").unwrap();
+        let v4 = Reader::from_path("./assets/sample_v4.doc").unwrap();
+        assert!(v4.warnings().is_empty());
+        assert_eq!(v3.walk().count(), v4.walk().count());
+        for ((path_a, _, a), (path_b, _, b)) in v3.walk().zip(v4.walk()) {
+            assert_eq!(path_a, path_b);
+            assert_eq!(a.clsid(), b.clsid());
+            // The root entry holds the mini stream, which is laid out
+            // differently in both files.
+            if a._type() != EntryType::UserStream {
+                continue;
+            }
+            let (mut buf_a, mut buf_b) = (Vec::new(), Vec::new());
+            v3.get_entry_slice(a)
+                .unwrap()
+                .read_to_end(&mut buf_a)
+                .unwrap();
+            v4.get_entry_slice(b)
+                .unwrap()
+                .read_to_end(&mut buf_b)
+                .unwrap();
+            assert_eq!(buf_a, buf_b);
+        }
+    }
+
+    #[test]
+    fn v4_header_fields() {
+        // 40 entries take more than one 4096 bytes directory sector.
+        let mut writer = Writer::new(Version::V4);
+        for i in 0..40 {
+            writer
+                .create_stream(&format!("Stream{i}"), &vec![i as u8; 100 + i * 200])
+                .unwrap();
+        }
+        let mut buf = Vec::new();
+        writer.write_to(&mut buf).unwrap();
+
+        {
+            let ole = Reader::new(&buf[..]).unwrap();
+            assert_eq!(ole.dir_sat.len(), 2);
+            assert_eq!(ole.header().directory_sector_count(), 2);
+            for i in 0..40 {
+                let entry = ole.entry_by_path(&format!("Stream{i}")).unwrap();
+                let mut data = Vec::new();
+                ole.get_entry_slice(entry)
+                    .unwrap()
+                    .read_to_end(&mut data)
+                    .unwrap();
+                assert_eq!(data, vec![i as u8; 100 + i * 200]);
+            }
+        }
+
+        // The directory sector count must match the directory chain.
+        buf[40] = 1;
+        assert!(matches!(
+            Reader::new(&buf[..]),
+            Err(Error::InvalidHeaderField { offset: 40, .. })
+        ));
+        let options = ReaderOptions::default().lenient(true);
+        let warnings = Reader::with_options(&buf[..], options)
+            .unwrap()
+            .warnings()
+            .len();
+        assert_eq!(warnings, 1);
+        buf[40] = 2;
+
+        // A version 4 file has 4096 bytes sectors.
+        buf[30] = 9;
+        assert!(matches!(
+            Reader::new(&buf[..]),
+            Err(Error::InvalidHeaderField { offset: 30, .. })
+        ));
+    }
+
+    #[test]
+    fn stream_size_high_dword() {
+        for version in [Version::V3, Version::V4] {
+            let mut writer = Writer::new(version);
+            writer.create_stream("Stream", &[1u8; 5000]).unwrap();
+            let mut buf = Vec::new();
+            writer.write_to(&mut buf).unwrap();
+            let offset = {
+                let ole = Reader::new(&buf[..]).unwrap();
+                ole.entry_offset(ole.entry_by_path("Stream").unwrap().id())
+                    .unwrap()
+            };
+            buf[offset + 124] = 1;
+
+            let options = ReaderOptions::default().lenient(true);
+            let ole = Reader::with_options(&buf[..], options).unwrap();
+            let entry = ole.entry_by_path("Stream").unwrap();
+            let mut data = Vec::new();
+            let mut slice = ole.get_entry_slice(entry).unwrap();
+            slice.read_to_end(&mut data).unwrap();
+            if version == Version::V3 {
+                // The high DWORD is ignored.
+                assert!(ole.warnings().is_empty());
+                assert_eq!(entry.len(), 5000);
+                assert!(Reader::new(&buf[..]).is_ok());
+            } else {
+                // The size is above 4 GiB, but the chain holds 2 sectors.
+                assert_eq!(ole.warnings().len(), 1);
+                assert_eq!(entry.len(), (1 << 32) + 5000);
+                assert_eq!(slice.len(), (1 << 32) + 5000);
+                assert_eq!(slice.real_len(), 2 * 4096);
+                assert!(matches!(
+                    Reader::new(&buf[..]),
+                    Err(Error::InvalidEntry { offset: o, .. }) if o == offset + 120
+                ));
+                // The whole chain is read.
+                assert_eq!(data.len(), 2 * 4096);
+            }
+            assert_eq!(data[..5000], [1u8; 5000]);
+        }
+    }
+
+    #[test]
+    fn seekable_same_content() {
+        let memory = Reader::from_path("./assets/sample.ppt").unwrap();
+        let file = std::fs::File::open("./assets/sample.ppt").unwrap();
+        let seekable = Reader::from_seekable(file).unwrap();
+        for (a, b) in memory.iterate().zip(seekable.iterate()) {
+            assert_eq!(a.name(), b.name());
+            if a.is_empty() {
+                continue;
+            }
+            let (mut buf_a, mut buf_b) = (Vec::new(), Vec::new());
+            memory
+                .get_entry_slice(a)
+                .unwrap()
+                .read_to_end(&mut buf_a)
+                .unwrap();
+            seekable
+                .get_entry_slice(b)
+                .unwrap()
+                .read_to_end(&mut buf_b)
+                .unwrap();
+            assert_eq!(buf_a, buf_b);
+        }
+    }
+
+    #[test]
+    fn seek_and_read_at() {
+        let ole = Reader::from_path("./assets/sample.doc").unwrap();
+        // A stream in the FAT and a stream in the mini FAT.
+        for path in ["1Table", "\u{5}SummaryInformation"] {
+            let mut data = Vec::new();
+            let mut slice = ole.open_stream(path).unwrap();
+            slice.read_to_end(&mut data).unwrap();
+            let len = data.len();
+
+            for (offset, n) in [(0, 10), (60, 10), (511, 2), (1000, 3000), (len - 5, 10)] {
+                let mut buf = vec![0u8; n];
+                let read = slice.read_at(offset as u64, &mut buf).unwrap();
+                let expected = &data[offset.min(len)..(offset + n).min(len)];
+                assert_eq!(&buf[..read], expected);
+
+                let position = slice.seek(SeekFrom::Start(offset as u64)).unwrap();
+                assert_eq!(position, offset as u64);
+                let read = slice.read(&mut buf).unwrap();
+                assert_eq!(&buf[..read], expected);
+            }
+            assert_eq!(slice.read_at(len as u64 + 10, &mut [0u8; 4]).unwrap(), 0);
+
+            slice.seek(SeekFrom::End(-4)).unwrap();
+            slice.seek(SeekFrom::Current(-2)).unwrap();
+            let mut buf = Vec::new();
+            slice.read_to_end(&mut buf).unwrap();
+            assert_eq!(buf, &data[len - 6..]);
+
+            assert!(slice.seek(SeekFrom::Current(-(len as i64) - 1)).is_err());
+            assert_eq!(slice.seek(SeekFrom::End(10)).unwrap(), len as u64 + 10);
+            assert_eq!(slice.read(&mut [0u8; 4]).unwrap(), 0);
+        }
+    }
+
+    #[test]
+    fn seekable_reads_on_demand() {
+        let data = std::fs::read("./assets/sample.ppt").unwrap();
+        let count = Rc::new(Cell::new(0));
+        let reader = CountingReader {
+            inner: Cursor::new(&data[..]),
+            count: count.clone(),
+        };
+        let ole = Reader::from_seekable_with_cache(reader, 128).unwrap();
+        let parsed = count.get();
+        assert!(parsed < data.len() / 10);
+
+        let entry = ole
+            .iterate()
+            .find(|e| e.name() == "\u{5}SummaryInformation")
+            .unwrap();
+        let mut buf = Vec::new();
+        ole.get_entry_slice(entry)
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        assert_eq!(buf.len() as u64, entry.len());
+        let read_once = count.get();
+        assert!(read_once > parsed);
+        assert!(read_once < data.len() / 10);
+
+        // The second read is served by the cache.
+        ole.get_entry_slice(entry)
+            .unwrap()
+            .read_to_end(&mut Vec::new())
+            .unwrap();
+        assert_eq!(count.get(), read_once);
+    }
+
+    /// Parses a hex dump, ignoring whitespace.
+    fn hex(dump: &str) -> Vec<u8> {
+        let digits: Vec<u8> = dump.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+        digits
+            .chunks(2)
+            .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).unwrap(), 16).unwrap())
+            .collect()
+    }
+
+    /// Appends unused directory entries to `sector`, up to `sector_size`.
+    fn fill_directory(sector: &mut Vec<u8>, sector_size: usize) {
+        while !sector.len().is_multiple_of(sector_size) {
+            let mut entry = [0u8; constants::DIRECTORY_ENTRY_SIZE];
+            entry[0x44..0x50].fill(0xFF);
+            sector.extend_from_slice(&entry);
+        }
+    }
+
+    #[test]
+    fn big_endian_hex_dump() {
+        // Header: version 3, byte order FF FE, 512 bytes sectors, one FAT
+        // sector (sector 0), the directory in sector 1, no mini FAT and no
+        // DIFAT sector.
+        let mut file = hex("
+            D0 CF 11 E0 A1 B1 1A E1  00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00  00 3E 00 03 FF FE 00 09
+            00 06 00 00 00 00 00 00  00 00 00 00 00 00 00 01
+            00 00 00 01 00 00 00 00  00 00 10 00 FF FF FF FE
+            00 00 00 00 FF FF FF FE  00 00 00 00 00 00 00 00
+            ");
+        file.resize(512, 0xFF);
+
+        // Sector 0, the FAT: itself, the directory, then the stream in
+        // sectors 2 to 9.
+        let mut fat = vec![constants::SECID_FAT_SECTOR, constants::SECID_END_OF_CHAIN];
+        fat.extend(3..10);
+        fat.push(constants::SECID_END_OF_CHAIN);
+        fat.resize(128, constants::SECID_FREE_SECTOR);
+        file.extend(fat.iter().flat_map(|id| id.to_be_bytes()));
+
+        // Sector 1, the directory: the root entry, with a CLSID and state
+        // bits, and a 4096 bytes stream starting in sector 2.
+        let mut directory = hex("
+            00 52 00 6F 00 6F 00 74  00 20 00 45 00 6E 00 74
+            00 72 00 79 00 00 00 00  00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00
+            00 16 05 01 FF FF FF FF  FF FF FF FF 00 00 00 01
+            00 02 09 06 00 00 00 00  C0 00 00 00 00 00 00 46
+            12 34 56 78 00 00 00 00  00 00 00 00 00 00 00 00
+            00 00 00 00 FF FF FF FE  00 00 00 00 00 00 00 00
+
+            00 44 00 61 00 74 00 61  00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00
+            00 0A 02 01 FF FF FF FF  FF FF FF FF FF FF FF FF
+            00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 02  00 00 10 00 00 00 00 00
+            ");
+        fill_directory(&mut directory, 512);
+        file.extend(directory);
+
+        let data: Vec<u8> = (0..4096u32).map(|i| (i % 251) as u8).collect();
+        file.extend(&data);
+
+        let ole = Reader::new(&file[..]).unwrap();
+        assert_eq!(ole.header().byte_order(), ByteOrder::BigEndian);
+        assert_eq!(ole.header().sector_size(), 512);
+        let names: Vec<&str> = ole.iterate().map(|entry| entry.name()).collect();
+        assert_eq!(names[..2], ["Root Entry", "Data"]);
+        let root = ole.iterate().next().unwrap();
+        assert_eq!(root.clsid(), Clsid::WORD_DOCUMENT);
+        assert_eq!(root.state_bits(), 0x1234_5678);
+
+        let mut buf = Vec::new();
+        ole.open_stream("Data")
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        assert_eq!(buf, data);
+    }
+
+    #[test]
+    fn v4_hex_dump() {
+        // Header: version 4, 4096 bytes sectors, one directory sector
+        // (sector 1), one FAT sector (sector 0), the mini FAT in sector 2
+        // and no DIFAT sector. The header is padded with zeros up to the
+        // first sector.
+        let mut file = hex("
+            D0 CF 11 E0 A1 B1 1A E1  00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00  3E 00 04 00 FE FF 0C 00
+            06 00 00 00 00 00 00 00  01 00 00 00 01 00 00 00
+            01 00 00 00 00 00 00 00  00 10 00 00 02 00 00 00
+            01 00 00 00 FE FF FF FF  00 00 00 00 00 00 00 00
+            ");
+        file.resize(512, 0xFF);
+        file.resize(4096, 0);
+
+        // Sector 0, the FAT: itself, the directory, the mini FAT, the mini
+        // stream, then `Data` in sectors 4 and 5.
+        let mut fat = hex("
+            FD FF FF FF FE FF FF FF  FE FF FF FF FE FF FF FF
+            05 00 00 00 FE FF FF FF
+            ");
+        fat.resize(4096, 0xFF);
+        file.extend(fat);
+
+        // Sector 1, the directory: the root entry, whose mini stream starts
+        // in sector 3, the 4100 bytes stream `Data` starting in sector 4,
+        // and its red right sibling `Small`, 50 bytes long in the mini
+        // stream.
+        let mut directory = hex("
+            52 00 6F 00 6F 00 74 00  20 00 45 00 6E 00 74 00
+            72 00 79 00 00 00 00 00  00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00
+            16 00 05 01 FF FF FF FF  FF FF FF FF 01 00 00 00
+            00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00
+            00 00 00 00 03 00 00 00  40 00 00 00 00 00 00 00
+
+            44 00 61 00 74 00 61 00  00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00
+            0A 00 02 01 FF FF FF FF  02 00 00 00 FF FF FF FF
+            00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00
+            00 00 00 00 04 00 00 00  04 10 00 00 00 00 00 00
+
+            53 00 6D 00 61 00 6C 00  6C 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00
+            0C 00 02 00 FF FF FF FF  FF FF FF FF FF FF FF FF
+            00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00  32 00 00 00 00 00 00 00
+            ");
+        fill_directory(&mut directory, 4096);
+        file.extend(directory);
+
+        // Sector 2, the mini FAT: `Small` in mini sector 0.
+        let mut mini_fat = hex("FE FF FF FF");
+        mini_fat.resize(4096, 0xFF);
+        file.extend(mini_fat);
+
+        // Sector 3, the mini stream.
+        let small: Vec<u8> = (0..50u8).map(|i| i + 100).collect();
+        let mut mini_stream = small.clone();
+        mini_stream.resize(4096, 0);
+        file.extend(mini_stream);
+
+        // Sectors 4 and 5, `Data`.
+        let data: Vec<u8> = (0..4100u32).map(|i| (i % 251) as u8).collect();
+        file.extend(&data);
+        file.resize(7 * 4096, 0);
+
+        let ole = Reader::new(&file[..]).unwrap();
+        assert_eq!(ole.header().major_version(), 4);
+        assert_eq!(ole.header().sector_size(), 4096);
+        assert_eq!(ole.header().directory_sector_count(), 1);
+        let names: Vec<&str> = ole.iterate().map(|entry| entry.name()).collect();
+        assert_eq!(names[..3], ["Root Entry", "Data", "Small"]);
+        assert_eq!(ole.validate().unwrap(), []);
+
+        let mut buf = Vec::new();
+        ole.open_stream("Data")
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        assert_eq!(buf, data);
+        buf.clear();
+        ole.open_stream("Small")
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        assert_eq!(buf, small);
+    }
+
+    /// Rewrites the little-endian file `buf` with a big-endian byte order.
+    /// Files with DIFAT sectors aren't supported.
+    fn to_big_endian(buf: &[u8]) -> Vec<u8> {
+        fn swap(buf: &mut [u8], width: usize) {
+            buf.chunks_exact_mut(width)
+                .for_each(|chunk| chunk.reverse());
+        }
+        fn swap_clsid(clsid: &mut [u8]) {
+            swap(&mut clsid[0..4], 4);
+            swap(&mut clsid[4..8], 2);
+        }
+
+        let ole = Reader::new(buf).unwrap();
+        let mut out = buf.to_vec();
+        let sec_size = ole.sec_size;
+        let sector = |id: u32| (id as usize + 1) * sec_size..(id as usize + 2) * sec_size;
+
+        out[28..30].copy_from_slice(&constants::BIG_ENDIAN_IDENTIFIER);
+        swap_clsid(&mut out[8..24]);
+        swap(&mut out[24..28], 2);
+        swap(&mut out[30..34], 2);
+        swap(&mut out[40..512], 4);
+
+        for &id in ole.main_sat.iter().chain(&ole.ssat_sectors) {
+            swap(&mut out[sector(id)], 4);
+        }
+        for &id in ole.dir_sat.iter() {
+            for entry in out[sector(id)].chunks_exact_mut(constants::DIRECTORY_ENTRY_SIZE) {
+                swap(&mut entry[0..66], 2);
+                swap(&mut entry[68..80], 4);
+                swap_clsid(&mut entry[80..96]);
+                swap(&mut entry[96..128], 4);
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn big_endian() {
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        let mut writer = Writer::new(Version::V3);
+        writer.create_storage("Storage").unwrap();
+        writer.create_stream("Storage/Small", b"small").unwrap();
+        writer.create_stream("Big", &data).unwrap();
+        writer.set_clsid("Storage", Clsid::WORD_DOCUMENT).unwrap();
+        writer.set_state_bits("Storage", 0x1234_5678).unwrap();
+        writer
+            .set_times("Storage", 0x01C1_3A42_D17D_8000, 0x0123_4567_89AB_CDEF)
+            .unwrap();
+        let mut little = Vec::new();
+        writer.write_to(&mut little).unwrap();
+
+        let big = to_big_endian(&little);
+        let ole = Reader::new(&big[..]).unwrap();
+        assert_eq!(ole.header().byte_order(), ByteOrder::BigEndian);
+        assert_eq!(ole.header().major_version(), 3);
+        assert_eq!(ole.header().sector_size(), 512);
+
+        let mut buf = Vec::new();
+        ole.open_stream("Big")
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        assert_eq!(buf, data);
+        buf.clear();
+        ole.open_stream("Storage/Small")
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        assert_eq!(buf, b"small");
+
+        let storage = ole.entry_by_path("Storage").unwrap();
+        assert_eq!(storage.clsid(), Clsid::WORD_DOCUMENT);
+        assert_eq!(storage.state_bits(), 0x1234_5678);
+        assert_eq!(storage.created().as_u64(), 0x01C1_3A42_D17D_8000);
+        assert_eq!(storage.modified().as_u64(), 0x0123_4567_89AB_CDEF);
+
+        assert!(matches!(
+            Editor::from_reader(ole),
+            Err(Error::NotImplementedYet)
+        ));
+    }
+
+    /// Returns a small valid file, with the offsets of the directory entry
+    /// of the root storage and of the stream `Big`.
+    fn small_file() -> (Vec<u8>, usize, usize) {
+        let mut writer = Writer::new(Version::V3);
+        writer.create_stream("Big", &[3u8; 5000]).unwrap();
+        let mut buf = Vec::new();
+        writer.write_to(&mut buf).unwrap();
+        let (root, big) = {
+            let ole = Reader::new(&buf[..]).unwrap();
+            let big = ole.entry_by_path("Big").unwrap();
+            (
+                ole.entry_offset(0).unwrap(),
+                ole.entry_offset(big.id()).unwrap(),
+            )
+        };
+        (buf, root, big)
+    }
+
+    #[test]
+    fn malformed() {
+        let (buf, root, big) = small_file();
+
+        assert!(matches!(
+            Reader::new(&buf[..100]),
+            Err(Error::UnexpectedEof {
+                offset: 0,
+                len: 512
+            })
+        ));
+
+        let mut bad = buf.clone();
+        bad[44..48].copy_from_slice(&0x1000_0000u32.to_le_bytes());
+        assert!(matches!(
+            Reader::new(&bad[..]),
+            Err(Error::InvalidHeaderField {
+                offset: 44,
+                value: 0x1000_0000
+            })
+        ));
+
+        let mut bad = buf.clone();
+        bad[48..52].copy_from_slice(&0x0FFF_0000u32.to_le_bytes());
+        assert!(matches!(
+            Reader::new(&bad[..]),
+            Err(Error::InvalidSectorId {
+                sector_id: 0x0FFF_0000,
+                offset: 48
+            })
+        ));
+
+        let mut bad = buf.clone();
+        bad[big + 116..big + 120].copy_from_slice(&0x0FFF_0000u32.to_le_bytes());
+        match Reader::new(&bad[..]) {
+            Err(Error::InvalidSectorId { sector_id, offset }) => {
+                assert_eq!((sector_id, offset), (0x0FFF_0000, big + 116))
+            }
+            _ => panic!("invalid start sector not detected"),
+        }
+
+        let mut bad = buf.clone();
+        bad[root + 76..root + 80].copy_from_slice(&1000u32.to_le_bytes());
+        match Reader::new(&bad[..]) {
+            Err(Error::InvalidEntryId { entry_id, offset }) => {
+                assert_eq!((entry_id, offset), (1000, root + 76))
+            }
+            _ => panic!("invalid child not detected"),
+        }
+
+        let mut bad = buf.clone();
+        bad[big + 66] = 9;
+        match Reader::new(&bad[..]) {
+            Err(Error::InvalidEntry {
+                entry_id, offset, ..
+            }) => assert_eq!((entry_id, offset), (1, big)),
+            _ => panic!("invalid entry type not detected"),
+        }
+
+        // The stream is in sectors 2 to 11, the file is cut in sector 5.
+        let ole = Reader::new(&buf[..6 * 512 + 100]).unwrap();
+        assert!(matches!(
+            ole.open_stream("Big"),
+            Err(Error::SectorOutOfRange {
+                sector_id: 5,
+                offset: 3072
+            })
+        ));
+    }
+
+    #[test]
+    fn cycles() {
+        let (buf, _, big) = small_file();
+
+        // `Big` is in sectors 2 to 11, sector 5 now links back to sector 3.
+        let mut bad = buf.clone();
+        bad[512 + 5 * 4..512 + 6 * 4].copy_from_slice(&3u32.to_le_bytes());
+        assert!(matches!(
+            Reader::new(&bad[..]),
+            Err(Error::ChainCycle {
+                sector_id: 3,
+                offset: 532
+            })
+        ));
+
+        let mut bad = buf.clone();
+        bad[big + 68..big + 72].copy_from_slice(&1u32.to_le_bytes());
+        match Reader::new(&bad[..]) {
+            Err(Error::DirectoryCycle { entry_id, offset }) => {
+                assert_eq!((entry_id, offset), (1, big + 68))
+            }
+            _ => panic!("directory cycle not detected"),
+        };
+    }
+
+    #[test]
+    fn deep_directory() {
+        let n = 1_000u32;
+        let mut writer = Writer::new(Version::V3);
+        for i in 0..n {
+            writer.create_stream(&format!("{i}"), &[]).unwrap();
+        }
+        let mut buf = Vec::new();
+        writer.write_to(&mut buf).unwrap();
+
+        // Turns the siblings into a list, as deep as there are streams.
+        let ole = Reader::new(&buf[..]).unwrap();
+        let offsets: Vec<usize> = (0..=n).map(|id| ole.entry_offset(id).unwrap()).collect();
+        drop(ole);
+        buf[offsets[0] + 76..offsets[0] + 80].copy_from_slice(&1u32.to_le_bytes());
+        for id in 1..=n {
+            let next = if id == n {
+                constants::NO_STREAM
+            } else {
+                id + 1
+            };
+            let entry = offsets[id as usize];
+            buf[entry + 68..entry + 72].copy_from_slice(&constants::NO_STREAM.to_le_bytes());
+            buf[entry + 72..entry + 76].copy_from_slice(&next.to_le_bytes());
+        }
+
+        let ole = Reader::new(&buf[..]).unwrap();
+        assert_eq!(
+            ole.iterate().next().unwrap().children_nodes().len(),
+            n as usize
+        );
+    }
+
+    #[test]
+    fn limits() {
+        let (buf, _, _) = small_file();
+        let limited = |options: ReaderOptions| match Reader::with_options(&buf[..], options) {
+            Err(Error::LimitExceeded { limit, max }) => Some((limit, max)),
+            Err(e) => panic!("unexpected error {e}"),
+            Ok(_) => None,
+        };
+
+        assert_eq!(limited(ReaderOptions::default()), None);
+        assert_eq!(
+            limited(ReaderOptions::default().max_file_size(buf.len())),
+            None
+        );
+        assert_eq!(
+            limited(ReaderOptions::default().max_file_size(buf.len() - 1)),
+            Some(("file size", buf.len() - 1))
+        );
+        let options = ReaderOptions::default().max_file_size(buf.len() - 1);
+        assert!(matches!(
+            Reader::from_seekable_with_options(Cursor::new(&buf[..]), 0, options),
+            Err(Error::LimitExceeded {
+                limit: "file size",
+                ..
+            })
+        ));
+        let options = ReaderOptions::default().max_entries(3);
+        assert!(matches!(
+            Reader::from_seekable_with_options(Cursor::new(&buf[..]), 16, options),
+            Err(Error::LimitExceeded {
+                limit: "directory entries",
+                ..
+            })
+        ));
+        // The directory is a single sector of 4 entries.
+        assert_eq!(limited(ReaderOptions::default().max_entries(4)), None);
+        assert_eq!(
+            limited(ReaderOptions::default().max_entries(3)),
+            Some(("directory entries", 3))
+        );
+        // `Big` is 10 sectors long.
+        assert_eq!(limited(ReaderOptions::default().max_chain_length(10)), None);
+        assert_eq!(
+            limited(ReaderOptions::default().max_chain_length(9)),
+            Some(("chain length", 9))
+        );
+
+        let ole =
+            Reader::with_options(&buf[..], ReaderOptions::default().max_stream_size(4999)).unwrap();
+        assert!(matches!(
+            ole.open_stream("Big"),
+            Err(Error::LimitExceeded {
+                limit: "stream size",
+                max: 4999
+            })
+        ));
+
+        let mut writer = Writer::new(Version::V3);
+        writer.create_storage("A").unwrap();
+        writer.create_storage("A/B").unwrap();
+        writer.create_stream("A/B/C", b"c").unwrap();
+        let mut buf = Vec::new();
+        writer.write_to(&mut buf).unwrap();
+        assert!(Reader::with_options(&buf[..], ReaderOptions::default().max_depth(3)).is_ok());
+        assert!(matches!(
+            Reader::with_options(&buf[..], ReaderOptions::default().max_depth(2)),
+            Err(Error::LimitExceeded {
+                limit: "storage depth",
+                max: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn lenient() {
+        let (buf, root, big) = small_file();
+        let lenient = |buf: &[u8]| {
+            let ole = Reader::with_options(buf, ReaderOptions::default().lenient(true)).unwrap();
+            let warnings = ole.warnings().iter().map(|e| e.to_string()).collect();
+            let stream = ole.open_stream("Big").map(|mut slice| {
+                let mut data = Vec::new();
+                slice.read_to_end(&mut data).unwrap();
+                (slice.len(), data)
+            });
+            (warnings, stream.ok())
+        };
+
+        let ole = Reader::with_options(&buf[..], ReaderOptions::default().lenient(true)).unwrap();
+        assert!(ole.warnings().is_empty());
+
+        // The stream is read with the standard cutoff.
+        let mut bad = buf.clone();
+        bad[56..60].copy_from_slice(&0u32.to_le_bytes());
+        assert!(Reader::new(&bad[..]).is_err());
+        let (warnings, stream): (Vec<String>, _) = lenient(&bad);
+        assert_eq!(
+            warnings,
+            [Error::InvalidHeaderField {
+                offset: 56,
+                value: 0
+            }
+            .to_string()]
+        );
+        assert_eq!(stream, Some((5000, vec![3u8; 5000])));
+
+        // `Big` is in sectors 2 to 11, the chain is cut at the cycle, and
+        // no longer matches the size of the stream.
+        let mut bad = buf.clone();
+        bad[512 + 5 * 4..512 + 6 * 4].copy_from_slice(&3u32.to_le_bytes());
+        let (warnings, stream) = lenient(&bad);
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(stream, Some((5000, vec![3u8; 4 * 512])));
+
+        // The file is cut in sector 5.
+        let (warnings, stream) = lenient(&buf[..6 * 512 + 100]);
+        assert_eq!(
+            warnings,
+            [Error::TruncatedStream {
+                entry_id: 1,
+                len: 5000,
+                real_len: 3 * 512
+            }
+            .to_string()]
+        );
+        assert_eq!(stream, Some((5000, vec![3u8; 3 * 512])));
+
+        // `Big` is read as an empty entry.
+        let mut bad = buf.clone();
+        bad[big + 66] = 9;
+        let (warnings, stream) = lenient(&bad);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(stream, None);
+
+        let mut bad = buf.clone();
+        bad[root + 76..root + 80].copy_from_slice(&1000u32.to_le_bytes());
+        let (warnings, stream) = lenient(&bad);
+        assert_eq!(
+            warnings,
+            [Error::InvalidEntryId {
+                entry_id: 1000,
+                offset: root + 76
+            }
+            .to_string()]
+        );
+        assert_eq!(stream, None);
+
+        // Limits are still enforced.
+        assert!(matches!(
+            Reader::with_options(
+                &buf[..],
+                ReaderOptions::default().lenient(true).max_chain_length(9)
+            ),
+            Err(Error::LimitExceeded { .. })
+        ));
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn mmap_same_content() {
+        let memory = Reader::from_path("./assets/Thumbs.db").unwrap();
+        // SAFETY: the assets aren't modified by the tests.
+        let mapped = unsafe { Reader::from_mmap("./assets/Thumbs.db") }.unwrap();
+        for (a, b) in memory.iterate().zip(mapped.iterate()) {
+            assert_eq!(a.name(), b.name());
+            if a.is_empty() {
+                continue;
+            }
+            let (mut buf_a, mut buf_b) = (Vec::new(), Vec::new());
+            memory
+                .get_entry_slice(a)
+                .unwrap()
+                .read_to_end(&mut buf_a)
+                .unwrap();
+            mapped
+                .get_entry_slice(b)
+                .unwrap()
+                .read_to_end(&mut buf_b)
+                .unwrap();
+            assert_eq!(buf_a, buf_b);
+        }
+
+        let options = ReaderOptions::default().max_file_size(1024);
+        assert!(matches!(
+            unsafe { Reader::from_mmap_with_options("./assets/Thumbs.db", options) },
+            Err(Error::LimitExceeded {
+                limit: "file size",
+                max: 1024
+            })
+        ));
+    }
 }
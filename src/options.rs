@@ -0,0 +1,104 @@
+//             DO WHAT THE FUCK YOU WANT TO PUBLIC LICENSE
+//                    Version 2, December 2004
+//
+// Copyright (C) 2018 Thomas Bailleux <thomas@bailleux.me>
+//
+// Everyone is permitted to copy and distribute verbatim or modified
+// copies of this license document, and changing it is allowed as long
+// as the name is changed.
+//
+//            DO WHAT THE FUCK YOU WANT TO PUBLIC LICENSE
+//   TERMS AND CONDITIONS FOR COPYING, DISTRIBUTION AND MODIFICATION
+//
+//  0. You just DO WHAT THE FUCK YOU WANT TO.
+//
+// Author: zadig <thomas chr(0x40) bailleux.me>
+
+/// Limits enforced while parsing a file, to reject hostile files before
+/// they make the `Reader` allocate or loop too much.
+///
+/// The defaults accept any well-formed file; every limit can be lowered
+/// when the input isn't trusted.
+///
+/// # Examples
+///
+/// ```
+/// use ole::{Reader, ReaderOptions};
+///
+/// let options = ReaderOptions::default()
+///     .max_file_size(1 << 20)
+///     .max_entries(1024);
+/// let file = std::fs::File::open("assets/Thumbs.db").unwrap();
+/// let parser = Reader::with_options(file, options).unwrap();
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReaderOptions {
+    pub(crate) max_file_size: usize,
+    pub(crate) max_entries: usize,
+    pub(crate) max_chain_length: usize,
+    pub(crate) max_stream_size: usize,
+    pub(crate) max_depth: usize,
+    pub(crate) lenient: bool,
+}
+
+impl Default for ReaderOptions {
+    fn default() -> ReaderOptions {
+        ReaderOptions {
+            max_file_size: usize::MAX,
+            max_entries: 1 << 20,
+            max_chain_length: usize::MAX,
+            max_stream_size: usize::MAX,
+            max_depth: 256,
+            lenient: false,
+        }
+    }
+}
+
+impl ReaderOptions {
+    /// Sets the maximum size of the file, in bytes. No limit by default.
+    pub fn max_file_size(mut self, max: usize) -> ReaderOptions {
+        self.max_file_size = max;
+        self
+    }
+
+    /// Sets the maximum number of directory entries, counting the unused
+    /// ones. Defaults to 2^20.
+    pub fn max_entries(mut self, max: usize) -> ReaderOptions {
+        self.max_entries = max;
+        self
+    }
+
+    /// Sets the maximum number of sectors in a chain. No limit by default.
+    pub fn max_chain_length(mut self, max: usize) -> ReaderOptions {
+        self.max_chain_length = max;
+        self
+    }
+
+    /// Sets the maximum size of a stream, in bytes. Larger streams are
+    /// rejected when their `EntrySlice` is requested. No limit by default.
+    pub fn max_stream_size(mut self, max: usize) -> ReaderOptions {
+        self.max_stream_size = max;
+        self
+    }
+
+    /// Sets the maximum nesting depth of entries, which is the number of
+    /// components of their path: `A/B/C` is at depth 3. Defaults to 256.
+    pub fn max_depth(mut self, max: usize) -> ReaderOptions {
+        self.max_depth = max;
+        self
+    }
+
+    /// Sets whether damaged files are parsed leniently. Disabled by default.
+    ///
+    /// In lenient mode, inconsistencies which don't prevent reading the file
+    /// are recorded as warnings, returned by `Reader::warnings`, instead of
+    /// failing: broken sector chains are cut where they break, invalid
+    /// directory entries are read as empty ones, and streams which go beyond
+    /// the end of the file are read up to it, with an
+    /// `Error::TruncatedStream` warning (their `EntrySlice::real_len` is then
+    /// smaller than their `len`). The limits are still enforced.
+    pub fn lenient(mut self, lenient: bool) -> ReaderOptions {
+        self.lenient = lenient;
+        self
+    }
+}
@@ -0,0 +1,146 @@
+//             DO WHAT THE FUCK YOU WANT TO PUBLIC LICENSE
+//                    Version 2, December 2004
+//
+// Copyright (C) 2018 Thomas Bailleux <thomas@bailleux.me>
+//
+// Everyone is permitted to copy and distribute verbatim or modified
+// copies of this license document, and changing it is allowed as long
+// as the name is changed.
+//
+//            DO WHAT THE FUCK YOU WANT TO PUBLIC LICENSE
+//   TERMS AND CONDITIONS FOR COPYING, DISTRIBUTION AND MODIFICATION
+//
+//  0. You just DO WHAT THE FUCK YOU WANT TO.
+//
+// Author: zadig <thomas chr(0x40) bailleux.me>
+
+use std::collections::HashSet;
+
+use super::{
+    constants,
+    entry::{Entry, EntryType},
+    error::Error,
+    ole::Reader,
+    source::BlockSource,
+};
+
+impl<'ole> Reader<'ole> {
+    /// Returns the directory entries which can't be reached from the root
+    /// storage, and the unused entries which still hold a name. Both most
+    /// likely describe deleted storages or streams.
+    ///
+    /// The name, size and start sector of an unused entry are kept as they
+    /// were found, but its type is `EntryType::Empty`. Use `recover_orphan`
+    /// to read their data.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let parser = ole::Reader::from_path("assets/sample.doc").unwrap();
+    /// for entry in parser.orphaned_entries() {
+    ///     println!("{} ({} bytes)", entry.name(), entry.len());
+    /// }
+    /// ```
+    pub fn orphaned_entries(&self) -> Vec<&Entry> {
+        self.iterate()
+            .filter(|entry| match entry.entry_type {
+                EntryType::Empty => !entry.name.is_empty(),
+                _ => entry.parent_node.is_none() && Some(entry.id) != self.root_entry,
+            })
+            .collect()
+    }
+
+    /// Reads, on a best-effort basis, the data of an entry returned by
+    /// `orphaned_entries`, as if it were a stream.
+    ///
+    /// The chain of the entry is followed from its start sector in the FAT,
+    /// or in the mini FAT for a small stream. Where the chain ends before
+    /// the size of the entry is reached, as happens when the sectors of a
+    /// deleted stream were freed, the following sectors are read, assuming
+    /// the stream was stored contiguously. Reading stops at the end of the
+    /// file, or when a sector would be read twice, so the data may be shorter
+    /// than the size of the entry. Freed sectors may have been reused since,
+    /// so the data may not be the original one.
+    pub fn recover_orphan(&self, entry: &Entry) -> Result<Vec<u8>, Error> {
+        if entry.entry_type == EntryType::UserStorage || entry.entry_type == EntryType::RootStorage
+        {
+            return Err(Error::NotAStream(entry.name.clone()));
+        }
+        let size = entry.size;
+        let mini = size < self.minimum_standard_stream_size;
+        let (table, sector_size) = if mini {
+            (&self.ssat, self.short_sec_size)
+        } else {
+            (&self.sat, self.sec_size)
+        };
+
+        let mut data = Vec::new();
+        let mut visited = HashSet::new();
+        let mut sector_id = entry.sec_id_chain.first().copied();
+        while let Some(id) = sector_id {
+            if data.len() as u64 >= size || id > constants::SECID_MAX || !visited.insert(id) {
+                break;
+            }
+            let offset = if mini {
+                self.mini_sector_offset(id)
+            } else {
+                self.checked_sector_offset(id)
+            };
+            let Ok(offset) = offset else {
+                break;
+            };
+            let len = (sector_size as u64).min(size - data.len() as u64) as usize;
+            data.extend_from_slice(&self.source.block(offset, len)?);
+            sector_id = match table.get(id as usize) {
+                Some(&next) if next <= constants::SECID_MAX => Some(next),
+                _ => id.checked_add(1),
+            };
+        }
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{constants, sample::Sample, EntryType, Reader};
+
+    /// Returns a file holding the stream `Big`, of 10 sectors, and the small
+    /// stream `Small`.
+    fn sample() -> Sample {
+        Sample::new(&[("Big", &[1u8; 5000]), ("Small", &[2u8; 100])])
+    }
+
+    #[test]
+    fn unreachable() {
+        let sample = sample();
+        let (mut buf, root) = (sample.buf.clone(), sample.offset("Root Entry"));
+        assert!(Reader::new(&buf[..]).unwrap().orphaned_entries().is_empty());
+
+        buf[root + 76..root + 80].copy_from_slice(&constants::NO_STREAM.to_le_bytes());
+        let ole = Reader::new(&buf[..]).unwrap();
+        let orphans = ole.orphaned_entries();
+        let names: Vec<&str> = orphans.iter().map(|e| e.name()).collect();
+        assert_eq!(names, ["Big", "Small"]);
+        assert_eq!(ole.recover_orphan(orphans[0]).unwrap(), [1u8; 5000]);
+        assert_eq!(ole.recover_orphan(orphans[1]).unwrap(), [2u8; 100]);
+    }
+
+    #[test]
+    fn deleted() {
+        let sample = sample();
+        let (mut buf, big) = (sample.buf.clone(), sample.offset("Big"));
+        // `Big` is deleted: its entry is marked unused and its sectors freed.
+        buf[big + 66] = 0;
+        for &sector_id in sample.chain("Big") {
+            let fat_entry = 512 + sector_id as usize * 4;
+            buf[fat_entry..fat_entry + 4]
+                .copy_from_slice(&constants::SECID_FREE_SECTOR.to_le_bytes());
+        }
+        let ole = Reader::new(&buf[..]).unwrap();
+        let orphans = ole.orphaned_entries();
+        assert_eq!(orphans.len(), 1);
+        assert_eq!(orphans[0].name(), "Big");
+        assert_eq!(orphans[0]._type(), EntryType::Empty);
+        assert_eq!(ole.recover_orphan(orphans[0]).unwrap(), [1u8; 5000]);
+    }
+}
@@ -0,0 +1,169 @@
+//             DO WHAT THE FUCK YOU WANT TO PUBLIC LICENSE
+//                    Version 2, December 2004
+//
+// Copyright (C) 2018 Thomas Bailleux <thomas@bailleux.me>
+//
+// Everyone is permitted to copy and distribute verbatim or modified
+// copies of this license document, and changing it is allowed as long
+// as the name is changed.
+//
+//            DO WHAT THE FUCK YOU WANT TO PUBLIC LICENSE
+//   TERMS AND CONDITIONS FOR COPYING, DISTRIBUTION AND MODIFICATION
+//
+//  0. You just DO WHAT THE FUCK YOU WANT TO.
+//
+// Author: zadig <thomas chr(0x40) bailleux.me>
+
+use std::cmp::Ordering;
+
+use super::{
+    entry::{compare_names, Entry, EntrySlice, EntryType},
+    error::Error,
+    ole::Reader,
+};
+
+/// Returns the DirID of the child of `parent` named `name`.
+pub(crate) fn find_child(entries: &[Entry], parent: u32, name: &str) -> Option<u32> {
+    entries
+        .get(parent as usize)?
+        .children_nodes
+        .iter()
+        .copied()
+        .find(|&c| compare_names(&entries[c as usize].name, name) == Ordering::Equal)
+}
+
+/// Returns the DirID of the entry at `path`, using `/` as separator.
+///
+/// Paths are relative to the root storage `root`. They may also start with
+/// the name of the root storage, e.g. `/Root Entry/VBA/dir`.
+pub(crate) fn find_path(entries: &[Entry], root: u32, path: &str) -> Result<u32, Error> {
+    let not_found = || Error::EntryNotFound(path.to_string());
+    let mut names = path.split('/').filter(|name| !name.is_empty()).peekable();
+
+    if let (Some(first), Some(root_entry)) = (names.peek(), entries.get(root as usize)) {
+        if find_child(entries, root, first).is_none()
+            && compare_names(&root_entry.name, first) == Ordering::Equal
+        {
+            names.next();
+        }
+    }
+
+    let mut current = root;
+    for name in names {
+        current = find_child(entries, current, name).ok_or_else(not_found)?;
+    }
+    Ok(current)
+}
+
+impl<'ole> Reader<'ole> {
+    /// Returns the entry at `path`.
+    ///
+    /// Storages are separated by `/`. Names are compared the way the
+    /// compound file format does, i.e. case-insensitively. Paths may start
+    /// with the name of the root storage.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ole;
+    /// let parser = ole::Reader::from_path("assets/sample.doc").unwrap();
+    ///
+    /// let entry = parser.entry_by_path("ObjectPool/_1106396471/Workbook").unwrap();
+    /// assert_eq!(entry.name(), "Workbook");
+    /// let entry = parser.entry_by_path("/Root Entry/\u{5}SummaryInformation").unwrap();
+    /// assert_eq!(entry.len(), 432);
+    /// ```
+    pub fn entry_by_path(&self, path: &str) -> Result<&Entry, Error> {
+        let entries = self.entries.as_deref().unwrap_or_default();
+        let id = find_path(entries, self.root_entry.unwrap_or(0), path)?;
+        Ok(&entries[id as usize])
+    }
+
+    /// Returns the slice for the stream at `path`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ole;
+    /// use std::io::Read;
+    /// let parser = ole::Reader::from_path("assets/sample.doc").unwrap();
+    ///
+    /// let mut buf = Vec::new();
+    /// let mut slice = parser.open_stream("WordDocument").unwrap();
+    /// slice.read_to_end(&mut buf).unwrap();
+    /// ```
+    pub fn open_stream(&self, path: &str) -> Result<EntrySlice<'_>, Error> {
+        let entry = self.entry_by_path(path)?;
+        if entry._type() != EntryType::UserStream {
+            return Err(Error::NotAStream(path.to_string()));
+        }
+        self.get_entry_slice(entry)
+    }
+
+    /// Returns whether a stream or a storage exists at `path`.
+    pub fn exists(&self, path: &str) -> bool {
+        self.entry_by_path(path).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{EntryType, Error, Reader};
+    use std::io::Read;
+
+    #[test]
+    fn lookup() {
+        let ole = Reader::from_path("./assets/sample.doc").unwrap();
+        assert_eq!(ole.entry_by_path("").unwrap().id(), 0);
+        assert_eq!(ole.entry_by_path("/").unwrap().id(), 0);
+        assert_eq!(ole.entry_by_path("Root Entry").unwrap().id(), 0);
+        assert_eq!(ole.entry_by_path("WordDocument").unwrap().id(), 2);
+        assert_eq!(ole.entry_by_path("/worddocument").unwrap().id(), 2);
+        assert_eq!(
+            ole.entry_by_path("/Root Entry/WordDocument").unwrap().id(),
+            2
+        );
+        assert_eq!(
+            ole.entry_by_path("\u{5}SummaryInformation").unwrap().id(),
+            13
+        );
+        assert_eq!(
+            ole.entry_by_path("ObjectPool/_1106396471/\u{5}SummaryInformation")
+                .unwrap()
+                .id(),
+            10
+        );
+        assert_eq!(
+            ole.entry_by_path("ObjectPool/_1106396471").unwrap()._type(),
+            EntryType::UserStorage
+        );
+        assert!(matches!(
+            ole.entry_by_path("ObjectPool/Workbook"),
+            Err(Error::EntryNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn exists() {
+        let ole = Reader::from_path("./assets/sample.doc").unwrap();
+        assert!(ole.exists("ObjectPool/_1106396471/Workbook"));
+        assert!(ole.exists("OBJECTPOOL"));
+        assert!(!ole.exists("Workbook"));
+        assert!(!ole.exists("WordDocument/Data"));
+    }
+
+    #[test]
+    fn open_stream() {
+        let ole = Reader::from_path("./assets/sample.doc").unwrap();
+        let mut buf = Vec::new();
+        ole.open_stream("ObjectPool/_1106396471/\u{1}Ole")
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        assert_eq!(buf.len(), 20);
+        assert!(matches!(
+            ole.open_stream("ObjectPool"),
+            Err(Error::NotAStream(_))
+        ));
+    }
+}
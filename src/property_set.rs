@@ -0,0 +1,1628 @@
+//             DO WHAT THE FUCK YOU WANT TO PUBLIC LICENSE
+//                    Version 2, December 2004
+//
+// Copyright (C) 2018 Thomas Bailleux <thomas@bailleux.me>
+//
+// Everyone is permitted to copy and distribute verbatim or modified
+// copies of this license document, and changing it is allowed as long
+// as the name is changed.
+//
+//            DO WHAT THE FUCK YOU WANT TO PUBLIC LICENSE
+//   TERMS AND CONDITIONS FOR COPYING, DISTRIBUTION AND MODIFICATION
+//
+//  0. You just DO WHAT THE FUCK YOU WANT TO.
+//
+// Author: zadig <thomas chr(0x40) bailleux.me>
+
+//! Property set streams, as described in [MS-OLEPS].
+//!
+//! Office documents store their metadata (title, author, timestamps...) in
+//! the `\x05SummaryInformation` and `\x05DocumentSummaryInformation`
+//! streams. Both are property set streams, made of one or more sections,
+//! each identified by a FMTID and holding typed properties.
+//!
+//! ```
+//! use ole;
+//! let parser = ole::Reader::from_path("assets/sample.doc").unwrap();
+//!
+//! let summary = parser.summary_information().unwrap();
+//! assert_eq!(summary.author(), Some("Lexis Nexis Group"));
+//! assert_eq!(summary.word_count(), Some(201));
+//! ```
+//!
+//! [MS-OLEPS]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-oleps
+
+use std::{
+    io::{Read, Write},
+    time::Duration,
+};
+
+use super::{
+    clsid::Clsid,
+    editor::Editor,
+    error::Error,
+    filetime::FileTime,
+    ole::Reader,
+    util::{filetime_from_slice, ByteOrder, FromSlice},
+};
+
+/// FMTID of the `\x05SummaryInformation` section,
+/// `{F29F85E0-4FF9-1068-AB91-08002B27B3D9}`.
+pub const FMTID_SUMMARY_INFORMATION: [u8; 16] = [
+    0xE0, 0x85, 0x9F, 0xF2, 0xF9, 0x4F, 0x68, 0x10, 0xAB, 0x91, 0x08, 0x00, 0x2B, 0x27, 0xB3, 0xD9,
+];
+
+/// FMTID of the first `\x05DocumentSummaryInformation` section,
+/// `{D5CDD502-2E9C-101B-9397-08002B2CF9AE}`.
+pub const FMTID_DOC_SUMMARY_INFORMATION: [u8; 16] = [
+    0x02, 0xD5, 0xCD, 0xD5, 0x9C, 0x2E, 0x1B, 0x10, 0x93, 0x97, 0x08, 0x00, 0x2B, 0x2C, 0xF9, 0xAE,
+];
+
+/// FMTID of the user-defined properties section of
+/// `\x05DocumentSummaryInformation`, `{D5CDD505-2E9C-101B-9397-08002B2CF9AE}`.
+pub const FMTID_USER_DEFINED_PROPERTIES: [u8; 16] = [
+    0x05, 0xD5, 0xCD, 0xD5, 0x9C, 0x2E, 0x1B, 0x10, 0x93, 0x97, 0x08, 0x00, 0x2B, 0x2C, 0xF9, 0xAE,
+];
+
+/// Property holding the dictionary of property names.
+pub const PID_DICTIONARY: u32 = 0x0000_0000;
+/// Property holding the code page of the strings of a section.
+pub const PID_CODEPAGE: u32 = 0x0000_0001;
+/// Property holding the locale of a section.
+pub const PID_LOCALE: u32 = 0x8000_0000;
+
+/// Title of the document.
+pub const PIDSI_TITLE: u32 = 0x02;
+/// Subject of the document.
+pub const PIDSI_SUBJECT: u32 = 0x03;
+/// Author of the document.
+pub const PIDSI_AUTHOR: u32 = 0x04;
+/// Keywords of the document.
+pub const PIDSI_KEYWORDS: u32 = 0x05;
+/// Comments on the document.
+pub const PIDSI_COMMENTS: u32 = 0x06;
+/// Template the document was created from.
+pub const PIDSI_TEMPLATE: u32 = 0x07;
+/// Last user who saved the document.
+pub const PIDSI_LASTAUTHOR: u32 = 0x08;
+/// Revision number of the document.
+pub const PIDSI_REVNUMBER: u32 = 0x09;
+/// Total time spent editing the document.
+pub const PIDSI_EDITTIME: u32 = 0x0A;
+/// Time the document was last printed.
+pub const PIDSI_LASTPRINTED: u32 = 0x0B;
+/// Time the document was created.
+pub const PIDSI_CREATE_DTM: u32 = 0x0C;
+/// Time the document was last saved.
+pub const PIDSI_LASTSAVE_DTM: u32 = 0x0D;
+/// Number of pages.
+pub const PIDSI_PAGECOUNT: u32 = 0x0E;
+/// Number of words.
+pub const PIDSI_WORDCOUNT: u32 = 0x0F;
+/// Number of characters.
+pub const PIDSI_CHARCOUNT: u32 = 0x10;
+/// Thumbnail of the document.
+pub const PIDSI_THUMBNAIL: u32 = 0x11;
+/// Name of the application that created the document.
+pub const PIDSI_APPNAME: u32 = 0x12;
+/// Security flags of the document.
+pub const PIDSI_SECURITY: u32 = 0x13;
+
+/// Category of the document.
+pub const PIDDSI_CATEGORY: u32 = 0x02;
+/// Manager of the author.
+pub const PIDDSI_MANAGER: u32 = 0x0E;
+/// Company of the author.
+pub const PIDDSI_COMPANY: u32 = 0x0F;
+
+/// Code page of UTF-16LE strings.
+pub const CP_WINUNICODE: u16 = 1200;
+/// Code page of Windows-1252 strings.
+pub const CP_WINDOWS_1252: u16 = 1252;
+/// Code page of UTF-8 strings.
+pub const CP_UTF8: u16 = 65001;
+
+const BYTE_ORDER_MARK: u16 = 0xFFFE;
+
+/// Win32, version 5.0.
+const SYSTEM_IDENTIFIER: u32 = 0x0002_0005;
+
+const VT_EMPTY: u16 = 0x0000;
+const VT_NULL: u16 = 0x0001;
+const VT_I2: u16 = 0x0002;
+const VT_I4: u16 = 0x0003;
+const VT_R4: u16 = 0x0004;
+const VT_R8: u16 = 0x0005;
+const VT_CY: u16 = 0x0006;
+const VT_DATE: u16 = 0x0007;
+const VT_BSTR: u16 = 0x0008;
+const VT_ERROR: u16 = 0x000A;
+const VT_BOOL: u16 = 0x000B;
+const VT_VARIANT: u16 = 0x000C;
+const VT_I1: u16 = 0x0010;
+const VT_UI1: u16 = 0x0011;
+const VT_UI2: u16 = 0x0012;
+const VT_UI4: u16 = 0x0013;
+const VT_I8: u16 = 0x0014;
+const VT_UI8: u16 = 0x0015;
+const VT_INT: u16 = 0x0016;
+const VT_UINT: u16 = 0x0017;
+const VT_LPSTR: u16 = 0x001E;
+const VT_LPWSTR: u16 = 0x001F;
+const VT_FILETIME: u16 = 0x0040;
+const VT_BLOB: u16 = 0x0041;
+const VT_CF: u16 = 0x0047;
+const VT_CLSID: u16 = 0x0048;
+const VT_VECTOR: u16 = 0x1000;
+
+/// A typed property value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PropertyValue {
+    /// `VT_EMPTY`.
+    Empty,
+    /// `VT_NULL`.
+    Null,
+    /// `VT_I1`.
+    I1(i8),
+    /// `VT_UI1`.
+    UI1(u8),
+    /// `VT_I2`.
+    I2(i16),
+    /// `VT_UI2`.
+    UI2(u16),
+    /// `VT_I4` or `VT_INT`.
+    I4(i32),
+    /// `VT_UI4` or `VT_UINT`.
+    UI4(u32),
+    /// `VT_I8`.
+    I8(i64),
+    /// `VT_UI8`.
+    UI8(u64),
+    /// `VT_R4`.
+    R4(f32),
+    /// `VT_R8`.
+    R8(f64),
+    /// `VT_CY`, in ten-thousandths of a unit.
+    Currency(i64),
+    /// `VT_DATE`, as an OLE automation date.
+    Date(f64),
+    /// `VT_ERROR`, a HRESULT.
+    Error(u32),
+    /// `VT_BOOL`.
+    Bool(bool),
+    /// `VT_BSTR`.
+    Bstr(String),
+    /// `VT_LPSTR`, decoded from the code page of the section.
+    LpStr(String),
+    /// `VT_LPWSTR`.
+    LpWStr(String),
+    /// `VT_FILETIME`.
+    FileTime(FileTime),
+    /// `VT_BLOB`.
+    Blob(Vec<u8>),
+    /// `VT_CF`.
+    ClipboardData {
+        /// Clipboard format.
+        format: i32,
+        /// Clipboard data.
+        data: Vec<u8>,
+    },
+    /// `VT_CLSID`.
+    Clsid(Clsid),
+    /// `VT_VECTOR`, with the type of its elements.
+    Vector(u16, Vec<PropertyValue>),
+    /// The names of the properties of a section, by property identifier.
+    Dictionary(Vec<(u32, String)>),
+    /// A value whose type isn't supported, with its raw bytes.
+    Unknown(u16, Vec<u8>),
+}
+
+impl PropertyValue {
+    /// Returns the value as a string, if it is one.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            PropertyValue::Bstr(s) | PropertyValue::LpStr(s) | PropertyValue::LpWStr(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as an `i32`, if it is a 1, 2 or 4 bytes integer.
+    pub fn as_i32(&self) -> Option<i32> {
+        match *self {
+            PropertyValue::I1(i) => Some(i32::from(i)),
+            PropertyValue::UI1(i) => Some(i32::from(i)),
+            PropertyValue::I2(i) => Some(i32::from(i)),
+            PropertyValue::UI2(i) => Some(i32::from(i)),
+            PropertyValue::I4(i) => Some(i),
+            PropertyValue::UI4(i) => i32::try_from(i).ok(),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as a FILETIME, if it is one.
+    pub fn as_filetime(&self) -> Option<FileTime> {
+        match *self {
+            PropertyValue::FileTime(t) => Some(t),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as a boolean, if it is one.
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            PropertyValue::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+}
+
+/// A property: an identifier and a value.
+#[derive(Clone, Debug)]
+pub struct Property {
+    id: u32,
+    value: PropertyValue,
+
+    /// The bytes the property was parsed from, written back as is as long
+    /// as the property isn't modified.
+    raw: Option<Vec<u8>>,
+}
+
+impl PartialEq for Property {
+    fn eq(&self, other: &Property) -> bool {
+        self.id == other.id && self.value == other.value
+    }
+}
+
+impl Property {
+    /// Returns the identifier of the property.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns the value of the property.
+    pub fn value(&self) -> &PropertyValue {
+        &self.value
+    }
+}
+
+/// A section of a property set stream, i.e. the properties of one FMTID.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Section {
+    fmtid: [u8; 16],
+    properties: Vec<Property>,
+}
+
+impl Section {
+    /// Constructs an empty section, whose strings are stored as UTF-16.
+    pub fn new(fmtid: [u8; 16]) -> Section {
+        let mut section = Section {
+            fmtid,
+            properties: Vec::new(),
+        };
+        section.set_code_page(CP_WINUNICODE);
+        section
+    }
+
+    /// Returns the FMTID of the section.
+    pub fn fmtid(&self) -> &[u8; 16] {
+        &self.fmtid
+    }
+
+    /// Returns the properties, in the order they are declared.
+    pub fn properties(&self) -> &[Property] {
+        &self.properties
+    }
+
+    /// Returns the value of the property `id`.
+    pub fn get(&self, id: u32) -> Option<&PropertyValue> {
+        self.properties
+            .iter()
+            .find(|p| p.id == id)
+            .map(|p| &p.value)
+    }
+
+    /// Returns the code page used by the strings of the section.
+    pub fn code_page(&self) -> Option<u16> {
+        match *self.get(PID_CODEPAGE)? {
+            PropertyValue::I2(cp) => Some(cp as u16),
+            PropertyValue::UI2(cp) => Some(cp),
+            _ => None,
+        }
+    }
+
+    /// Returns the names of the properties, if the section has a
+    /// dictionary.
+    pub fn dictionary(&self) -> Option<&[(u32, String)]> {
+        match self.get(PID_DICTIONARY)? {
+            PropertyValue::Dictionary(names) => Some(names),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of the property named `name` in the dictionary.
+    pub fn get_by_name(&self, name: &str) -> Option<&PropertyValue> {
+        let &(id, _) = self.dictionary()?.iter().find(|(_, n)| n == name)?;
+        self.get(id)
+    }
+
+    /// Sets the value of the property `id`, adding it if it doesn't exist.
+    ///
+    /// Strings are encoded in the code page of the section when the stream
+    /// is written. The other properties are written back unchanged, so
+    /// changing the code page only affects the modified properties.
+    pub fn set(&mut self, id: u32, value: PropertyValue) {
+        match self.properties.iter_mut().find(|p| p.id == id) {
+            Some(property) => {
+                property.value = value;
+                property.raw = None;
+            }
+            None => self.properties.push(Property {
+                id,
+                value,
+                raw: None,
+            }),
+        }
+    }
+
+    /// Sets the code page used by the strings of the section.
+    ///
+    /// The unmodified strings and dictionary are encoded again in the new
+    /// code page when the section is written.
+    pub fn set_code_page(&mut self, code_page: u16) {
+        if self.code_page().unwrap_or(CP_WINDOWS_1252) != code_page {
+            for property in self.properties.iter_mut() {
+                if uses_code_page(&property.value) {
+                    property.raw = None;
+                }
+            }
+        }
+        self.set(PID_CODEPAGE, PropertyValue::I2(code_page as i16));
+    }
+
+    /// Removes the property `id`, and returns its value.
+    pub fn remove(&mut self, id: u32) -> Option<PropertyValue> {
+        let index = self.properties.iter().position(|p| p.id == id)?;
+        Some(self.properties.remove(index).value)
+    }
+
+    /// Encodes the section.
+    fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        let code_page = self.code_page().unwrap_or(CP_WINDOWS_1252);
+        let header_len = 8 + 8 * self.properties.len();
+        let mut table = Vec::with_capacity(header_len);
+        let mut values = Vec::new();
+        for property in self.properties.iter() {
+            table.extend_from_slice(&property.id.to_le_bytes());
+            table.extend_from_slice(&to_u32(header_len + values.len())?.to_le_bytes());
+            match (&property.raw, &property.value) {
+                (Some(raw), _) => values.extend_from_slice(raw),
+                (None, PropertyValue::Dictionary(names)) if property.id == PID_DICTIONARY => {
+                    encode_dictionary(names, code_page, &mut values)?
+                }
+                (None, _) if property.id == PID_DICTIONARY => {
+                    return Err(Error::InvalidPropertySet("Bad dictionary"))
+                }
+                (None, value) => encode_typed(value, code_page, &mut values)?,
+            }
+            values.resize(pad4(values.len()), 0);
+        }
+
+        let mut buf = Vec::with_capacity(header_len + values.len());
+        buf.extend_from_slice(&to_u32(header_len + values.len())?.to_le_bytes());
+        buf.extend_from_slice(&to_u32(self.properties.len())?.to_le_bytes());
+        buf.extend_from_slice(&table);
+        buf.extend_from_slice(&values);
+        Ok(buf)
+    }
+
+    fn parse(
+        fmtid: [u8; 16],
+        stream: &[u8],
+        offset: usize,
+        order: ByteOrder,
+    ) -> Result<Section, Error> {
+        let size = usize::from_slice_with(bytes(stream, offset, 4)?, order);
+        let data = bytes(stream, offset, size)?;
+        let count = usize::from_slice_with(bytes(data, 4, 4)?, order);
+        let table = count
+            .checked_mul(8)
+            .and_then(|len| data.get(8..8 + len))
+            .ok_or(Error::InvalidPropertySet("Too many properties"))?;
+        let ids: Vec<(u32, usize)> = table
+            .chunks(8)
+            .map(|pair| {
+                (
+                    u32::from_slice_with(&pair[0..4], order),
+                    usize::from_slice_with(&pair[4..8], order),
+                )
+            })
+            .collect();
+
+        // Values have no explicit size: each one ends where the next one
+        // starts, or at the end of the section.
+        let mut offsets: Vec<usize> = ids.iter().map(|&(_, offset)| offset).collect();
+        offsets.sort_unstable();
+        offsets.dedup();
+        let value_at = |offset: usize| -> Result<&[u8], Error> {
+            let end = offsets
+                .iter()
+                .copied()
+                .find(|&o| o > offset)
+                .unwrap_or(size);
+            data.get(offset..end)
+                .ok_or(Error::InvalidPropertySet("Property offset out of bounds"))
+        };
+
+        // The code page is needed to decode the other properties.
+        let mut code_page = CP_WINDOWS_1252;
+        if let Some(&(_, offset)) = ids.iter().find(|&&(id, _)| id == PID_CODEPAGE) {
+            if let (PropertyValue::I2(cp), _) = parse_typed(value_at(offset)?, code_page, order)? {
+                code_page = cp as u16;
+            }
+        }
+
+        let mut properties = Vec::with_capacity(ids.len());
+        for &(id, offset) in ids.iter() {
+            let raw = value_at(offset)?;
+            let value = if id == PID_DICTIONARY {
+                parse_dictionary(raw, code_page, order)?
+            } else {
+                parse_typed(raw, code_page, order)?.0
+            };
+            // Big-endian values are written back as little-endian ones.
+            properties.push(Property {
+                id,
+                value,
+                raw: (order == ByteOrder::LittleEndian).then(|| raw.to_vec()),
+            });
+        }
+        Ok(Section { fmtid, properties })
+    }
+}
+
+/// A property set stream, such as `\x05SummaryInformation`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PropertySet {
+    version: u16,
+    system_identifier: u32,
+    clsid: Clsid,
+    sections: Vec<Section>,
+}
+
+impl Default for PropertySet {
+    fn default() -> PropertySet {
+        PropertySet::new()
+    }
+}
+
+impl PropertySet {
+    /// Constructs an empty property set stream.
+    pub fn new() -> PropertySet {
+        PropertySet {
+            version: 0,
+            system_identifier: SYSTEM_IDENTIFIER,
+            clsid: Clsid::NIL,
+            sections: Vec::new(),
+        }
+    }
+
+    /// Parses a property set stream from `reader`, typically an
+    /// `EntrySlice`.
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<PropertySet, Error> {
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf)?;
+        PropertySet::parse(&buf)
+    }
+
+    /// Parses a property set stream.
+    ///
+    /// Big-endian streams are supported: their properties are written back
+    /// as little-endian ones by `to_bytes`.
+    pub fn parse(buf: &[u8]) -> Result<PropertySet, Error> {
+        let order = ByteOrder::from_mark(bytes(buf, 0, 2)?)
+            .ok_or(Error::InvalidPropertySet("Bad byte order mark"))?;
+        let version = u16::from_slice_with(bytes(buf, 2, 2)?, order);
+        let system_identifier = u32::from_slice_with(bytes(buf, 4, 4)?, order);
+        let clsid = Clsid::from_slice_with(bytes(buf, 8, 16)?, order);
+        let count = usize::from_slice_with(bytes(buf, 24, 4)?, order);
+
+        let mut sections = Vec::new();
+        for i in 0..count {
+            let header = i
+                .checked_mul(20)
+                .and_then(|o| buf.get(28 + o..48 + o))
+                .ok_or(Error::InvalidPropertySet("Too many sections"))?;
+            let mut fmtid = [0u8; 16];
+            fmtid.copy_from_slice(&header[0..16]);
+            let offset = usize::from_slice_with(&header[16..20], order);
+            sections.push(Section::parse(fmtid, buf, offset, order)?);
+        }
+        Ok(PropertySet {
+            version,
+            system_identifier,
+            clsid,
+            sections,
+        })
+    }
+
+    /// Returns the version of the property set format, 0 or 1.
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    /// Returns the identifier of the system that wrote the stream.
+    pub fn system_identifier(&self) -> u32 {
+        self.system_identifier
+    }
+
+    /// Returns the CLSID of the stream.
+    pub fn clsid(&self) -> Clsid {
+        self.clsid
+    }
+
+    /// Returns the sections of the stream.
+    pub fn sections(&self) -> &[Section] {
+        &self.sections
+    }
+
+    /// Returns the section identified by `fmtid`.
+    pub fn section(&self, fmtid: &[u8; 16]) -> Option<&Section> {
+        self.sections.iter().find(|s| &s.fmtid == fmtid)
+    }
+
+    /// Returns the value of the property `id` of the section `fmtid`.
+    pub fn get(&self, fmtid: &[u8; 16], id: u32) -> Option<&PropertyValue> {
+        self.section(fmtid)?.get(id)
+    }
+
+    /// Returns the section identified by `fmtid`, for modification.
+    pub fn section_mut(&mut self, fmtid: &[u8; 16]) -> Option<&mut Section> {
+        self.sections.iter_mut().find(|s| &s.fmtid == fmtid)
+    }
+
+    /// Returns the section identified by `fmtid`, appending a new one if it
+    /// doesn't exist.
+    pub fn insert_section(&mut self, fmtid: [u8; 16]) -> &mut Section {
+        let index = match self.sections.iter().position(|s| s.fmtid == fmtid) {
+            Some(index) => index,
+            None => {
+                self.sections.push(Section::new(fmtid));
+                self.sections.len() - 1
+            }
+        };
+        &mut self.sections[index]
+    }
+
+    /// Removes the section identified by `fmtid`.
+    pub fn remove_section(&mut self, fmtid: &[u8; 16]) -> Option<Section> {
+        let index = self.sections.iter().position(|s| &s.fmtid == fmtid)?;
+        Some(self.sections.remove(index))
+    }
+
+    /// Sets the value of the property `id` of the section `fmtid`, adding
+    /// the section and the property if they don't exist.
+    pub fn set(&mut self, fmtid: [u8; 16], id: u32, value: PropertyValue) {
+        self.insert_section(fmtid).set(id, value);
+    }
+
+    /// Removes the property `id` of the section `fmtid`, and returns its
+    /// value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ole::property_set::{FMTID_SUMMARY_INFORMATION, PIDSI_AUTHOR};
+    /// let parser = ole::Reader::from_path("assets/sample.doc").unwrap();
+    ///
+    /// let mut summary = parser.summary_information().unwrap();
+    /// summary.remove(&FMTID_SUMMARY_INFORMATION, PIDSI_AUTHOR);
+    /// let scrubbed = summary.to_bytes().unwrap();
+    /// ```
+    pub fn remove(&mut self, fmtid: &[u8; 16], id: u32) -> Option<PropertyValue> {
+        self.section_mut(fmtid)?.remove(id)
+    }
+
+    /// Encodes the stream.
+    ///
+    /// Properties that weren't modified since the stream was parsed are
+    /// written back byte for byte, including the ones whose type isn't
+    /// supported.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        let header_len = 28 + 20 * self.sections.len();
+        let mut header = Vec::with_capacity(header_len);
+        header.extend_from_slice(&BYTE_ORDER_MARK.to_le_bytes());
+        header.extend_from_slice(&self.version.to_le_bytes());
+        header.extend_from_slice(&self.system_identifier.to_le_bytes());
+        header.extend_from_slice(self.clsid.as_bytes());
+        header.extend_from_slice(&to_u32(self.sections.len())?.to_le_bytes());
+
+        let mut body = Vec::new();
+        for section in self.sections.iter() {
+            header.extend_from_slice(&section.fmtid);
+            header.extend_from_slice(&to_u32(header_len + body.len())?.to_le_bytes());
+            body.extend_from_slice(&section.to_bytes()?);
+        }
+        header.extend_from_slice(&body);
+        Ok(header)
+    }
+
+    /// Writes the encoded stream to `out`.
+    pub fn write_to<W: Write>(&self, mut out: W) -> Result<(), Error> {
+        out.write_all(&self.to_bytes()?)?;
+        Ok(())
+    }
+
+    fn summary_str(&self, id: u32) -> Option<&str> {
+        self.get(&FMTID_SUMMARY_INFORMATION, id)?.as_str()
+    }
+
+    fn summary_i32(&self, id: u32) -> Option<i32> {
+        self.get(&FMTID_SUMMARY_INFORMATION, id)?.as_i32()
+    }
+
+    fn summary_filetime(&self, id: u32) -> Option<FileTime> {
+        self.get(&FMTID_SUMMARY_INFORMATION, id)?.as_filetime()
+    }
+
+    /// Returns the title of the document.
+    pub fn title(&self) -> Option<&str> {
+        self.summary_str(PIDSI_TITLE)
+    }
+
+    /// Returns the subject of the document.
+    pub fn subject(&self) -> Option<&str> {
+        self.summary_str(PIDSI_SUBJECT)
+    }
+
+    /// Returns the author of the document.
+    pub fn author(&self) -> Option<&str> {
+        self.summary_str(PIDSI_AUTHOR)
+    }
+
+    /// Returns the keywords of the document.
+    pub fn keywords(&self) -> Option<&str> {
+        self.summary_str(PIDSI_KEYWORDS)
+    }
+
+    /// Returns the comments on the document.
+    pub fn comments(&self) -> Option<&str> {
+        self.summary_str(PIDSI_COMMENTS)
+    }
+
+    /// Returns the template the document was created from.
+    pub fn template(&self) -> Option<&str> {
+        self.summary_str(PIDSI_TEMPLATE)
+    }
+
+    /// Returns the last user who saved the document.
+    pub fn last_saved_by(&self) -> Option<&str> {
+        self.summary_str(PIDSI_LASTAUTHOR)
+    }
+
+    /// Returns the revision number of the document.
+    pub fn revision(&self) -> Option<&str> {
+        self.summary_str(PIDSI_REVNUMBER)
+    }
+
+    /// Returns the name of the application that created the document.
+    pub fn application_name(&self) -> Option<&str> {
+        self.summary_str(PIDSI_APPNAME)
+    }
+
+    /// Returns the total editing time.
+    pub fn edit_time(&self) -> Option<Duration> {
+        let ticks = self.summary_filetime(PIDSI_EDITTIME)?.as_u64();
+        Some(Duration::from_nanos(ticks.checked_mul(100)?))
+    }
+
+    /// Returns the time the document was last printed.
+    pub fn last_printed(&self) -> Option<FileTime> {
+        self.summary_filetime(PIDSI_LASTPRINTED)
+    }
+
+    /// Returns the time the document was created.
+    pub fn created(&self) -> Option<FileTime> {
+        self.summary_filetime(PIDSI_CREATE_DTM)
+    }
+
+    /// Returns the time the document was last saved.
+    pub fn last_saved(&self) -> Option<FileTime> {
+        self.summary_filetime(PIDSI_LASTSAVE_DTM)
+    }
+
+    /// Returns the number of pages.
+    pub fn page_count(&self) -> Option<i32> {
+        self.summary_i32(PIDSI_PAGECOUNT)
+    }
+
+    /// Returns the number of words.
+    pub fn word_count(&self) -> Option<i32> {
+        self.summary_i32(PIDSI_WORDCOUNT)
+    }
+
+    /// Returns the number of characters.
+    pub fn char_count(&self) -> Option<i32> {
+        self.summary_i32(PIDSI_CHARCOUNT)
+    }
+
+    /// Returns the category of the document.
+    pub fn category(&self) -> Option<&str> {
+        self.get(&FMTID_DOC_SUMMARY_INFORMATION, PIDDSI_CATEGORY)?
+            .as_str()
+    }
+
+    /// Returns the manager of the author.
+    pub fn manager(&self) -> Option<&str> {
+        self.get(&FMTID_DOC_SUMMARY_INFORMATION, PIDDSI_MANAGER)?
+            .as_str()
+    }
+
+    /// Returns the company of the author.
+    pub fn company(&self) -> Option<&str> {
+        self.get(&FMTID_DOC_SUMMARY_INFORMATION, PIDDSI_COMPANY)?
+            .as_str()
+    }
+}
+
+impl<'ole> Reader<'ole> {
+    /// Parses the property set stream at `path`.
+    pub fn property_set(&self, path: &str) -> Result<PropertySet, Error> {
+        PropertySet::from_reader(self.open_stream(path)?)
+    }
+
+    /// Parses the `\x05SummaryInformation` stream.
+    pub fn summary_information(&self) -> Result<PropertySet, Error> {
+        self.property_set("\u{5}SummaryInformation")
+    }
+
+    /// Parses the `\x05DocumentSummaryInformation` stream.
+    pub fn document_summary_information(&self) -> Result<PropertySet, Error> {
+        self.property_set("\u{5}DocumentSummaryInformation")
+    }
+}
+
+impl Editor {
+    /// Parses the property set stream at `path`.
+    pub fn property_set(&self, path: &str) -> Result<PropertySet, Error> {
+        PropertySet::parse(&self.read_stream(path)?)
+    }
+
+    /// Replaces the property set stream at `path` with `set`, creating the
+    /// stream if it doesn't exist.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ole::property_set::{FMTID_SUMMARY_INFORMATION, PIDSI_AUTHOR, PIDSI_LASTAUTHOR};
+    /// let mut editor = ole::Editor::from_path("assets/sample.doc").unwrap();
+    ///
+    /// let mut summary = editor.property_set("\u{5}SummaryInformation").unwrap();
+    /// summary.remove(&FMTID_SUMMARY_INFORMATION, PIDSI_AUTHOR);
+    /// summary.remove(&FMTID_SUMMARY_INFORMATION, PIDSI_LASTAUTHOR);
+    /// editor
+    ///     .write_property_set("\u{5}SummaryInformation", &summary)
+    ///     .unwrap();
+    /// ```
+    pub fn write_property_set(&mut self, path: &str, set: &PropertySet) -> Result<(), Error> {
+        let data = set.to_bytes()?;
+        match self.write_stream(path, &data) {
+            Err(Error::EntryNotFound(_)) => self.create_stream(path, &data),
+            result => result,
+        }
+    }
+}
+
+/// Returns whether `value` is encoded with the code page of its section.
+fn uses_code_page(value: &PropertyValue) -> bool {
+    match value {
+        PropertyValue::Bstr(_) | PropertyValue::LpStr(_) | PropertyValue::Dictionary(_) => true,
+        PropertyValue::Vector(_, values) => values.iter().any(uses_code_page),
+        _ => false,
+    }
+}
+
+/// Returns the `len` bytes of `buf` starting at `offset`.
+fn bytes(buf: &[u8], offset: usize, len: usize) -> Result<&[u8], Error> {
+    offset
+        .checked_add(len)
+        .and_then(|end| buf.get(offset..end))
+        .ok_or(Error::InvalidPropertySet("Value out of bounds"))
+}
+
+fn pad4(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+fn to_u32(len: usize) -> Result<u32, Error> {
+    u32::try_from(len).map_err(|_| Error::InvalidPropertySet("Value too big"))
+}
+
+/// Parses a `TypedPropertyValue`, returning the value and its size.
+fn parse_typed(
+    data: &[u8],
+    code_page: u16,
+    order: ByteOrder,
+) -> Result<(PropertyValue, usize), Error> {
+    let vt = u16::from_slice_with(bytes(data, 0, 2)?, order);
+    let body = &data[4.min(data.len())..];
+    if vt & VT_VECTOR != 0 {
+        let element = vt & !VT_VECTOR;
+        let count = usize::from_slice_with(bytes(body, 0, 4)?, order);
+        let mut values = Vec::new();
+        let mut pos = 4;
+        for _ in 0..count {
+            let parsed = if element == VT_VARIANT {
+                let item = &body[pos.min(body.len())..];
+                // A variant in a vector can't be a vector itself, which also
+                // bounds the recursion.
+                if u16::from_slice_with(bytes(item, 0, 2)?, order) & VT_VECTOR != 0 {
+                    return Err(Error::InvalidPropertySet("Nested vector"));
+                }
+                Some(parse_typed(item, code_page, order)?)
+            } else {
+                parse_scalar(element, &body[pos.min(body.len())..], code_page, order)?
+            };
+            match parsed {
+                Some((value, len)) if len > 0 => {
+                    values.push(value);
+                    pos += len;
+                }
+                _ => return Ok((PropertyValue::Unknown(vt, body.to_vec()), data.len())),
+            }
+        }
+        return Ok((PropertyValue::Vector(element, values), 4 + pad4(pos)));
+    }
+    match parse_scalar(vt, body, code_page, order)? {
+        Some((value, len)) if vt == VT_BSTR || vt == VT_LPSTR => Ok((value, 4 + len)),
+        Some((value, len)) => Ok((value, 4 + pad4(len))),
+        None => Ok((PropertyValue::Unknown(vt, body.to_vec()), data.len())),
+    }
+}
+
+/// Parses a value of type `vt`, returning the value and its size, or `None`
+/// if the type isn't supported.
+///
+/// Strings of 8-bit characters aren't padded: Office doesn't pad them in
+/// vectors, and anywhere else their size doesn't matter.
+fn parse_scalar(
+    vt: u16,
+    data: &[u8],
+    code_page: u16,
+    order: ByteOrder,
+) -> Result<Option<(PropertyValue, usize)>, Error> {
+    let value = match vt {
+        VT_EMPTY => (PropertyValue::Empty, 0),
+        VT_NULL => (PropertyValue::Null, 0),
+        VT_I1 => (PropertyValue::I1(bytes(data, 0, 1)?[0] as i8), 1),
+        VT_UI1 => (PropertyValue::UI1(bytes(data, 0, 1)?[0]), 1),
+        VT_I2 => (
+            PropertyValue::I2(u16::from_slice_with(bytes(data, 0, 2)?, order) as i16),
+            2,
+        ),
+        VT_UI2 => (
+            PropertyValue::UI2(u16::from_slice_with(bytes(data, 0, 2)?, order)),
+            2,
+        ),
+        VT_BOOL => (
+            PropertyValue::Bool(u16::from_slice_with(bytes(data, 0, 2)?, order) != 0),
+            2,
+        ),
+        VT_I4 | VT_INT => (
+            PropertyValue::I4(i32::from_slice_with(bytes(data, 0, 4)?, order)),
+            4,
+        ),
+        VT_UI4 | VT_UINT => (
+            PropertyValue::UI4(u32::from_slice_with(bytes(data, 0, 4)?, order)),
+            4,
+        ),
+        VT_ERROR => (
+            PropertyValue::Error(u32::from_slice_with(bytes(data, 0, 4)?, order)),
+            4,
+        ),
+        VT_R4 => (
+            PropertyValue::R4(f32::from_bits(u32::from_slice_with(
+                bytes(data, 0, 4)?,
+                order,
+            ))),
+            4,
+        ),
+        VT_I8 => (
+            PropertyValue::I8(u64::from_slice_with(bytes(data, 0, 8)?, order) as i64),
+            8,
+        ),
+        VT_UI8 => (
+            PropertyValue::UI8(u64::from_slice_with(bytes(data, 0, 8)?, order)),
+            8,
+        ),
+        VT_CY => (
+            PropertyValue::Currency(u64::from_slice_with(bytes(data, 0, 8)?, order) as i64),
+            8,
+        ),
+        VT_R8 => (
+            PropertyValue::R8(f64::from_bits(u64::from_slice_with(
+                bytes(data, 0, 8)?,
+                order,
+            ))),
+            8,
+        ),
+        VT_DATE => (
+            PropertyValue::Date(f64::from_bits(u64::from_slice_with(
+                bytes(data, 0, 8)?,
+                order,
+            ))),
+            8,
+        ),
+        VT_FILETIME => (
+            PropertyValue::FileTime(FileTime::new(filetime_from_slice(
+                bytes(data, 0, 8)?,
+                order,
+            ))),
+            8,
+        ),
+        VT_CLSID => {
+            let clsid = Clsid::from_slice_with(bytes(data, 0, 16)?, order);
+            (PropertyValue::Clsid(clsid), 16)
+        }
+        VT_BSTR | VT_LPSTR => {
+            let size = usize::from_slice_with(bytes(data, 0, 4)?, order);
+            let s = decode(bytes(data, 4, size)?, code_page, order);
+            let len = if code_page == CP_WINUNICODE {
+                4 + pad4(size)
+            } else {
+                4 + size
+            };
+            if vt == VT_BSTR {
+                (PropertyValue::Bstr(s), len)
+            } else {
+                (PropertyValue::LpStr(s), len)
+            }
+        }
+        VT_LPWSTR => {
+            let size = usize::from_slice_with(bytes(data, 0, 4)?, order)
+                .checked_mul(2)
+                .ok_or(Error::InvalidPropertySet("Value out of bounds"))?;
+            let s = decode(bytes(data, 4, size)?, CP_WINUNICODE, order);
+            (PropertyValue::LpWStr(s), 4 + pad4(size))
+        }
+        VT_BLOB => {
+            let size = usize::from_slice_with(bytes(data, 0, 4)?, order);
+            (
+                PropertyValue::Blob(bytes(data, 4, size)?.to_vec()),
+                4 + pad4(size),
+            )
+        }
+        VT_CF => {
+            let size = usize::from_slice_with(bytes(data, 0, 4)?, order);
+            if size < 4 {
+                return Err(Error::InvalidPropertySet("Bad clipboard data size"));
+            }
+            let format = i32::from_slice_with(bytes(data, 4, 4)?, order);
+            let data = bytes(data, 8, size - 4)?.to_vec();
+            (
+                PropertyValue::ClipboardData { format, data },
+                4 + pad4(size),
+            )
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(value))
+}
+
+/// Parses the dictionary property.
+fn parse_dictionary(data: &[u8], code_page: u16, order: ByteOrder) -> Result<PropertyValue, Error> {
+    let count = usize::from_slice_with(bytes(data, 0, 4)?, order);
+    let mut names = Vec::new();
+    let mut pos = 4;
+    for _ in 0..count {
+        let id = u32::from_slice_with(bytes(data, pos, 4)?, order);
+        let len = usize::from_slice_with(bytes(data, pos + 4, 4)?, order);
+        pos += 8;
+        if code_page == CP_WINUNICODE {
+            let size = len
+                .checked_mul(2)
+                .ok_or(Error::InvalidPropertySet("Value out of bounds"))?;
+            names.push((id, decode(bytes(data, pos, size)?, code_page, order)));
+            pos += pad4(size);
+        } else {
+            names.push((id, decode(bytes(data, pos, len)?, code_page, order)));
+            pos += len;
+        }
+    }
+    Ok(PropertyValue::Dictionary(names))
+}
+
+/// Returns the `VT_*` type of `value`, or `None` for a dictionary.
+fn value_type(value: &PropertyValue) -> Option<u16> {
+    let vt = match value {
+        PropertyValue::Empty => VT_EMPTY,
+        PropertyValue::Null => VT_NULL,
+        PropertyValue::I1(_) => VT_I1,
+        PropertyValue::UI1(_) => VT_UI1,
+        PropertyValue::I2(_) => VT_I2,
+        PropertyValue::UI2(_) => VT_UI2,
+        PropertyValue::I4(_) => VT_I4,
+        PropertyValue::UI4(_) => VT_UI4,
+        PropertyValue::I8(_) => VT_I8,
+        PropertyValue::UI8(_) => VT_UI8,
+        PropertyValue::R4(_) => VT_R4,
+        PropertyValue::R8(_) => VT_R8,
+        PropertyValue::Currency(_) => VT_CY,
+        PropertyValue::Date(_) => VT_DATE,
+        PropertyValue::Error(_) => VT_ERROR,
+        PropertyValue::Bool(_) => VT_BOOL,
+        PropertyValue::Bstr(_) => VT_BSTR,
+        PropertyValue::LpStr(_) => VT_LPSTR,
+        PropertyValue::LpWStr(_) => VT_LPWSTR,
+        PropertyValue::FileTime(_) => VT_FILETIME,
+        PropertyValue::Blob(_) => VT_BLOB,
+        PropertyValue::ClipboardData { .. } => VT_CF,
+        PropertyValue::Clsid(_) => VT_CLSID,
+        PropertyValue::Vector(element, _) => VT_VECTOR | element,
+        PropertyValue::Dictionary(_) => return None,
+        PropertyValue::Unknown(vt, _) => *vt,
+    };
+    Some(vt)
+}
+
+/// Encodes a `TypedPropertyValue`, the counterpart of `parse_typed`.
+fn encode_typed(value: &PropertyValue, code_page: u16, out: &mut Vec<u8>) -> Result<(), Error> {
+    let vt = value_type(value).ok_or(Error::InvalidPropertySet("Bad dictionary"))?;
+    let start = out.len();
+    out.extend_from_slice(&vt.to_le_bytes());
+    out.extend_from_slice(&[0u8; 2]);
+    encode_value(value, code_page, out)?;
+    if vt != VT_BSTR && vt != VT_LPSTR {
+        out.resize(start + pad4(out.len() - start), 0);
+    }
+    Ok(())
+}
+
+/// Encodes a value without its type, the counterpart of `parse_scalar`.
+fn encode_value(value: &PropertyValue, code_page: u16, out: &mut Vec<u8>) -> Result<(), Error> {
+    match value {
+        PropertyValue::Empty | PropertyValue::Null => {}
+        PropertyValue::I1(i) => out.push(*i as u8),
+        PropertyValue::UI1(i) => out.push(*i),
+        PropertyValue::I2(i) => out.extend_from_slice(&i.to_le_bytes()),
+        PropertyValue::UI2(i) => out.extend_from_slice(&i.to_le_bytes()),
+        PropertyValue::Bool(b) => {
+            out.extend_from_slice(&(if *b { 0xFFFFu16 } else { 0 }).to_le_bytes())
+        }
+        PropertyValue::I4(i) => out.extend_from_slice(&i.to_le_bytes()),
+        PropertyValue::UI4(i) | PropertyValue::Error(i) => out.extend_from_slice(&i.to_le_bytes()),
+        PropertyValue::R4(f) => out.extend_from_slice(&f.to_le_bytes()),
+        PropertyValue::I8(i) | PropertyValue::Currency(i) => {
+            out.extend_from_slice(&i.to_le_bytes())
+        }
+        PropertyValue::UI8(i) => out.extend_from_slice(&i.to_le_bytes()),
+        PropertyValue::FileTime(t) => out.extend_from_slice(&t.as_u64().to_le_bytes()),
+        PropertyValue::R8(f) | PropertyValue::Date(f) => out.extend_from_slice(&f.to_le_bytes()),
+        PropertyValue::Clsid(clsid) => out.extend_from_slice(clsid.as_bytes()),
+        PropertyValue::Bstr(s) | PropertyValue::LpStr(s) => {
+            let encoded = encode(s, code_page)?;
+            out.extend_from_slice(&to_u32(encoded.len())?.to_le_bytes());
+            out.extend_from_slice(&encoded);
+            if code_page == CP_WINUNICODE {
+                out.resize(out.len() + pad4(encoded.len()) - encoded.len(), 0);
+            }
+        }
+        PropertyValue::LpWStr(s) => {
+            let encoded = encode(s, CP_WINUNICODE)?;
+            out.extend_from_slice(&to_u32(encoded.len() / 2)?.to_le_bytes());
+            out.extend_from_slice(&encoded);
+            out.resize(out.len() + pad4(encoded.len()) - encoded.len(), 0);
+        }
+        PropertyValue::Blob(data) => {
+            out.extend_from_slice(&to_u32(data.len())?.to_le_bytes());
+            out.extend_from_slice(data);
+            out.resize(out.len() + pad4(data.len()) - data.len(), 0);
+        }
+        PropertyValue::ClipboardData { format, data } => {
+            out.extend_from_slice(&to_u32(4 + data.len())?.to_le_bytes());
+            out.extend_from_slice(&format.to_le_bytes());
+            out.extend_from_slice(data);
+            out.resize(out.len() + pad4(data.len()) - data.len(), 0);
+        }
+        PropertyValue::Vector(element, values) => {
+            out.extend_from_slice(&to_u32(values.len())?.to_le_bytes());
+            for value in values.iter() {
+                if *element == VT_VARIANT {
+                    encode_typed(value, code_page, out)?;
+                } else if value_type(value) == Some(*element) {
+                    encode_value(value, code_page, out)?;
+                } else {
+                    return Err(Error::InvalidPropertySet("Bad vector element type"));
+                }
+            }
+        }
+        PropertyValue::Dictionary(_) => return Err(Error::InvalidPropertySet("Bad dictionary")),
+        PropertyValue::Unknown(_, data) => out.extend_from_slice(data),
+    }
+    Ok(())
+}
+
+/// Encodes the dictionary property, the counterpart of `parse_dictionary`.
+fn encode_dictionary(
+    names: &[(u32, String)],
+    code_page: u16,
+    out: &mut Vec<u8>,
+) -> Result<(), Error> {
+    out.extend_from_slice(&to_u32(names.len())?.to_le_bytes());
+    for (id, name) in names.iter() {
+        let encoded = encode(name, code_page)?;
+        out.extend_from_slice(&id.to_le_bytes());
+        if code_page == CP_WINUNICODE {
+            out.extend_from_slice(&to_u32(encoded.len() / 2)?.to_le_bytes());
+            out.extend_from_slice(&encoded);
+            out.resize(out.len() + pad4(encoded.len()) - encoded.len(), 0);
+        } else {
+            out.extend_from_slice(&to_u32(encoded.len())?.to_le_bytes());
+            out.extend_from_slice(&encoded);
+        }
+    }
+    Ok(())
+}
+
+/// Characters 0x80 to 0x9F of Windows-1252. The others match Latin-1.
+const WINDOWS_1252: [char; 32] = [
+    '\u{20AC}', '\u{0081}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{008D}', '\u{017D}', '\u{008F}',
+    '\u{0090}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{009D}', '\u{017E}', '\u{0178}',
+];
+
+/// Decodes a null-terminated string in `code_page`.
+///
+/// Code pages other than UTF-16, UTF-8 and Windows-1252 are decoded as
+/// Latin-1.
+fn decode(bytes: &[u8], code_page: u16, order: ByteOrder) -> String {
+    let s: String = match code_page {
+        CP_WINUNICODE => String::from_utf16_lossy(
+            &bytes
+                .chunks_exact(2)
+                .map(|unit| u16::from_slice_with(unit, order))
+                .collect::<Vec<u16>>(),
+        ),
+        CP_UTF8 => String::from_utf8_lossy(bytes).into_owned(),
+        CP_WINDOWS_1252 => bytes
+            .iter()
+            .map(|&b| match b {
+                0x80..=0x9F => WINDOWS_1252[usize::from(b - 0x80)],
+                _ => char::from(b),
+            })
+            .collect(),
+        _ => bytes.iter().map(|&b| char::from(b)).collect(),
+    };
+    match s.find('\0') {
+        Some(end) => s[..end].to_string(),
+        None => s,
+    }
+}
+
+/// Encodes `s` in `code_page`, followed by a null character. This is the
+/// counterpart of `decode`.
+fn encode(s: &str, code_page: u16) -> Result<Vec<u8>, Error> {
+    let unsupported = || Error::InvalidPropertySet("Character not supported by the code page");
+    let mut buf: Vec<u8> = match code_page {
+        CP_WINUNICODE => s.encode_utf16().flat_map(u16::to_le_bytes).collect(),
+        CP_UTF8 => s.as_bytes().to_vec(),
+        CP_WINDOWS_1252 => s
+            .chars()
+            .map(|c| match WINDOWS_1252.iter().position(|&w| w == c) {
+                Some(i) => Ok(0x80 + i as u8),
+                None if ('\u{80}'..='\u{9F}').contains(&c) => Err(unsupported()),
+                None => u8::try_from(c).map_err(|_| unsupported()),
+            })
+            .collect::<Result<_, _>>()?,
+        _ => s
+            .chars()
+            .map(|c| u8::try_from(c).map_err(|_| unsupported()))
+            .collect::<Result<_, _>>()?,
+    };
+    if code_page == CP_WINUNICODE {
+        buf.extend_from_slice(&[0u8; 2]);
+    } else {
+        buf.push(0);
+    }
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EntryType;
+
+    #[test]
+    fn summary_information() {
+        let ole = Reader::from_path("./assets/sample.doc").unwrap();
+        let summary = ole.summary_information().unwrap();
+        assert_eq!(summary.sections().len(), 1);
+        let section = summary.section(&FMTID_SUMMARY_INFORMATION).unwrap();
+        assert_eq!(section.code_page(), Some(CP_WINDOWS_1252));
+        assert_eq!(section.properties().len(), 17);
+        assert_eq!(summary.title(), Some(""));
+        assert_eq!(summary.author(), Some("Lexis Nexis Group"));
+        assert_eq!(summary.last_saved_by(), Some("Lexis Nexis Group"));
+        assert_eq!(summary.template(), Some("Normal.dot"));
+        assert_eq!(summary.revision(), Some("7"));
+        assert_eq!(summary.application_name(), Some("Microsoft Word 9.0"));
+        assert_eq!(
+            summary.created().unwrap().to_string(),
+            "2001-09-10 21:52:00 UTC"
+        );
+        assert_eq!(
+            summary.last_saved(),
+            Some(FileTime::new(0x01C2_D145_6B1F_F400))
+        );
+        assert_eq!(summary.edit_time(), Some(Duration::from_secs(2460)));
+        assert_eq!(summary.page_count(), Some(1));
+        assert_eq!(summary.word_count(), Some(201));
+        assert_eq!(summary.char_count(), Some(1147));
+        assert_eq!(summary.company(), None);
+    }
+
+    #[test]
+    fn document_summary_information() {
+        let ole = Reader::from_path("./assets/sample.doc").unwrap();
+        let summary = ole.document_summary_information().unwrap();
+        assert_eq!(summary.sections().len(), 2);
+        assert_eq!(summary.company(), Some("MBCO"));
+        assert_eq!(
+            summary.get(&FMTID_DOC_SUMMARY_INFORMATION, 0x0C),
+            Some(&PropertyValue::Vector(
+                VT_VARIANT,
+                vec![
+                    PropertyValue::LpStr("Title".to_string()),
+                    PropertyValue::I4(1)
+                ]
+            ))
+        );
+        assert_eq!(
+            summary.get(&FMTID_DOC_SUMMARY_INFORMATION, 0x0D),
+            Some(&PropertyValue::Vector(
+                VT_LPSTR,
+                vec![PropertyValue::LpStr(String::new())]
+            ))
+        );
+
+        let user = summary.section(&FMTID_USER_DEFINED_PROPERTIES).unwrap();
+        assert_eq!(
+            user.dictionary(),
+            Some(&[(2, "_PID_HLINKS".to_string())][..])
+        );
+        match user.get_by_name("_PID_HLINKS") {
+            Some(PropertyValue::Blob(blob)) => assert_eq!(blob.len(), 0x64),
+            other => panic!("unexpected value {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unpadded_string_vector() {
+        let ole = Reader::from_path("./assets/sample.ppt").unwrap();
+        let summary = ole.document_summary_information().unwrap();
+        assert_eq!(summary.company(), Some("DePaul"));
+        match summary.get(&FMTID_DOC_SUMMARY_INFORMATION, 0x0D) {
+            Some(PropertyValue::Vector(VT_LPSTR, names)) => {
+                assert_eq!(names[0].as_str(), Some("Arial"));
+                assert_eq!(names[1].as_str(), Some("Wingdings"));
+            }
+            other => panic!("unexpected value {:?}", other),
+        }
+
+        let summary = ole.summary_information().unwrap();
+        assert!(matches!(
+            summary.get(&FMTID_SUMMARY_INFORMATION, PIDSI_THUMBNAIL),
+            Some(PropertyValue::ClipboardData { .. })
+        ));
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(matches!(
+            PropertySet::parse(&[0u8; 48]),
+            Err(Error::InvalidPropertySet(_))
+        ));
+        let mut buf = vec![0u8; 48];
+        buf[0..2].copy_from_slice(&BYTE_ORDER_MARK.to_le_bytes());
+        buf[24..28].copy_from_slice(&1u32.to_le_bytes());
+        buf[44..48].copy_from_slice(&0xFFFF_FFF0u32.to_le_bytes());
+        assert!(matches!(
+            PropertySet::parse(&buf),
+            Err(Error::InvalidPropertySet(_))
+        ));
+        buf[44..48].copy_from_slice(&40u32.to_le_bytes());
+        buf[40..44].copy_from_slice(&0xFFFF_FFFFu32.to_le_bytes());
+        assert!(PropertySet::parse(&buf).is_err());
+    }
+
+    #[test]
+    fn nested_vectors() {
+        // A vector of variants, each holding the next vector, down to an
+        // integer.
+        let mut value = Vec::new();
+        for _ in 0..200_000 {
+            value.extend_from_slice(&(VT_VECTOR | VT_VARIANT).to_le_bytes());
+            value.extend_from_slice(&[0, 0, 1, 0, 0, 0]);
+        }
+        value.extend_from_slice(&[3, 0, 0, 0, 7, 0, 0, 0]);
+
+        let mut buf = vec![0u8; 48];
+        buf[0..2].copy_from_slice(&BYTE_ORDER_MARK.to_le_bytes());
+        buf[24..28].copy_from_slice(&1u32.to_le_bytes());
+        buf[44..48].copy_from_slice(&48u32.to_le_bytes());
+        buf.extend_from_slice(&(16 + value.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&1u32.to_le_bytes());
+        buf.extend_from_slice(&2u32.to_le_bytes());
+        buf.extend_from_slice(&16u32.to_le_bytes());
+        buf.extend_from_slice(&value);
+        assert!(matches!(
+            PropertySet::parse(&buf),
+            Err(Error::InvalidPropertySet("Nested vector"))
+        ));
+    }
+
+    #[test]
+    fn big_endian() {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&BYTE_ORDER_MARK.to_be_bytes());
+        buf.extend_from_slice(&1u16.to_be_bytes());
+        buf.extend_from_slice(&SYSTEM_IDENTIFIER.to_be_bytes());
+        buf.extend_from_slice(&[0u8; 16]);
+        buf.extend_from_slice(&1u32.to_be_bytes());
+        buf.extend_from_slice(&FMTID_SUMMARY_INFORMATION);
+        buf.extend_from_slice(&48u32.to_be_bytes());
+        let table = [
+            68,
+            3,
+            PID_CODEPAGE,
+            32,
+            PIDSI_TITLE,
+            40,
+            PIDSI_CREATE_DTM,
+            56,
+        ];
+        buf.extend(table.iter().flat_map(|value| value.to_be_bytes()));
+        // VT_I2 1200, VT_LPSTR "Hi" in UTF-16, VT_FILETIME.
+        buf.extend_from_slice(&[0, 0x02, 0, 0, 0x04, 0xB0, 0, 0]);
+        buf.extend_from_slice(&[0, 0x1E, 0, 0, 0, 0, 0, 6]);
+        buf.extend_from_slice(&[0, b'H', 0, b'i', 0, 0, 0, 0]);
+        buf.extend_from_slice(&[0, 0x40, 0, 0]);
+        buf.extend_from_slice(&[0xD1, 0x7D, 0x80, 0x00, 0x01, 0xC1, 0x3A, 0x42]);
+
+        let set = PropertySet::parse(&buf).unwrap();
+        assert_eq!(set.version(), 1);
+        assert_eq!(set.title(), Some("Hi"));
+        assert_eq!(
+            set.created().unwrap().to_string(),
+            "2001-09-10 21:52:00 UTC"
+        );
+
+        // Written back as little-endian.
+        let written = PropertySet::parse(&set.to_bytes().unwrap()).unwrap();
+        assert_eq!(written.to_bytes().unwrap()[0..2], [0xFE, 0xFF]);
+        assert_eq!(written, set);
+    }
+
+    #[test]
+    fn code_pages() {
+        assert_eq!(
+            decode(
+                b"caf\xe9 \x80\0junk",
+                CP_WINDOWS_1252,
+                ByteOrder::LittleEndian
+            ),
+            "café €"
+        );
+        assert_eq!(
+            decode("café".as_bytes(), CP_UTF8, ByteOrder::LittleEndian),
+            "café"
+        );
+        assert_eq!(
+            decode(b"c\0a\0\0\0", CP_WINUNICODE, ByteOrder::LittleEndian),
+            "ca"
+        );
+        assert_eq!(
+            encode("café €", CP_WINDOWS_1252).unwrap(),
+            b"caf\xe9 \x80\0"
+        );
+        assert_eq!(encode("ca", CP_WINUNICODE).unwrap(), b"c\0a\0\0\0");
+        assert!(encode("\u{3A9}", CP_WINDOWS_1252).is_err());
+    }
+
+    #[test]
+    fn change_code_page() {
+        let ole = Reader::from_path("./assets/sample.doc").unwrap();
+        for path in ["\u{5}SummaryInformation", "\u{5}DocumentSummaryInformation"] {
+            let original = PropertySet::parse(&read(&ole, path)).unwrap();
+            let mut set = original.clone();
+            for fmtid in original.sections().iter().map(Section::fmtid) {
+                set.section_mut(fmtid).unwrap().set_code_page(CP_WINUNICODE);
+            }
+
+            let written = PropertySet::parse(&set.to_bytes().unwrap()).unwrap();
+            for (section, expected) in written.sections().iter().zip(original.sections()) {
+                assert_eq!(section.code_page(), Some(CP_WINUNICODE));
+                for property in expected.properties() {
+                    if property.id() != PID_CODEPAGE {
+                        assert_eq!(section.get(property.id()), Some(property.value()));
+                    }
+                }
+            }
+        }
+    }
+
+    fn read(ole: &Reader, path: &str) -> Vec<u8> {
+        let mut buf = Vec::new();
+        ole.open_stream(path)
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        buf
+    }
+
+    #[test]
+    fn write_unchanged() {
+        let ole = Reader::from_path("./assets/sample.doc").unwrap();
+        let original = read(&ole, "\u{5}SummaryInformation");
+        let summary = PropertySet::parse(&original).unwrap();
+        assert_eq!(summary.to_bytes().unwrap(), original);
+
+        for (file, path) in [
+            ("./assets/sample.doc", "\u{5}DocumentSummaryInformation"),
+            ("./assets/sample.ppt", "\u{5}SummaryInformation"),
+            ("./assets/sample.ppt", "\u{5}DocumentSummaryInformation"),
+        ] {
+            let ole = Reader::from_path(file).unwrap();
+            let set = ole.property_set(path).unwrap();
+            let written = set.to_bytes().unwrap();
+            assert_eq!(PropertySet::parse(&written).unwrap(), set);
+            assert_eq!(
+                PropertySet::parse(&written).unwrap().to_bytes().unwrap(),
+                written
+            );
+        }
+    }
+
+    #[test]
+    fn write_modified() {
+        let values = vec![
+            (2, PropertyValue::LpStr("caf\u{e9}".to_string())),
+            (3, PropertyValue::LpWStr("\u{3A9}mega".to_string())),
+            (4, PropertyValue::I2(-2)),
+            (5, PropertyValue::Bool(true)),
+            (
+                6,
+                PropertyValue::FileTime(FileTime::new(0x01C1_3A42_D17D_8000)),
+            ),
+            (7, PropertyValue::Blob(vec![1, 2, 3])),
+            (
+                8,
+                PropertyValue::ClipboardData {
+                    format: -1,
+                    data: vec![4, 5],
+                },
+            ),
+            (
+                9,
+                PropertyValue::Vector(
+                    VT_VARIANT,
+                    vec![
+                        PropertyValue::LpStr("Title".to_string()),
+                        PropertyValue::I4(1),
+                    ],
+                ),
+            ),
+            (
+                10,
+                PropertyValue::Vector(
+                    VT_LPSTR,
+                    vec![
+                        PropertyValue::LpStr("Arial".to_string()),
+                        PropertyValue::LpStr(String::new()),
+                    ],
+                ),
+            ),
+            (11, PropertyValue::Vector(VT_I2, vec![PropertyValue::I2(7)])),
+            (
+                PID_DICTIONARY,
+                PropertyValue::Dictionary(vec![(2, "Name".to_string())]),
+            ),
+        ];
+
+        for code_page in [CP_WINDOWS_1252, CP_WINUNICODE, CP_UTF8] {
+            let mut set = PropertySet::new();
+            set.insert_section(FMTID_USER_DEFINED_PROPERTIES)
+                .set_code_page(code_page);
+            for (id, value) in values.iter() {
+                set.set(FMTID_USER_DEFINED_PROPERTIES, *id, value.clone());
+            }
+            let parsed = PropertySet::parse(&set.to_bytes().unwrap()).unwrap();
+            assert_eq!(parsed, set);
+            let section = parsed.section(&FMTID_USER_DEFINED_PROPERTIES).unwrap();
+            assert_eq!(section.code_page(), Some(code_page));
+            assert_eq!(
+                section.get_by_name("Name").and_then(PropertyValue::as_str),
+                Some("caf\u{e9}")
+            );
+        }
+
+        let mut set = PropertySet::new();
+        set.set(
+            FMTID_SUMMARY_INFORMATION,
+            PIDSI_TITLE,
+            PropertyValue::Vector(VT_I4, vec![PropertyValue::I2(1)]),
+        );
+        assert!(set.to_bytes().is_err());
+    }
+
+    #[test]
+    fn scrub() {
+        let mut editor = Editor::from_path("./assets/sample.doc").unwrap();
+        let mut summary = editor.property_set("\u{5}SummaryInformation").unwrap();
+        let removed = {
+            let ole = Reader::from_path("./assets/sample.doc").unwrap();
+            let summary = ole.summary_information().unwrap();
+            let doc_summary = ole.document_summary_information().unwrap();
+            [
+                summary.author().unwrap().to_string(),
+                summary.last_saved_by().unwrap().to_string(),
+                doc_summary.company().unwrap().to_string(),
+            ]
+        };
+        assert!(summary
+            .remove(&FMTID_SUMMARY_INFORMATION, PIDSI_AUTHOR)
+            .is_some());
+        summary.set(
+            FMTID_SUMMARY_INFORMATION,
+            PIDSI_LASTAUTHOR,
+            PropertyValue::LpStr(String::new()),
+        );
+        editor
+            .write_property_set("\u{5}SummaryInformation", &summary)
+            .unwrap();
+
+        let mut doc_summary = editor
+            .property_set("\u{5}DocumentSummaryInformation")
+            .unwrap();
+        doc_summary.remove(&FMTID_DOC_SUMMARY_INFORMATION, PIDDSI_COMPANY);
+        editor
+            .write_property_set("\u{5}DocumentSummaryInformation", &doc_summary)
+            .unwrap();
+
+        let mut buf = Vec::new();
+        editor.write_to(&mut buf).unwrap();
+        // The removed strings are gone from the file, not only from the
+        // property sets: they are only found in the other streams.
+        let ole = Reader::new(&buf[..]).unwrap();
+        let count =
+            |buf: &[u8], s: &str| buf.windows(s.len()).filter(|w| *w == s.as_bytes()).count();
+        for removed in removed {
+            let in_streams: usize = ole
+                .walk()
+                .filter(|(_, _, entry)| entry._type() == EntryType::UserStream && !entry.is_empty())
+                .map(|(_, _, entry)| {
+                    let mut data = Vec::new();
+                    ole.get_entry_slice(entry)
+                        .unwrap()
+                        .read_to_end(&mut data)
+                        .unwrap();
+                    count(&data, &removed)
+                })
+                .sum();
+            assert_eq!(count(&buf, &removed), in_streams, "{removed}");
+        }
+        let summary = ole.summary_information().unwrap();
+        assert_eq!(summary.author(), None);
+        assert_eq!(summary.last_saved_by(), Some(""));
+        assert_eq!(summary.template(), Some("Normal.dot"));
+        assert_eq!(summary.word_count(), Some(201));
+
+        let doc_summary = ole.document_summary_information().unwrap();
+        assert_eq!(doc_summary.company(), None);
+        let user = doc_summary.section(&FMTID_USER_DEFINED_PROPERTIES).unwrap();
+        assert_eq!(
+            user.dictionary(),
+            Some(&[(2, "_PID_HLINKS".to_string())][..])
+        );
+    }
+}
@@ -0,0 +1,69 @@
+//             DO WHAT THE FUCK YOU WANT TO PUBLIC LICENSE
+//                    Version 2, December 2004
+//
+// Copyright (C) 2018 Thomas Bailleux <thomas@bailleux.me>
+//
+// Everyone is permitted to copy and distribute verbatim or modified
+// copies of this license document, and changing it is allowed as long
+// as the name is changed.
+//
+//            DO WHAT THE FUCK YOU WANT TO PUBLIC LICENSE
+//   TERMS AND CONDITIONS FOR COPYING, DISTRIBUTION AND MODIFICATION
+//
+//  0. You just DO WHAT THE FUCK YOU WANT TO.
+//
+// Author: zadig <thomas chr(0x40) bailleux.me>
+
+use std::collections::HashMap;
+
+use crate::{EntryType, Reader, Version, Writer};
+
+/// A version 3 file written by `Writer`, which tests damage to check how
+/// it is read.
+pub(crate) struct Sample {
+    /// The file.
+    pub(crate) buf: Vec<u8>,
+
+    /// Id, offset of the directory entry and chain of each entry, by name.
+    entries: HashMap<String, (u32, usize, Vec<u32>)>,
+}
+
+impl Sample {
+    /// Writes a file holding `streams`, given by name and content, in its
+    /// root storage.
+    pub(crate) fn new(streams: &[(&str, &[u8])]) -> Sample {
+        let mut writer = Writer::new(Version::V3);
+        for (name, data) in streams {
+            writer.create_stream(name, data).unwrap();
+        }
+        let mut buf = Vec::new();
+        writer.write_to(&mut buf).unwrap();
+        let ole = Reader::new(&buf[..]).unwrap();
+        let entries = ole
+            .iterate()
+            .filter(|entry| entry._type() != EntryType::Empty)
+            .map(|entry| {
+                let offset = ole.entry_offset(entry.id()).unwrap();
+                let info = (entry.id(), offset, entry.sec_id_chain.clone());
+                (entry.name().to_string(), info)
+            })
+            .collect();
+        drop(ole);
+        Sample { buf, entries }
+    }
+
+    /// Returns the id of the entry `name`.
+    pub(crate) fn id(&self, name: &str) -> u32 {
+        self.entries[name].0
+    }
+
+    /// Returns the offset of the directory entry of `name`.
+    pub(crate) fn offset(&self, name: &str) -> usize {
+        self.entries[name].1
+    }
+
+    /// Returns the chain of the entry `name`.
+    pub(crate) fn chain(&self, name: &str) -> &[u32] {
+        &self.entries[name].2
+    }
+}
@@ -14,24 +14,94 @@
 //
 // Author: zadig <thomas chr(0x40) bailleux.me>
 
-use std::{self, vec::Vec};
+use std::{self, collections::HashSet, vec::Vec};
 
 use super::{constants, error::Error, ole::Reader, util::FromSlice};
 
+/// An entry of the FAT or of the mini FAT, as returned by
+/// `Reader::fat_entries` and `Reader::mini_fat_entries`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SectorId {
+    /// The sector is followed by this sector in its chain.
+    Regular(u32),
+
+    /// A reserved value, which shouldn't be found in a file.
+    Reserved(u32),
+
+    /// The sector is a DIFAT sector.
+    DifSect,
+
+    /// The sector is a FAT sector.
+    FatSect,
+
+    /// The sector is the last of its chain.
+    EndOfChain,
+
+    /// The sector is unused.
+    Free,
+}
+
+impl From<u32> for SectorId {
+    fn from(value: u32) -> SectorId {
+        match value {
+            0..=constants::SECID_MAX => SectorId::Regular(value),
+            constants::SECID_DIFAT_SECTOR => SectorId::DifSect,
+            constants::SECID_FAT_SECTOR => SectorId::FatSect,
+            constants::SECID_END_OF_CHAIN => SectorId::EndOfChain,
+            constants::SECID_FREE_SECTOR => SectorId::Free,
+            _ => SectorId::Reserved(value),
+        }
+    }
+}
+
+impl From<SectorId> for u32 {
+    fn from(id: SectorId) -> u32 {
+        match id {
+            SectorId::Regular(value) | SectorId::Reserved(value) => value,
+            SectorId::DifSect => constants::SECID_DIFAT_SECTOR,
+            SectorId::FatSect => constants::SECID_FAT_SECTOR,
+            SectorId::EndOfChain => constants::SECID_END_OF_CHAIN,
+            SectorId::Free => constants::SECID_FREE_SECTOR,
+        }
+    }
+}
+
 impl<'ole> Reader<'ole> {
-    // Use the DIFAT sector ID's `main_sat` to assemble the FAT `sat`.
-    pub(crate) fn build_sat(&mut self) -> Result<(), Error> {
-        let sector_size = self.sec_size;
-        //let mut sec_ids = vec![constants::FREE_SECID_U32; sector_size / 4];
+    /// Returns the entries of the FAT: the entry `i` describes the sector
+    /// `i`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ole::SectorId;
+    /// let parser = ole::Reader::from_path("assets/Thumbs.db").unwrap();
+    ///
+    /// for (i, id) in parser.fat_entries().enumerate() {
+    ///     match id {
+    ///         SectorId::Regular(next) => println!("{i:#x}: {next:#x}"),
+    ///         other => println!("{i:#x}: {other:?}"),
+    ///     }
+    /// }
+    /// ```
+    pub fn fat_entries(&self) -> impl Iterator<Item = SectorId> + '_ {
+        self.sat.iter().map(|&id| SectorId::from(id))
+    }
 
-        let mut sec_ids = Vec::with_capacity(sector_size / 4);
-        let msat_entries = &self.main_sat;
+    /// Returns the entries of the mini FAT: the entry `i` describes the mini
+    /// sector `i`.
+    pub fn mini_fat_entries(&self) -> impl Iterator<Item = SectorId> + '_ {
+        self.ssat.iter().map(|&id| SectorId::from(id))
+    }
 
-        if msat_entries.is_empty() {
+    // Use the DIFAT sector ID's `main_sat` to assemble the FAT `sat`.
+    pub(crate) fn build_sat(&mut self) -> Result<(), Error> {
+        if self.main_sat.is_empty() {
             Err(Error::EmptyMasterSectorAllocationTable)
         } else {
-            for &sector_index in msat_entries.iter() {
-                self.read_sat_sector(sector_index, &mut sec_ids)?;
+            let mut sec_ids = Vec::with_capacity(self.sec_size / 4);
+            for i in 0..self.main_sat.len() {
+                let result = self.read_sat_sector(self.main_sat[i], &mut sec_ids);
+                self.recover_sat_sector(result, &mut sec_ids)?;
                 self.sat.extend_from_slice(&sec_ids);
                 sec_ids.clear();
             }
@@ -49,46 +119,91 @@ impl<'ole> Reader<'ole> {
         let sector = self.read_sector(sector_index)?;
 
         for data in sector.chunks_exact(constants::U32_SIZE) {
-            sec_ids.push(u32::from_slice(data));
+            sec_ids.push(u32::from_slice_with(data, self.byte_order));
         }
         Ok(())
     }
 
-    pub(crate) fn build_chain_from_sat(&mut self, start: u32) -> Vec<u32> {
-        let mut chain = Vec::new();
-        let mut sector_index = start;
-        while sector_index != constants::SECID_END_OF_CHAIN && sector_index != constants::SECID_FREE_SECTOR
-        {
-            chain.push(sector_index);
-            // println!("\t\tbuild_chain_from_sat 0x{sector_index:8x}");
-            sector_index = self.sat[sector_index as usize];
+    /// In lenient mode, fills `sec_ids` with free sectors in place of a FAT
+    /// or mini FAT sector which can't be read, so the following sectors of
+    /// the table keep their index.
+    fn recover_sat_sector(
+        &mut self,
+        result: Result<(), Error>,
+        sec_ids: &mut Vec<u32>,
+    ) -> Result<(), Error> {
+        if let Err(error) = result {
+            self.recover(error)?;
+            sec_ids.resize(
+                self.sec_size / constants::U32_SIZE,
+                constants::SECID_FREE_SECTOR,
+            );
         }
+        Ok(())
+    }
 
-        chain
+    /// Follows the FAT from `start`, which was read at `offset` in the
+    /// file.
+    ///
+    /// In lenient mode, a broken chain is cut where it breaks.
+    pub(crate) fn build_chain_from_sat(
+        &mut self,
+        start: u32,
+        offset: usize,
+    ) -> Result<Vec<u32>, Error> {
+        let mut chain = Vec::new();
+        let result = build_chain(
+            &self.sat,
+            start,
+            offset,
+            self.options.max_chain_length,
+            &mut chain,
+            |index| table_entry_offset(self, &self.main_sat, index),
+        );
+        self.recover_chain(result, chain)
     }
 
-    pub(crate) fn build_chain_from_ssat(&mut self, start: u32) -> Vec<u32> {
+    /// Follows the mini FAT from `start`, which was read at `offset` in the
+    /// file.
+    ///
+    /// In lenient mode, a broken chain is cut where it breaks.
+    pub(crate) fn build_chain_from_ssat(
+        &mut self,
+        start: u32,
+        offset: usize,
+    ) -> Result<Vec<u32>, Error> {
         let mut chain = Vec::new();
-        let mut sector_index = start;
-        while sector_index != constants::SECID_END_OF_CHAIN && sector_index != constants::SECID_FREE_SECTOR
-        {
-            chain.push(sector_index);
+        let result = build_chain(
+            &self.ssat,
+            start,
+            offset,
+            self.options.max_chain_length,
+            &mut chain,
+            |index| table_entry_offset(self, &self.ssat_sectors, index),
+        );
+        self.recover_chain(result, chain)
+    }
 
-            sector_index = self.ssat[sector_index as usize];
+    fn recover_chain(
+        &mut self,
+        result: Result<(), Error>,
+        chain: Vec<u32>,
+    ) -> Result<Vec<u32>, Error> {
+        if let Err(error) = result {
+            self.recover(error)?;
         }
-
-        chain
+        Ok(chain)
     }
 
     // Build the mini FAT `ssat`
     pub(crate) fn build_ssat(&mut self) -> Result<(), Error> {
-        let mut sec_ids = Vec::with_capacity(self.sec_size / 4);
-
         let sector_index = self.ssat.remove(0);
-        let chain = self.build_chain_from_sat(sector_index);
+        self.ssat_sectors = self.build_chain_from_sat(sector_index, 60)?;
 
-        for sector_index in chain {
-            self.read_sat_sector(sector_index, &mut sec_ids)?;
+        let mut sec_ids = Vec::with_capacity(self.sec_size / 4);
+        for i in 0..self.ssat_sectors.len() {
+            let result = self.read_sat_sector(self.ssat_sectors[i], &mut sec_ids);
+            self.recover_sat_sector(result, &mut sec_ids)?;
             self.ssat.extend_from_slice(&sec_ids);
             sec_ids.clear();
         }
@@ -97,12 +212,126 @@ impl<'ole> Reader<'ole> {
 
     pub(crate) fn build_dsat(&mut self) -> Result<(), Error> {
         let sector_index = self.dir_sat.remove(0);
-        let chain = self.build_chain_from_sat(sector_index);
+        self.dir_sat = self.build_chain_from_sat(sector_index, 48)?;
 
-        for sector_index in chain {
-            self.dir_sat.push(sector_index);
+        // Version 4 also gives the number of directory sectors.
+        let count = self.header.directory_sector_count();
+        if self.version_number == 4 && count as usize != self.dir_sat.len() {
+            self.recover(Error::InvalidHeaderField {
+                offset: 40,
+                value: count,
+            })?;
         }
-
         Ok(())
     }
 }
+
+/// Follows `table` from `start`, read at `offset`, until the end of the
+/// chain, failing if the chain loops or is longer than `max_length`. The
+/// sectors are pushed to `chain`, which holds the chain up to where it
+/// breaks on failure.
+/// `entry_offset` returns the offset in the file of an entry of the table,
+/// to report where an invalid sector id was read.
+fn build_chain(
+    table: &[u32],
+    start: u32,
+    offset: usize,
+    max_length: usize,
+    chain: &mut Vec<u32>,
+    entry_offset: impl Fn(u32) -> usize,
+) -> Result<(), Error> {
+    // Every sector of the table is visited at most once, which bounds the
+    // loop.
+    let mut visited = HashSet::new();
+    let mut sector_index = start;
+    let mut offset = offset;
+    while sector_index != constants::SECID_END_OF_CHAIN
+        && sector_index != constants::SECID_FREE_SECTOR
+    {
+        let Some(&next) = table.get(sector_index as usize) else {
+            return Err(Error::InvalidSectorId {
+                sector_id: sector_index,
+                offset,
+            });
+        };
+        if !visited.insert(sector_index) {
+            return Err(Error::ChainCycle {
+                sector_id: sector_index,
+                offset,
+            });
+        }
+        if chain.len() == max_length {
+            return Err(Error::LimitExceeded {
+                limit: "chain length",
+                max: max_length,
+            });
+        }
+        chain.push(sector_index);
+        offset = entry_offset(sector_index);
+        sector_index = next;
+    }
+
+    Ok(())
+}
+
+/// Returns the offset in the file of the entry `index` of an allocation
+/// table stored in `sectors`.
+fn table_entry_offset(reader: &Reader, sectors: &[u32], index: u32) -> usize {
+    let ids_per_sector = reader.sec_size / constants::U32_SIZE;
+    sectors
+        .get(index as usize / ids_per_sector)
+        .and_then(|&sector| reader.sector_offset(sector).ok())
+        .map_or(usize::MAX, |sector_offset| {
+            sector_offset + index as usize % ids_per_sector * constants::U32_SIZE
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SectorId;
+    use crate::{Reader, Version, Writer};
+
+    #[test]
+    fn sector_ids() {
+        for value in [
+            0,
+            0x1234,
+            0xFFFF_FFF9,
+            0xFFFF_FFFA,
+            0xFFFF_FFFB,
+            u32::MAX - 3,
+            u32::MAX,
+        ] {
+            assert_eq!(u32::from(SectorId::from(value)), value);
+        }
+        assert_eq!(SectorId::from(0xFFFF_FFFA), SectorId::Reserved(0xFFFF_FFFA));
+        assert_eq!(SectorId::from(0xFFFF_FFFC), SectorId::DifSect);
+        assert_eq!(SectorId::from(0xFFFF_FFFD), SectorId::FatSect);
+        assert_eq!(SectorId::from(0xFFFF_FFFE), SectorId::EndOfChain);
+        assert_eq!(SectorId::from(0xFFFF_FFFF), SectorId::Free);
+    }
+
+    #[test]
+    fn fat_entries() {
+        let mut writer = Writer::new(Version::V3);
+        writer.create_stream("Big", &[1u8; 5000]).unwrap();
+        writer.create_stream("Small", &[2u8; 100]).unwrap();
+        let mut buf = Vec::new();
+        writer.write_to(&mut buf).unwrap();
+        let ole = Reader::new(&buf[..]).unwrap();
+
+        let fat: Vec<SectorId> = ole.fat_entries().collect();
+        assert_eq!(fat.len(), 128);
+        assert_eq!(fat[0], SectorId::FatSect);
+        let chain = &ole.entry_by_path("Big").unwrap().sec_id_chain;
+        for pair in chain.windows(2) {
+            assert_eq!(fat[pair[0] as usize], SectorId::Regular(pair[1]));
+        }
+        assert_eq!(fat[chain[9] as usize], SectorId::EndOfChain);
+        assert_eq!(fat[127], SectorId::Free);
+
+        let mini_fat: Vec<SectorId> = ole.mini_fat_entries().collect();
+        assert_eq!(mini_fat[..2], [SectorId::Regular(1), SectorId::EndOfChain]);
+        assert_eq!(mini_fat[2], SectorId::Free);
+    }
+}
@@ -14,26 +14,60 @@
 //
 // Author: zadig <thomas chr(0x40) bailleux.me>
 
-use super::error::Error;
+use std::borrow::Cow;
+
+use super::{error::Error, source::BlockSource};
 
 impl<'ole> super::ole::Reader<'ole> {
-    pub(crate) fn read_sector(&self, sector_index: u32) -> Result<&[u8], Error> {
-        let sector_size = self.sec_size;
-        let sector_index = usize::try_from(sector_index).expect("sector_index to big for usize");
-        let offset = sector_size + sector_size * sector_index;
-        let max_size = offset + sector_size;
+    /// Reads the sector `sector_index`, which must be inside the file.
+    pub(crate) fn read_sector(&self, sector_index: u32) -> Result<Cow<'_, [u8]>, Error> {
+        let offset = self.checked_sector_offset(sector_index)?;
+        self.source.block(offset, self.sec_size)
+    }
 
-        // println!("\t + read_sector: {sector_index} 0x{offset:x}");
+    /// Returns the offset of the sector `sector_index` in the file.
+    pub(crate) fn sector_offset(&self, sector_index: u32) -> Result<usize, Error> {
+        (sector_index as usize)
+            .checked_add(1)
+            .and_then(|n| n.checked_mul(self.sec_size))
+            .ok_or(Error::SectorOutOfRange {
+                sector_id: sector_index,
+                offset: usize::MAX,
+            })
+    }
 
-        if self.body.len() >= max_size {
-            let sector = &self.body[offset..max_size];
-            Ok(sector)
+    /// Returns the offset of the sector `sector_index` in the file, checking
+    /// that the whole sector is inside the file.
+    pub(crate) fn checked_sector_offset(&self, sector_index: u32) -> Result<usize, Error> {
+        let offset = self.sector_offset(sector_index)?;
+        if offset
+            .checked_add(self.sec_size)
+            .is_some_and(|end| end <= self.source.len())
+        {
+            Ok(offset)
         } else {
-            println!(
-                "body: {} , max_size {max_size} {max_size:8x}",
-                self.body.len()
-            );
-            Err(Error::BadSizeValue("File is too short"))
+            Err(Error::SectorOutOfRange {
+                sector_id: sector_index,
+                offset,
+            })
         }
     }
+
+    /// Returns the offset in the file of the mini sector `mini_sector_id`.
+    pub(crate) fn mini_sector_offset(&self, mini_sector_id: u32) -> Result<usize, Error> {
+        let per_sector = self.sec_size / self.short_sec_size;
+        let mini_stream = self
+            .root_entry
+            .and_then(|id| self.entries.as_deref()?.get(id as usize))
+            .map(|root| &root.sec_id_chain[..])
+            .unwrap_or_default();
+        let sector_id = mini_stream
+            .get(mini_sector_id as usize / per_sector)
+            .ok_or(Error::SectorOutOfRange {
+                sector_id: mini_sector_id,
+                offset: mini_sector_id as usize * self.short_sec_size,
+            })?;
+        Ok(self.checked_sector_offset(*sector_id)?
+            + mini_sector_id as usize % per_sector * self.short_sec_size)
+    }
 }
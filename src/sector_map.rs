@@ -0,0 +1,253 @@
+//             DO WHAT THE FUCK YOU WANT TO PUBLIC LICENSE
+//                    Version 2, December 2004
+//
+// Copyright (C) 2018 Thomas Bailleux <thomas@bailleux.me>
+//
+// Everyone is permitted to copy and distribute verbatim or modified
+// copies of this license document, and changing it is allowed as long
+// as the name is changed.
+//
+//            DO WHAT THE FUCK YOU WANT TO PUBLIC LICENSE
+//   TERMS AND CONDITIONS FOR COPYING, DISTRIBUTION AND MODIFICATION
+//
+//  0. You just DO WHAT THE FUCK YOU WANT TO.
+//
+// Author: zadig <thomas chr(0x40) bailleux.me>
+
+use super::{
+    constants,
+    entry::{Entry, EntryType},
+    error::Error,
+    ole::Reader,
+    source::BlockSource,
+};
+
+/// What a sector of the file holds, as returned by `Reader::sector_map`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SectorKind {
+    /// The header, which fills the first sector.
+    Header,
+
+    /// A sector of the FAT.
+    Fat,
+
+    /// A sector of the DIFAT.
+    Difat,
+
+    /// A sector of the mini FAT.
+    MiniFat,
+
+    /// A sector of the directory.
+    Directory,
+
+    /// A sector of the mini stream, which holds the small streams.
+    MiniStream,
+
+    /// A sector of the stream of the directory entry with this id.
+    Stream(u32),
+
+    /// A sector marked free in the FAT.
+    Free,
+
+    /// A sector allocated in the FAT, or not described by it, but which
+    /// belongs to no chain.
+    Unreachable,
+}
+
+impl<'ole> Reader<'ole> {
+    /// Returns the kind of every sector of the file, including a last
+    /// incomplete one. The first element is the header, the element `i + 1`
+    /// is the sector `i`.
+    ///
+    /// A sector belonging to several chains is given the kind of the first
+    /// of them, in the order of the variants of `SectorKind`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ole::SectorKind;
+    /// let parser = ole::Reader::from_path("assets/Thumbs.db").unwrap();
+    ///
+    /// let map = parser.sector_map();
+    /// assert_eq!(map[0], SectorKind::Header);
+    /// let free = map.iter().filter(|&&kind| kind == SectorKind::Free).count();
+    /// println!("{free} free sectors");
+    /// ```
+    pub fn sector_map(&self) -> Vec<SectorKind> {
+        let sectors = self.source.len().div_ceil(self.sec_size).saturating_sub(1);
+        let mut map = vec![None; sectors];
+        let mut mark = |chain: &[u32], kind: SectorKind| {
+            for &sector_id in chain {
+                if let Some(slot @ None) = map.get_mut(sector_id as usize) {
+                    *slot = Some(kind);
+                }
+            }
+        };
+
+        mark(&self.main_sat, SectorKind::Fat);
+        mark(&self.difat_sectors, SectorKind::Difat);
+        mark(&self.ssat_sectors, SectorKind::MiniFat);
+        mark(&self.dir_sat, SectorKind::Directory);
+        let cutoff = self.minimum_standard_stream_size;
+        let entries = self.entries.as_deref().unwrap_or_default();
+        for entry in entries {
+            match entry.entry_type {
+                EntryType::RootStorage => mark(&entry.sec_id_chain, SectorKind::MiniStream),
+                EntryType::UserStream if entry.size >= cutoff => {
+                    mark(&entry.sec_id_chain, SectorKind::Stream(entry.id))
+                }
+                _ => {}
+            }
+        }
+
+        let mut kinds = Vec::with_capacity(sectors + 1);
+        kinds.push(SectorKind::Header);
+        kinds.extend(map.into_iter().enumerate().map(|(sector_id, kind)| {
+            kind.unwrap_or(match self.sat.get(sector_id) {
+                Some(&constants::SECID_FREE_SECTOR) => SectorKind::Free,
+                _ => SectorKind::Unreachable,
+            })
+        }));
+        kinds
+    }
+
+    /// Returns the raw content of the sector `sector_id`, whatever it holds.
+    ///
+    /// Together with `sector_map`, this reads the free and unreachable
+    /// sectors, which may hold remnants of deleted content.
+    pub fn read_raw_sector(&self, sector_id: u32) -> Result<Vec<u8>, Error> {
+        Ok(self.read_sector(sector_id)?.to_vec())
+    }
+
+    /// Returns the slack of the stream `entry`: the bytes following its end
+    /// in its last sector, or in its last mini sector for a small stream.
+    ///
+    /// The slack of the root entry is the slack of the mini stream.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let parser = ole::Reader::from_path("assets/sample.doc").unwrap();
+    /// let entry = parser.entry_by_path("1Table").unwrap();
+    /// let slack = parser.stream_slack(entry).unwrap();
+    /// assert_eq!(slack.len(), 512 - 6706 % 512);
+    /// ```
+    pub fn stream_slack(&self, entry: &Entry) -> Result<Vec<u8>, Error> {
+        let mini = match entry.entry_type {
+            EntryType::RootStorage => false,
+            EntryType::UserStream => entry.size < self.minimum_standard_stream_size,
+            _ => return Err(Error::NotAStream(entry.name.clone())),
+        };
+        let sector_size = if mini {
+            self.short_sec_size
+        } else {
+            self.sec_size
+        };
+        let used = (entry.size % sector_size as u64) as usize;
+        if used == 0 {
+            return Ok(Vec::new());
+        }
+        // The sector holding the end of the stream.
+        let Some(&last) = usize::try_from(entry.size / sector_size as u64)
+            .ok()
+            .and_then(|index| entry.sec_id_chain.get(index))
+        else {
+            return Ok(Vec::new());
+        };
+        let offset = if mini {
+            self.mini_sector_offset(last)?
+        } else {
+            self.checked_sector_offset(last)?
+        };
+        Ok(self
+            .source
+            .block(offset + used, sector_size - used)?
+            .to_vec())
+    }
+
+    /// Returns the raw content of the unused directory entries, with their
+    /// id. Names and sizes of deleted streams often survive in them.
+    pub fn unused_entry_slots(&self) -> Result<Vec<(u32, Vec<u8>)>, Error> {
+        self.iterate()
+            .filter(|entry| entry.entry_type == EntryType::Empty)
+            .map(|entry| {
+                let offset = self.entry_offset(entry.id)?;
+                let raw = self.source.block(offset, constants::DIRECTORY_ENTRY_SIZE)?;
+                Ok((entry.id, raw.to_vec()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SectorKind;
+    use crate::{sample::Sample, Reader};
+
+    /// Returns a file holding the stream `Big`, of 10 sectors, and the small
+    /// stream `Small`.
+    fn sample() -> Sample {
+        Sample::new(&[("Big", &[1u8; 5000]), ("Small", &[2u8; 100])])
+    }
+
+    #[test]
+    fn map() {
+        let sample = sample();
+        let (buf, big, chain) = (&sample.buf, sample.offset("Big"), sample.chain("Big"));
+        let big_id = sample.id("Big");
+        let ole = Reader::new(&buf[..]).unwrap();
+        let map = ole.sector_map();
+        assert_eq!(map.len(), buf.len() / 512);
+        assert_eq!(map[0], SectorKind::Header);
+        let count = |kind| map.iter().filter(|&&k| k == kind).count();
+        assert_eq!(count(SectorKind::Fat), 1);
+        assert_eq!(count(SectorKind::Directory), 1);
+        assert_eq!(count(SectorKind::MiniFat), 1);
+        assert_eq!(count(SectorKind::MiniStream), 1);
+        assert_eq!(count(SectorKind::Stream(big_id)), 10);
+        for &sector_id in chain {
+            assert_eq!(map[sector_id as usize + 1], SectorKind::Stream(big_id));
+        }
+        drop(ole);
+
+        // `Big` starts at its second sector, its first one is unreachable,
+        // then freed.
+        let mut bad = buf.clone();
+        bad[big + 116..big + 120].copy_from_slice(&chain[1].to_le_bytes());
+        bad[big + 120..big + 124].copy_from_slice(&(9 * 512u32).to_le_bytes());
+        let map = Reader::new(&bad[..]).unwrap().sector_map();
+        assert_eq!(map[chain[0] as usize + 1], SectorKind::Unreachable);
+        let fat_entry = 512 + chain[0] as usize * 4;
+        bad[fat_entry..fat_entry + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        let map = Reader::new(&bad[..]).unwrap().sector_map();
+        assert_eq!(map[chain[0] as usize + 1], SectorKind::Free);
+        let ole = Reader::new(&bad[..]).unwrap();
+        assert_eq!(ole.read_raw_sector(chain[0]).unwrap(), [1u8; 512]);
+    }
+
+    #[test]
+    fn slack() {
+        let sample = sample();
+        let (mut buf, chain) = (sample.buf.clone(), sample.chain("Big"));
+        // 392 bytes of `Big` are in its last sector.
+        let last = (chain[9] as usize + 1) * 512;
+        buf[last + 392..last + 512].fill(0xAA);
+        let ole = Reader::new(&buf[..]).unwrap();
+        let big = ole.entry_by_path("Big").unwrap();
+        assert_eq!(ole.stream_slack(big).unwrap(), [0xAA; 120]);
+
+        // 36 bytes of `Small` are in its second mini sector.
+        let small = ole.entry_by_path("Small").unwrap();
+        assert_eq!(ole.stream_slack(small).unwrap(), [0u8; 28]);
+
+        let root = ole.iterate().next().unwrap();
+        assert_eq!(ole.stream_slack(root).unwrap().len(), 512 - 128);
+
+        // The directory sector holds the root entry, the two streams, and an
+        // unused entry.
+        let slots = ole.unused_entry_slots().unwrap();
+        assert_eq!(slots.len(), 1);
+        assert_eq!(slots[0].1.len(), 128);
+    }
+}
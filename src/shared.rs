@@ -0,0 +1,312 @@
+//             DO WHAT THE FUCK YOU WANT TO PUBLIC LICENSE
+//                    Version 2, December 2004
+//
+// Copyright (C) 2018 Thomas Bailleux <thomas@bailleux.me>
+//
+// Everyone is permitted to copy and distribute verbatim or modified
+// copies of this license document, and changing it is allowed as long
+// as the name is changed.
+//
+//            DO WHAT THE FUCK YOU WANT TO PUBLIC LICENSE
+//   TERMS AND CONDITIONS FOR COPYING, DISTRIBUTION AND MODIFICATION
+//
+//  0. You just DO WHAT THE FUCK YOU WANT TO.
+//
+// Author: zadig <thomas chr(0x40) bailleux.me>
+
+use std::{
+    io::{self, Read, Seek},
+    ops::Deref,
+    sync::Arc,
+};
+
+use super::{
+    entry::{Entry, EntryType, StreamLayout},
+    error::Error,
+    ole::Reader,
+    options::ReaderOptions,
+    source::Source,
+};
+
+/// A `Reader` which can be shared between threads, and whose streams are
+/// opened as owned `Stream`s.
+///
+/// Cloning a `SharedReader` is cheap: the clones share the same `Reader`.
+/// The methods of `Reader` are available through `Deref`.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Read;
+/// let reader = ole::Reader::from_path("assets/sample.doc")
+///     .unwrap()
+///     .into_shared()
+///     .unwrap();
+///
+/// let mut stream = reader.open_stream("WordDocument").unwrap();
+/// let len = stream.len();
+/// let worker = std::thread::spawn(move || {
+///     let mut buf = Vec::new();
+///     stream.read_to_end(&mut buf).unwrap();
+///     buf.len() as u64
+/// });
+/// assert_eq!(worker.join().unwrap(), len);
+/// ```
+#[derive(Clone)]
+pub struct SharedReader(Arc<SendReader>);
+
+/// A `Reader` whose source can be sent to, and shared with, other threads.
+struct SendReader(Reader<'static>);
+
+// SAFETY: the source is the only part of a `Reader` which may be neither
+// `Send` nor `Sync`, when it reads a seekable reader which isn't `Send`
+// behind a `RefCell`. A `SendReader` is only built by `into_shared`, from a
+// `Reader` whose source `is_send`.
+unsafe impl Send for SendReader {}
+unsafe impl Sync for SendReader {}
+
+/// An owned stream of a `SharedReader`, which can be moved to another
+/// thread.
+///
+/// The stream keeps the file open as long as it is alive, even when every
+/// `SharedReader` is dropped.
+pub struct Stream {
+    /// The reader the stream is read from.
+    reader: Arc<SendReader>,
+
+    /// Where the bytes of the stream are in the file.
+    layout: StreamLayout,
+
+    /// Position of the next byte to read.
+    position: u64,
+}
+
+impl Reader<'static> {
+    /// Turns the `Reader` into a `SharedReader`, which is `Send` and `Sync`
+    /// and opens its streams as owned `Stream`s.
+    ///
+    /// A `Reader` built by `Reader::from_seekable`, whose reader may not be
+    /// `Send`, can't be shared: this returns `Error::NotShareable`. Use
+    /// `SharedReader::from_seekable` instead.
+    pub fn into_shared(self) -> Result<SharedReader, Error> {
+        if !self.source.is_send() {
+            return Err(Error::NotShareable);
+        }
+        Ok(SharedReader(Arc::new(SendReader(self))))
+    }
+}
+
+impl SharedReader {
+    /// Constructs a new `SharedReader` which reads `seekable` on demand,
+    /// like `Reader::from_seekable`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let file = std::fs::File::open("assets/sample.ppt").unwrap();
+    /// let reader = ole::SharedReader::from_seekable(file).unwrap();
+    /// ```
+    pub fn from_seekable<T>(seekable: T) -> Result<SharedReader, Error>
+    where
+        T: Read + Seek + Send + 'static,
+    {
+        SharedReader::from_seekable_with_options(seekable, 0, ReaderOptions::default())
+    }
+
+    /// Constructs a new `SharedReader` which reads `seekable` on demand,
+    /// keeps the last `cache_sectors` sectors read in memory, and enforces
+    /// the limits of `options`, like `Reader::from_seekable_with_options`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ole::{ReaderOptions, SharedReader};
+    /// let file = std::fs::File::open("assets/sample.ppt").unwrap();
+    /// let options = ReaderOptions::default().max_file_size(1 << 30);
+    /// let reader = SharedReader::from_seekable_with_options(file, 64, options).unwrap();
+    /// ```
+    pub fn from_seekable_with_options<T>(
+        seekable: T,
+        cache_sectors: usize,
+        options: ReaderOptions,
+    ) -> Result<SharedReader, Error>
+    where
+        T: Read + Seek + Send + 'static,
+    {
+        let source = Source::send_seekable(seekable, cache_sectors)?;
+        Reader::from_source(source, options)?.into_shared()
+    }
+
+    /// Returns the stream at `path`.
+    ///
+    /// Unlike `Reader::open_stream`, the stream doesn't borrow the reader.
+    pub fn open_stream(&self, path: &str) -> Result<Stream, Error> {
+        let entry = self.entry_by_path(path)?;
+        if entry._type() != EntryType::UserStream {
+            return Err(Error::NotAStream(path.to_string()));
+        }
+        self.get_entry_stream(entry)
+    }
+
+    /// Returns the stream for the entry.
+    pub fn get_entry_stream(&self, entry: &Entry) -> Result<Stream, Error> {
+        Ok(Stream {
+            reader: Arc::clone(&self.0),
+            layout: self.stream_layout(entry)?,
+            position: 0,
+        })
+    }
+}
+
+impl Deref for SharedReader {
+    type Target = Reader<'static>;
+
+    fn deref(&self) -> &Reader<'static> {
+        &self.0 .0
+    }
+}
+
+impl Stream {
+    /// Returns the length of the stream.
+    pub fn len(&self) -> u64 {
+        self.layout.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layout.len() == 0
+    }
+
+    /// Returns the real length of all chunks
+    pub fn real_len(&self) -> u64 {
+        self.layout.real_len()
+    }
+
+    /// Reads the bytes of the stream starting at `offset` into `buf`, and
+    /// returns how many bytes were read. The position of the stream isn't
+    /// changed.
+    pub fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<usize, io::Error> {
+        self.layout.read_at(&self.reader.0.source, offset, buf)
+    }
+}
+
+impl io::Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+        let read = self.read_at(self.position, buf)?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl io::Seek for Stream {
+    /// Moves the position of the stream. Seeking beyond the end of the
+    /// stream is allowed, reads then return no byte.
+    fn seek(&mut self, pos: io::SeekFrom) -> Result<u64, io::Error> {
+        self.position = self.layout.seek(self.position, pos)?;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SharedReader, Stream};
+    use crate::{EntryType, Error, Reader, ReaderOptions};
+    use std::{
+        io::{Read, Seek, SeekFrom},
+        thread,
+    };
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn send_sync() {
+        assert_send_sync::<SharedReader>();
+        assert_send_sync::<Stream>();
+    }
+
+    #[test]
+    fn threads() {
+        for seekable in [false, true] {
+            let file = std::fs::File::open("assets/sample.doc").unwrap();
+            let shared = if seekable {
+                SharedReader::from_seekable_with_options(file, 16, ReaderOptions::default())
+                    .unwrap()
+            } else {
+                Reader::new(file).unwrap().into_shared().unwrap()
+            };
+            let expected: Vec<(u32, Vec<u8>)> = shared
+                .iterate()
+                .filter(|entry| entry._type() == EntryType::UserStream && !entry.is_empty())
+                .map(|entry| {
+                    let mut buf = Vec::new();
+                    let mut slice = shared.get_entry_slice(entry).unwrap();
+                    slice.read_to_end(&mut buf).unwrap();
+                    (entry.id(), buf)
+                })
+                .collect();
+            assert!(!expected.is_empty());
+
+            let workers: Vec<_> = expected
+                .iter()
+                .map(|&(id, _)| {
+                    let entry = shared.iterate().find(|entry| entry.id() == id).unwrap();
+                    let mut stream = shared.get_entry_stream(entry).unwrap();
+                    thread::spawn(move || {
+                        let mut buf = Vec::new();
+                        stream.read_to_end(&mut buf).unwrap();
+                        buf
+                    })
+                })
+                .collect();
+            drop(shared);
+            for ((_, content), worker) in expected.iter().zip(workers) {
+                assert_eq!(&worker.join().unwrap(), content);
+            }
+        }
+    }
+
+    #[test]
+    fn into_shared() {
+        // The in-memory reader doesn't borrow `data`.
+        let data = std::fs::read("assets/sample.doc").unwrap();
+        let shared = Reader::new(&data[..]).unwrap().into_shared().unwrap();
+        drop(data);
+        assert!(shared.open_stream("WordDocument").is_ok());
+
+        let file = std::fs::File::open("assets/sample.doc").unwrap();
+        assert!(matches!(
+            Reader::from_seekable(file).unwrap().into_shared(),
+            Err(Error::NotShareable)
+        ));
+    }
+
+    #[test]
+    fn seek_and_read_at() {
+        let shared = Reader::from_path("assets/sample.doc")
+            .unwrap()
+            .into_shared()
+            .unwrap();
+        let mut expected = Vec::new();
+        shared
+            .open_stream("WordDocument")
+            .unwrap()
+            .read_to_end(&mut expected)
+            .unwrap();
+
+        let mut stream = shared.open_stream("WordDocument").unwrap();
+        assert_eq!(stream.len(), expected.len() as u64);
+        let mut buf = [0u8; 100];
+        assert_eq!(stream.read_at(5000, &mut buf).unwrap(), 100);
+        assert_eq!(buf[..], expected[5000..5100]);
+        assert_eq!(stream.seek(SeekFrom::End(-10)).unwrap(), stream.len() - 10);
+        assert_eq!(stream.read(&mut buf).unwrap(), 10);
+        assert_eq!(buf[..10], expected[expected.len() - 10..]);
+        assert!(stream
+            .seek(SeekFrom::Current(-(stream.len() as i64) - 1))
+            .is_err());
+
+        assert!(matches!(
+            shared.open_stream("Missing"),
+            Err(Error::EntryNotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,258 @@
+//             DO WHAT THE FUCK YOU WANT TO PUBLIC LICENSE
+//                    Version 2, December 2004
+//
+// Copyright (C) 2018 Thomas Bailleux <thomas@bailleux.me>
+//
+// Everyone is permitted to copy and distribute verbatim or modified
+// copies of this license document, and changing it is allowed as long
+// as the name is changed.
+//
+//            DO WHAT THE FUCK YOU WANT TO PUBLIC LICENSE
+//   TERMS AND CONDITIONS FOR COPYING, DISTRIBUTION AND MODIFICATION
+//
+//  0. You just DO WHAT THE FUCK YOU WANT TO.
+//
+// Author: zadig <thomas chr(0x40) bailleux.me>
+
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    io::{Read, Seek, SeekFrom},
+    sync::{Mutex, MutexGuard, PoisonError},
+    vec::Vec,
+};
+
+use super::error::Error;
+
+/// Something we can read blocks of bytes from.
+///
+/// `EntrySlice` only knows about this trait, so it doesn't depend on the
+/// lifetime of the underlying reader.
+pub(crate) trait BlockSource {
+    /// Returns the `len` bytes starting at `offset`.
+    fn block(&self, offset: usize, len: usize) -> Result<Cow<'_, [u8]>, Error>;
+
+    /// Fills `buf` with the bytes starting at `offset`, which are in blocks
+    /// of `block_size` bytes, aligned on `block_size`. Sources keeping a
+    /// cache read whole blocks, the others read `buf` at once.
+    fn read_exact_at(&self, offset: usize, buf: &mut [u8], block_size: usize) -> Result<(), Error>;
+
+    /// Returns whether `block` borrows from the file instead of copying it,
+    /// so that large blocks cost nothing.
+    fn is_borrowed(&self) -> bool;
+}
+
+pub(crate) trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek> ReadSeek for T {}
+
+/// Where the bytes of the OLE file come from.
+pub(crate) enum Source<'ole> {
+    /// The whole file, loaded in memory.
+    Memory(Vec<u8>),
+
+    /// A seekable reader, read on demand.
+    Seekable(RefCell<Seekable<dyn ReadSeek + 'ole>>),
+
+    /// A seekable reader which is `Send`, read on demand. It is behind a
+    /// `Mutex` so that a `SharedReader` can be shared between threads.
+    SendSeekable(Mutex<Seekable<dyn ReadSeek + Send + 'ole>>),
+
+    /// A memory-mapped file.
+    #[cfg(feature = "mmap")]
+    Mmap(memmap2::Mmap),
+}
+
+/// A seekable reader, with an optional cache of the last blocks read.
+pub(crate) struct Seekable<R: ?Sized> {
+    /// The reader.
+    inner: Box<R>,
+
+    /// Size of the file.
+    len: usize,
+
+    /// Cached blocks, by offset.
+    cache: HashMap<usize, Vec<u8>>,
+
+    /// Offsets of the cached blocks, from the oldest to the newest.
+    cache_order: VecDeque<usize>,
+
+    /// Maximum number of cached blocks.
+    cache_capacity: usize,
+}
+
+impl<'ole> Source<'ole> {
+    /// Constructs a `Source` reading `inner` on demand, and keeping up to
+    /// `cache_capacity` blocks in memory.
+    pub(crate) fn seekable<T>(inner: T, cache_capacity: usize) -> Result<Source<'ole>, Error>
+    where
+        T: Read + Seek + 'ole,
+    {
+        let seekable = Seekable::new(Box::new(inner) as Box<dyn ReadSeek + 'ole>, cache_capacity)?;
+        Ok(Source::Seekable(RefCell::new(seekable)))
+    }
+
+    /// Same as `seekable`, for a reader which can be sent to another
+    /// thread.
+    pub(crate) fn send_seekable<T>(inner: T, cache_capacity: usize) -> Result<Source<'ole>, Error>
+    where
+        T: Read + Seek + Send + 'ole,
+    {
+        let seekable = Seekable::new(
+            Box::new(inner) as Box<dyn ReadSeek + Send + 'ole>,
+            cache_capacity,
+        )?;
+        Ok(Source::SendSeekable(Mutex::new(seekable)))
+    }
+
+    /// Returns the size of the file.
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            Source::Memory(body) => body.len(),
+            Source::Seekable(seekable) => seekable.borrow().len,
+            Source::SendSeekable(seekable) => lock(seekable).len,
+            #[cfg(feature = "mmap")]
+            Source::Mmap(mmap) => mmap.len(),
+        }
+    }
+
+    /// Returns whether the source can be sent to, and shared with, other
+    /// threads.
+    pub(crate) fn is_send(&self) -> bool {
+        !matches!(self, Source::Seekable(_))
+    }
+
+    /// Returns the whole file.
+    pub(crate) fn into_vec(self) -> Result<Vec<u8>, Error> {
+        match self {
+            Source::Memory(body) => Ok(body),
+            Source::Seekable(seekable) => seekable.into_inner().into_vec(),
+            Source::SendSeekable(seekable) => seekable
+                .into_inner()
+                .unwrap_or_else(PoisonError::into_inner)
+                .into_vec(),
+            #[cfg(feature = "mmap")]
+            Source::Mmap(mmap) => Ok(mmap.to_vec()),
+        }
+    }
+}
+
+impl<'ole> BlockSource for Source<'ole> {
+    fn block(&self, offset: usize, len: usize) -> Result<Cow<'_, [u8]>, Error> {
+        match self {
+            Source::Memory(body) => offset
+                .checked_add(len)
+                .and_then(|end| body.get(offset..end))
+                .map(Cow::Borrowed)
+                .ok_or(Error::UnexpectedEof { offset, len }),
+            Source::Seekable(seekable) => seekable.borrow_mut().block(offset, len).map(Cow::Owned),
+            Source::SendSeekable(seekable) => lock(seekable).block(offset, len).map(Cow::Owned),
+            #[cfg(feature = "mmap")]
+            Source::Mmap(mmap) => offset
+                .checked_add(len)
+                .and_then(|end| mmap.get(offset..end))
+                .map(Cow::Borrowed)
+                .ok_or(Error::UnexpectedEof { offset, len }),
+        }
+    }
+
+    fn read_exact_at(&self, offset: usize, buf: &mut [u8], block_size: usize) -> Result<(), Error> {
+        match self {
+            Source::Seekable(seekable) => {
+                seekable.borrow_mut().read_exact_at(offset, buf, block_size)
+            }
+            Source::SendSeekable(seekable) => lock(seekable).read_exact_at(offset, buf, block_size),
+            _ => {
+                buf.copy_from_slice(&self.block(offset, buf.len())?);
+                Ok(())
+            }
+        }
+    }
+
+    fn is_borrowed(&self) -> bool {
+        !matches!(self, Source::Seekable(_) | Source::SendSeekable(_))
+    }
+}
+
+/// Locks `seekable`. A panic while it was locked can't leave it in an
+/// inconsistent state, as every read seeks first, so poisoning is ignored.
+fn lock<'a, R: ?Sized>(seekable: &'a Mutex<Seekable<R>>) -> MutexGuard<'a, Seekable<R>> {
+    seekable.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl<R: ReadSeek + ?Sized> Seekable<R> {
+    fn new(mut inner: Box<R>, cache_capacity: usize) -> Result<Seekable<R>, Error> {
+        let len = usize::try_from(inner.seek(SeekFrom::End(0))?).map_err(|_| Error::BadFileSize)?;
+        Ok(Seekable {
+            inner,
+            len,
+            cache: HashMap::new(),
+            cache_order: VecDeque::new(),
+            cache_capacity,
+        })
+    }
+
+    fn block(&mut self, offset: usize, len: usize) -> Result<Vec<u8>, Error> {
+        if let Some(block) = self.cache.get(&offset).filter(|b| b.len() >= len) {
+            return Ok(block[..len].to_vec());
+        }
+        if offset.checked_add(len).is_none_or(|end| end > self.len) {
+            return Err(Error::UnexpectedEof { offset, len });
+        }
+
+        let mut block = vec![0u8; len];
+        self.inner.seek(SeekFrom::Start(offset as u64))?;
+        self.inner.read_exact(&mut block)?;
+        self.insert(offset, &block);
+        Ok(block)
+    }
+
+    fn read_exact_at(
+        &mut self,
+        offset: usize,
+        buf: &mut [u8],
+        block_size: usize,
+    ) -> Result<(), Error> {
+        if self.cache_capacity == 0 {
+            let len = buf.len();
+            if offset.checked_add(len).is_none_or(|end| end > self.len) {
+                return Err(Error::UnexpectedEof { offset, len });
+            }
+            self.inner.seek(SeekFrom::Start(offset as u64))?;
+            self.inner.read_exact(buf)?;
+            return Ok(());
+        }
+        let mut read = 0;
+        while read != buf.len() {
+            let position = offset + read;
+            let start = position % block_size;
+            let len = std::cmp::min(block_size - start, buf.len() - read);
+            let block = self.block(position - start, block_size)?;
+            buf[read..read + len].copy_from_slice(&block[start..start + len]);
+            read += len;
+        }
+        Ok(())
+    }
+
+    fn into_vec(mut self) -> Result<Vec<u8>, Error> {
+        let mut body = Vec::with_capacity(self.len);
+        self.inner.seek(SeekFrom::Start(0))?;
+        self.inner.read_to_end(&mut body)?;
+        Ok(body)
+    }
+
+    fn insert(&mut self, offset: usize, block: &[u8]) {
+        if self.cache_capacity == 0 {
+            return;
+        }
+        if self.cache.len() == self.cache_capacity {
+            if let Some(oldest) = self.cache_order.pop_front() {
+                self.cache.remove(&oldest);
+            }
+        }
+        if self.cache.insert(offset, block.to_vec()).is_none() {
+            self.cache_order.push_back(offset);
+        }
+    }
+}
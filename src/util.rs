@@ -14,36 +14,113 @@
 //
 // Author: zadig <thomas chr(0x40) bailleux.me>
 
-pub(crate) trait FromSlice<T> {
-    fn from_slice(buf: &[T]) -> Self;
+use super::{clsid::Clsid, constants};
+
+/// The byte order of a compound file, given by the byte order mark of its
+/// header.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ByteOrder {
+    /// Little-endian, as written by Windows.
+    #[default]
+    LittleEndian,
+
+    /// Big-endian, as written by some legacy Macintosh and Unix tools.
+    BigEndian,
+}
+
+impl ByteOrder {
+    /// Returns the byte order identified by the two bytes of `mark`, or
+    /// `None` if it isn't a byte order mark.
+    pub(crate) fn from_mark(mark: &[u8]) -> Option<ByteOrder> {
+        if mark == constants::LITTLE_ENDIAN_IDENTIFIER {
+            Some(ByteOrder::LittleEndian)
+        } else if mark == constants::BIG_ENDIAN_IDENTIFIER {
+            Some(ByteOrder::BigEndian)
+        } else {
+            None
+        }
+    }
+}
+
+pub(crate) trait FromSlice<T>: Sized {
+    /// Reads a little-endian value.
+    fn from_slice(buf: &[T]) -> Self {
+        Self::from_slice_with(buf, ByteOrder::LittleEndian)
+    }
+
+    /// Reads a value stored in `order`.
+    fn from_slice_with(buf: &[T], order: ByteOrder) -> Self;
+}
+
+/// Copies `buf`, which callers check is `N` bytes long.
+fn to_array<const N: usize>(buf: &[u8]) -> [u8; N] {
+    <[u8; N]>::try_from(buf).expect("fixed-size values are read from slices of their size")
 }
 
 impl FromSlice<u8> for usize {
-    fn from_slice(buf: &[u8]) -> Self {
-        usize::try_from(u32::from_slice(buf)).expect("Your platform usize is smaller than u32!")
+    fn from_slice_with(buf: &[u8], order: ByteOrder) -> Self {
+        // 16 bits platforms aren't supported, see `lib.rs`.
+        u32::from_slice_with(buf, order) as usize
     }
 }
 
 impl FromSlice<u8> for u32 {
-    fn from_slice(buf: &[u8]) -> Self {
-        u32::from_le_bytes(buf.try_into().expect("Incorrect length"))
+    fn from_slice_with(buf: &[u8], order: ByteOrder) -> Self {
+        let bytes = to_array(buf);
+        match order {
+            ByteOrder::LittleEndian => u32::from_le_bytes(bytes),
+            ByteOrder::BigEndian => u32::from_be_bytes(bytes),
+        }
     }
 }
 
 impl FromSlice<u8> for u16 {
-    fn from_slice(buf: &[u8]) -> Self {
-        u16::from_le_bytes(buf.try_into().expect("Incorrect length"))
+    fn from_slice_with(buf: &[u8], order: ByteOrder) -> Self {
+        let bytes = to_array(buf);
+        match order {
+            ByteOrder::LittleEndian => u16::from_le_bytes(bytes),
+            ByteOrder::BigEndian => u16::from_be_bytes(bytes),
+        }
     }
 }
 
 impl FromSlice<u8> for i32 {
-    fn from_slice(buf: &[u8]) -> Self {
-        i32::from_le_bytes(buf.try_into().expect("Incorrect length"))
+    fn from_slice_with(buf: &[u8], order: ByteOrder) -> Self {
+        let bytes = to_array(buf);
+        match order {
+            ByteOrder::LittleEndian => i32::from_le_bytes(bytes),
+            ByteOrder::BigEndian => i32::from_be_bytes(bytes),
+        }
     }
 }
 
 impl FromSlice<u8> for u64 {
-    fn from_slice(buf: &[u8]) -> Self {
-        u64::from_le_bytes(buf.try_into().expect("Incorrect length"))
+    fn from_slice_with(buf: &[u8], order: ByteOrder) -> Self {
+        let bytes = to_array(buf);
+        match order {
+            ByteOrder::LittleEndian => u64::from_le_bytes(bytes),
+            ByteOrder::BigEndian => u64::from_be_bytes(bytes),
+        }
+    }
+}
+
+/// A CLSID keeps the binary form of little-endian files: the first three
+/// fields of a big-endian one are swapped.
+impl FromSlice<u8> for Clsid {
+    fn from_slice_with(buf: &[u8], order: ByteOrder) -> Self {
+        let mut bytes: [u8; 16] = to_array(buf);
+        if order == ByteOrder::BigEndian {
+            bytes[0..4].reverse();
+            bytes[4..6].reverse();
+            bytes[6..8].reverse();
+        }
+        Clsid::from_bytes(bytes)
     }
 }
+
+/// Reads a FILETIME, which is made of a low and a high 32 bits words.
+pub(crate) fn filetime_from_slice(buf: &[u8], order: ByteOrder) -> u64 {
+    let low = u64::from(u32::from_slice_with(&buf[0..4], order));
+    let high = u64::from(u32::from_slice_with(&buf[4..8], order));
+    high << 32 | low
+}
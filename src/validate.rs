@@ -0,0 +1,557 @@
+//             DO WHAT THE FUCK YOU WANT TO PUBLIC LICENSE
+//                    Version 2, December 2004
+//
+// Copyright (C) 2018 Thomas Bailleux <thomas@bailleux.me>
+//
+// Everyone is permitted to copy and distribute verbatim or modified
+// copies of this license document, and changing it is allowed as long
+// as the name is changed.
+//
+//            DO WHAT THE FUCK YOU WANT TO PUBLIC LICENSE
+//   TERMS AND CONDITIONS FOR COPYING, DISTRIBUTION AND MODIFICATION
+//
+//  0. You just DO WHAT THE FUCK YOU WANT TO.
+//
+// Author: zadig <thomas chr(0x40) bailleux.me>
+
+use std::{cmp::Ordering, fmt};
+
+use super::{
+    constants,
+    entry::{compare_names, Entry, EntryType, NodeColour},
+    error::Error,
+    ole::Reader,
+    source::BlockSource,
+};
+
+/// A violation of the compound file format, found by `Reader::validate`.
+///
+/// Sector ids are indexes of sectors in the file, or of mini sectors in the
+/// mini stream for the `*MiniSector` variants.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Violation {
+    /// A reserved field of the header, at `offset`, isn't zero.
+    NonZeroReservedField { offset: usize },
+
+    /// The sector belongs to more than one chain.
+    SharedSector { sector_id: u32 },
+
+    /// The mini sector belongs to more than one chain.
+    SharedMiniSector { sector_id: u32 },
+
+    /// The sector is allocated in the FAT but belongs to no chain.
+    OrphanedSector { sector_id: u32 },
+
+    /// The mini sector is allocated in the mini FAT but belongs to no chain.
+    OrphanedMiniSector { sector_id: u32 },
+
+    /// The FAT entry of the sector points to `next`, which is beyond the end
+    /// of the file.
+    SectorBeyondEnd { sector_id: u32, next: u32 },
+
+    /// The mini stream, whose size is the size of the root entry, is
+    /// smaller than the `used` bytes allocated in the mini FAT.
+    MiniStreamSizeMismatch { size: u64, used: u64 },
+
+    /// The size of the entry doesn't match the length of its chain, in
+    /// bytes.
+    StreamSizeMismatch {
+        entry_id: u32,
+        size: u64,
+        chain_size: u64,
+    },
+
+    /// The high DWORD of the stream size is set in a version 3 file.
+    StreamSizeHighDword { entry_id: u32 },
+
+    /// The red-black tree of siblings is invalid at the entry.
+    InvalidColouring { entry_id: u32, reason: &'static str },
+
+    /// The entry has the same name as one of its siblings.
+    DuplicateName { entry_id: u32, name: String },
+
+    /// The entry is one of its own descendants in the tree of siblings.
+    SiblingLoop { entry_id: u32 },
+
+    /// The entry is unused, but isn't all zeros with no sibling and no
+    /// child.
+    NonZeroFreeEntry { entry_id: u32 },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Violation::NonZeroReservedField { offset } => {
+                write!(f, "Reserved header field at offset {offset:#x} is not zero")
+            }
+            Violation::SharedSector { sector_id } => {
+                write!(f, "Sector {sector_id:#x} belongs to several chains")
+            }
+            Violation::SharedMiniSector { sector_id } => {
+                write!(f, "Mini sector {sector_id:#x} belongs to several chains")
+            }
+            Violation::OrphanedSector { sector_id } => {
+                write!(f, "Sector {sector_id:#x} is allocated but unused")
+            }
+            Violation::OrphanedMiniSector { sector_id } => {
+                write!(f, "Mini sector {sector_id:#x} is allocated but unused")
+            }
+            Violation::SectorBeyondEnd { sector_id, next } => write!(
+                f,
+                "Sector {sector_id:#x} links to sector {next:#x}, beyond the end of the file"
+            ),
+            Violation::MiniStreamSizeMismatch { size, used } => write!(
+                f,
+                "Mini stream is {size} bytes long, but {used} bytes are allocated"
+            ),
+            Violation::StreamSizeMismatch {
+                entry_id,
+                size,
+                chain_size,
+            } => write!(
+                f,
+                "Entry {entry_id} is {size} bytes long, but its chain holds {chain_size} bytes"
+            ),
+            Violation::StreamSizeHighDword { entry_id } => write!(
+                f,
+                "Entry {entry_id} has the high DWORD of its size set in a version 3 file"
+            ),
+            Violation::InvalidColouring { entry_id, reason } => {
+                write!(f, "Invalid red-black tree at entry {entry_id}: {reason}")
+            }
+            Violation::DuplicateName { entry_id, name } => {
+                write!(f, "Entry {entry_id} has the same name as a sibling: {name}")
+            }
+            Violation::SiblingLoop { entry_id } => {
+                write!(
+                    f,
+                    "Entry {entry_id} is its own descendant in the tree of siblings"
+                )
+            }
+            Violation::NonZeroFreeEntry { entry_id } => {
+                write!(f, "Entry {entry_id} is unused but not zeroed")
+            }
+        }
+    }
+}
+
+impl<'ole> Reader<'ole> {
+    /// Checks the structure of the file against the compound file format
+    /// specification, and returns the violations found.
+    ///
+    /// A file is valid when the returned list is empty. Only the structure
+    /// is checked, not the content of the streams.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ole;
+    /// let parser = ole::Reader::from_path("assets/Thumbs.db").unwrap();
+    /// for violation in parser.validate().unwrap() {
+    ///     println!("{violation}");
+    /// }
+    /// ```
+    pub fn validate(&self) -> Result<Vec<Violation>, Error> {
+        let mut violations = Vec::new();
+        let entries = self.entries.as_deref().unwrap_or_default();
+        self.validate_header(&mut violations)?;
+        self.validate_sectors(entries, &mut violations);
+        self.validate_entries(entries, &mut violations)?;
+        validate_trees(entries, &mut violations);
+        Ok(violations)
+    }
+
+    fn validate_header(&self, violations: &mut Vec<Violation>) -> Result<(), Error> {
+        let header = self.read_header()?;
+        if header[34..40].iter().any(|&b| b != 0) {
+            violations.push(Violation::NonZeroReservedField { offset: 34 });
+        }
+        // The number of directory sectors isn't used in version 3.
+        if self.version_number == 3 && header[40..44].iter().any(|&b| b != 0) {
+            violations.push(Violation::NonZeroReservedField { offset: 40 });
+        }
+        // The header is padded with zeros up to the first sector.
+        if self.sec_size > constants::HEADER_SIZE {
+            let padding = self.source.block(
+                constants::HEADER_SIZE,
+                self.sec_size - constants::HEADER_SIZE,
+            )?;
+            if padding.iter().any(|&b| b != 0) {
+                violations.push(Violation::NonZeroReservedField {
+                    offset: constants::HEADER_SIZE,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_sectors(&self, entries: &[Entry], violations: &mut Vec<Violation>) {
+        let cutoff = self.minimum_standard_stream_size;
+        let file_sectors = (self.source.len() / self.sec_size).saturating_sub(1);
+
+        // Number of chains each sector belongs to.
+        let mut uses = vec![0u32; self.sat.len()];
+        let mut mini_uses = vec![0u32; self.ssat.len()];
+        let count = |uses: &mut [u32], chain: &[u32]| {
+            for &sector_id in chain {
+                if let Some(n) = uses.get_mut(sector_id as usize) {
+                    *n += 1;
+                }
+            }
+        };
+        count(&mut uses, &self.main_sat);
+        count(&mut uses, &self.ssat_sectors);
+        count(&mut uses, &self.dir_sat);
+        for entry in entries {
+            match entry.entry_type {
+                EntryType::RootStorage => count(&mut uses, &entry.sec_id_chain),
+                EntryType::UserStream if entry.size < cutoff => {
+                    count(&mut mini_uses, &entry.sec_id_chain)
+                }
+                EntryType::UserStream => count(&mut uses, &entry.sec_id_chain),
+                _ => {}
+            }
+        }
+
+        for (sector_id, (&next, &n)) in (0u32..).zip(self.sat.iter().zip(&uses)) {
+            if n > 1 {
+                violations.push(Violation::SharedSector { sector_id });
+            }
+            // DIFAT sectors aren't part of a chain.
+            if n == 0
+                && (sector_id as usize) < file_sectors
+                && next != constants::SECID_FREE_SECTOR
+                && next != constants::SECID_DIFAT_SECTOR
+            {
+                violations.push(Violation::OrphanedSector { sector_id });
+            }
+            if next <= constants::SECID_MAX && next as usize >= file_sectors {
+                violations.push(Violation::SectorBeyondEnd { sector_id, next });
+            }
+        }
+
+        for (sector_id, (&next, &n)) in (0u32..).zip(self.ssat.iter().zip(&mini_uses)) {
+            if n > 1 {
+                violations.push(Violation::SharedMiniSector { sector_id });
+            }
+            if n == 0 && next != constants::SECID_FREE_SECTOR {
+                violations.push(Violation::OrphanedMiniSector { sector_id });
+            }
+        }
+
+        // The mini stream holds every mini sector allocated in the mini FAT.
+        let size = self
+            .root_entry
+            .and_then(|id| entries.get(id as usize))
+            .map_or(0, |root| root.size);
+        let used = self
+            .ssat
+            .iter()
+            .rposition(|&next| next != constants::SECID_FREE_SECTOR)
+            .map_or(0, |last| ((last + 1) * self.short_sec_size) as u64);
+        if used > size {
+            violations.push(Violation::MiniStreamSizeMismatch { size, used });
+        }
+    }
+
+    fn validate_entries(
+        &self,
+        entries: &[Entry],
+        violations: &mut Vec<Violation>,
+    ) -> Result<(), Error> {
+        let cutoff = self.minimum_standard_stream_size;
+        let mut free = [0u8; constants::DIRECTORY_ENTRY_SIZE];
+        Entry::write_free(&mut free);
+        for entry in entries {
+            if entry.entry_type == EntryType::Empty {
+                let offset = self.entry_offset(entry.id)?;
+                if *self.source.block(offset, free.len())? != free {
+                    violations.push(Violation::NonZeroFreeEntry { entry_id: entry.id });
+                }
+                continue;
+            }
+            let sector_size = match entry.entry_type {
+                EntryType::RootStorage => self.sec_size,
+                EntryType::UserStream if entry.size < cutoff => self.short_sec_size,
+                EntryType::UserStream => self.sec_size,
+                _ => continue,
+            };
+            let chain_size = (entry.sec_id_chain.len() * sector_size) as u64;
+            if entry.size.div_ceil(sector_size as u64) != entry.sec_id_chain.len() as u64 {
+                violations.push(Violation::StreamSizeMismatch {
+                    entry_id: entry.id,
+                    size: entry.size,
+                    chain_size,
+                });
+            }
+            if self.version_number == 3 {
+                let offset = self.entry_offset(entry.id)? + 124;
+                if self.source.block(offset, 4)?.iter().any(|&b| b != 0) {
+                    violations.push(Violation::StreamSizeHighDword { entry_id: entry.id });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks the red-black trees of siblings, and that siblings have distinct
+/// names.
+fn validate_trees(entries: &[Entry], violations: &mut Vec<Violation>) {
+    let entry = |id: u32| entries.get(id as usize);
+    let is_red = |id: u32| entry(id).is_some_and(|e| e.color == NodeColour::Red);
+
+    // Number of black nodes from each entry down to its leaves, computed
+    // children first.
+    let mut black_height = vec![None; entries.len()];
+    // Entries whose children were pushed. An entry entered again before its
+    // height is known is one of its own descendants.
+    let mut entered = vec![false; entries.len()];
+    for storage in entries.iter().filter(|e| {
+        e.entry_type == EntryType::RootStorage || e.entry_type == EntryType::UserStorage
+    }) {
+        if is_red(storage.root_node) {
+            violations.push(Violation::InvalidColouring {
+                entry_id: storage.root_node,
+                reason: "the root of the tree is red",
+            });
+        }
+
+        // (id, whether its children were visited)
+        let mut stack = vec![(storage.root_node, false)];
+        while let Some((id, visited)) = stack.pop() {
+            let Some(node) = entry(id) else {
+                continue;
+            };
+            let (left, right) = (node.left_child_node, node.right_child_node);
+            if !visited {
+                if entered[id as usize] {
+                    if black_height[id as usize].is_none() {
+                        violations.push(Violation::SiblingLoop { entry_id: id });
+                    }
+                } else {
+                    entered[id as usize] = true;
+                    stack.push((id, true));
+                    stack.push((left, false));
+                    stack.push((right, false));
+                }
+                continue;
+            }
+            let height = |id: u32| entry(id).map_or(Some(0), |_| black_height[id as usize]);
+            let (left_height, right_height) = (height(left), height(right));
+            if left_height != right_height {
+                violations.push(Violation::InvalidColouring {
+                    entry_id: id,
+                    reason: "the subtrees have different black heights",
+                });
+            }
+            let red = node.color == NodeColour::Red;
+            if red && (is_red(left) || is_red(right)) {
+                violations.push(Violation::InvalidColouring {
+                    entry_id: id,
+                    reason: "a red entry has a red child",
+                });
+            }
+            let height = left_height.max(right_height).unwrap_or(0) + u32::from(!red);
+            black_height[id as usize] = Some(height);
+        }
+
+        let mut children: Vec<&Entry> = storage
+            .children_nodes
+            .iter()
+            .filter_map(|&id| entry(id))
+            .collect();
+        children.sort_by(|a, b| compare_names(&a.name, &b.name).then(a.id.cmp(&b.id)));
+        for pair in children.windows(2) {
+            if compare_names(&pair[0].name, &pair[1].name) == Ordering::Equal {
+                violations.push(Violation::DuplicateName {
+                    entry_id: pair[1].id,
+                    name: pair[1].name.clone(),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Violation;
+    use crate::{sample::Sample, Error, Reader, ReaderOptions, Version, Writer};
+
+    /// Returns a file holding the streams `A` and `B`, of 10 sectors each,
+    /// and the small stream `s`.
+    fn sample() -> Sample {
+        Sample::new(&[("A", &[1u8; 5000]), ("B", &[2u8; 5000]), ("s", &[3u8; 100])])
+    }
+
+    /// Validates `buf`, read in lenient mode so that damaged files can be
+    /// validated.
+    fn violations(buf: &[u8]) -> Vec<Violation> {
+        let options = ReaderOptions::default().lenient(true);
+        Reader::with_options(buf, options)
+            .unwrap()
+            .validate()
+            .unwrap()
+    }
+
+    /// Offset of the FAT entry of `sector_id`, the FAT being in sector 0.
+    fn fat_entry(sector_id: u32) -> usize {
+        512 + sector_id as usize * 4
+    }
+
+    #[test]
+    fn valid() {
+        for version in [Version::V3, Version::V4] {
+            let mut writer = Writer::new(version);
+            for i in 0..20 {
+                writer.create_storage(&format!("{i}")).unwrap();
+                writer
+                    .create_stream(&format!("{i}/big"), &[1u8; 5000])
+                    .unwrap();
+                writer
+                    .create_stream(&format!("{i}/small"), &[2u8; 100])
+                    .unwrap();
+            }
+            let mut buf = Vec::new();
+            writer.write_to(&mut buf).unwrap();
+            assert_eq!(violations(&buf), []);
+        }
+        assert_eq!(violations(&sample().buf), []);
+    }
+
+    #[test]
+    fn header() {
+        let mut buf = sample().buf;
+        buf[34] = 1;
+        buf[40] = 1;
+        assert_eq!(
+            violations(&buf),
+            [
+                Violation::NonZeroReservedField { offset: 34 },
+                Violation::NonZeroReservedField { offset: 40 }
+            ]
+        );
+    }
+
+    #[test]
+    fn sectors() {
+        let sample = sample();
+        let (buf, a, a_id) = (&sample.buf, sample.offset("A"), sample.id("A"));
+        let chain = sample.chain("A");
+        let (first, last) = (chain[0], chain[9]);
+
+        // `A` goes on with the sectors of `B`.
+        let mut bad = buf.clone();
+        bad[fat_entry(last)..fat_entry(last) + 4].copy_from_slice(&(last + 1).to_le_bytes());
+        let found = violations(&bad);
+        assert!(found.contains(&Violation::SharedSector {
+            sector_id: last + 1
+        }));
+        assert!(found.contains(&Violation::StreamSizeMismatch {
+            entry_id: a_id,
+            size: 5000,
+            chain_size: 20 * 512
+        }));
+
+        let mut bad = buf.clone();
+        bad[fat_entry(last)..fat_entry(last) + 4].copy_from_slice(&100u32.to_le_bytes());
+        let found = violations(&bad);
+        assert!(found.contains(&Violation::SectorBeyondEnd {
+            sector_id: last,
+            next: 100
+        }));
+
+        // `A` starts at its second sector.
+        let mut bad = buf.clone();
+        bad[a + 116..a + 120].copy_from_slice(&(first + 1).to_le_bytes());
+        assert!(matches!(
+            Reader::new(&bad[..]),
+            Err(Error::InvalidEntry { offset, .. }) if offset == a + 120
+        ));
+        assert_eq!(
+            violations(&bad),
+            [
+                Violation::OrphanedSector { sector_id: first },
+                Violation::StreamSizeMismatch {
+                    entry_id: a_id,
+                    size: 5000,
+                    chain_size: 9 * 512
+                }
+            ]
+        );
+
+        // The root entry is also the mini stream.
+        let mut bad = buf.clone();
+        bad[512 * 2 + 120..512 * 2 + 124].copy_from_slice(&64u32.to_le_bytes());
+        let found = violations(&bad);
+        assert!(found.contains(&Violation::MiniStreamSizeMismatch {
+            size: 64,
+            used: 128
+        }));
+
+        let mut bad = buf.clone();
+        bad[a + 124] = 1;
+        assert_eq!(
+            violations(&bad),
+            [Violation::StreamSizeHighDword { entry_id: a_id }]
+        );
+    }
+
+    #[test]
+    fn entries() {
+        let sample = Sample::new(&[("s", &[3u8; 100]), ("e", &[])]);
+        let mut buf = sample.buf.clone();
+        let e = sample.offset("e");
+        // The entry after `e` is unused.
+        let free = e + 128;
+        assert_eq!(violations(&buf), []);
+
+        let mut bad = buf.clone();
+        bad[free + 67] = 1;
+        assert_eq!(
+            violations(&bad),
+            [Violation::NonZeroFreeEntry {
+                entry_id: sample.id("e") + 1
+            }]
+        );
+
+        // An empty stream may start in sector 0, which belongs to `s`.
+        buf[e + 116..e + 120].copy_from_slice(&0u32.to_le_bytes());
+        assert_eq!(violations(&buf), []);
+    }
+
+    #[test]
+    fn trees() {
+        let sample = sample();
+        let (buf, a, b) = (&sample.buf, sample.offset("A"), sample.offset("B"));
+
+        // Two of the three siblings are red.
+        let mut bad = buf.clone();
+        bad[a + 67] = 0;
+        bad[b + 67] = 0;
+        let found = violations(&bad);
+        assert!(!found.is_empty());
+        assert!(found
+            .iter()
+            .all(|v| matches!(v, Violation::InvalidColouring { .. })));
+
+        // `A` is its own left sibling.
+        let a_id = sample.id("A");
+        let mut bad = buf.clone();
+        bad[a + 68..a + 72].copy_from_slice(&a_id.to_le_bytes());
+        assert!(violations(&bad).contains(&Violation::SiblingLoop { entry_id: a_id }));
+
+        let mut bad = buf.clone();
+        bad[b..b + 2].copy_from_slice(&u16::from(b'a').to_le_bytes());
+        let ole = Reader::new(&bad[..]).unwrap();
+        let b_id = ole.iterate().find(|e| e.name() == "a").unwrap().id();
+        assert_eq!(
+            ole.validate().unwrap(),
+            [Violation::DuplicateName {
+                entry_id: b_id,
+                name: "a".to_string()
+            }]
+        );
+    }
+}
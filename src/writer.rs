@@ -0,0 +1,572 @@
+//             DO WHAT THE FUCK YOU WANT TO PUBLIC LICENSE
+//                    Version 2, December 2004
+//
+// Copyright (C) 2018 Thomas Bailleux <thomas@bailleux.me>
+//
+// Everyone is permitted to copy and distribute verbatim or modified
+// copies of this license document, and changing it is allowed as long
+// as the name is changed.
+//
+//            DO WHAT THE FUCK YOU WANT TO PUBLIC LICENSE
+//   TERMS AND CONDITIONS FOR COPYING, DISTRIBUTION AND MODIFICATION
+//
+//  0. You just DO WHAT THE FUCK YOU WANT TO.
+//
+// Author: zadig <thomas chr(0x40) bailleux.me>
+
+use std::{cmp::Ordering, io::Write, vec::Vec};
+
+use super::{
+    clsid::Clsid,
+    constants,
+    entry::{build_sibling_tree, check_name, compare_names, Entry, EntryType},
+    error::Error,
+};
+
+/// Version of the compound file format.
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum Version {
+    /// Version 3, with 512 bytes sectors.
+    V3,
+
+    /// Version 4, with 4096 bytes sectors.
+    V4,
+}
+
+impl Version {
+    /// Returns the major version number written in the header.
+    pub fn major(self) -> u16 {
+        match self {
+            Version::V3 => 3,
+            Version::V4 => 4,
+        }
+    }
+
+    /// Returns the sector shift, i.e. `log2` of the sector size.
+    pub fn sector_shift(self) -> u16 {
+        match self {
+            Version::V3 => 9,
+            Version::V4 => 12,
+        }
+    }
+
+    /// Returns the size of one sector.
+    pub fn sector_size(self) -> usize {
+        1 << self.sector_shift()
+    }
+}
+
+/// A storage or a stream waiting to be written.
+struct Node {
+    name: String,
+    entry_type: EntryType,
+    identifier: [u8; 16],
+    flags: u32,
+    creation_time: u64,
+    last_modification_time: u64,
+    data: Vec<u8>,
+    children: Vec<usize>,
+}
+
+impl Node {
+    fn new(name: &str, entry_type: EntryType) -> Node {
+        Node {
+            name: name.to_string(),
+            entry_type,
+            identifier: [0u8; 16],
+            flags: 0,
+            creation_time: 0,
+            last_modification_time: 0,
+            data: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    fn is_storage(&self) -> bool {
+        self.entry_type == EntryType::RootStorage || self.entry_type == EntryType::UserStorage
+    }
+}
+
+/// An OLE file writer.
+///
+/// Storages and streams are described by their path, using `/` as
+/// separator, relative to the root storage. The whole file is laid out
+/// when it is written.
+///
+/// # Basic Example
+///
+/// ```
+/// use ole::{Reader, Version, Writer};
+///
+/// let mut writer = Writer::new(Version::V3);
+/// writer.create_storage("Macros").unwrap();
+/// writer.create_stream("Macros/dir", b"Attribute VB_Name").unwrap();
+/// writer.create_stream("WordDocument", &[0u8; 8192]).unwrap();
+///
+/// let mut buf = Vec::new();
+/// writer.write_to(&mut buf).unwrap();
+///
+/// let reader = Reader::new(&buf[..]).unwrap();
+/// assert_eq!(reader.iterate().filter(|e| e.name() == "dir").count(), 1);
+/// ```
+pub struct Writer {
+    /// Version of the file to produce.
+    version: Version,
+
+    /// Storages and streams. The root storage is the first one.
+    nodes: Vec<Node>,
+}
+
+impl Writer {
+    /// Constructs a new `Writer`, with an empty root storage.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ole::{Version, Writer};
+    /// let writer = Writer::new(Version::V4);
+    /// ```
+    pub fn new(version: Version) -> Writer {
+        Writer {
+            version,
+            nodes: vec![Node::new("Root Entry", EntryType::RootStorage)],
+        }
+    }
+
+    /// Returns the version of the file to produce.
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// Creates an empty storage. Its parent storage must already exist.
+    pub fn create_storage(&mut self, path: &str) -> Result<(), Error> {
+        self.create(path, Node::new("", EntryType::UserStorage))
+    }
+
+    /// Creates a stream holding `data`. Its parent storage must already
+    /// exist.
+    pub fn create_stream(&mut self, path: &str, data: &[u8]) -> Result<(), Error> {
+        let mut node = Node::new("", EntryType::UserStream);
+        node.data = data.to_vec();
+        self.create(path, node)
+    }
+
+    /// Sets the CLSID of a storage.
+    pub fn set_clsid(&mut self, path: &str, clsid: Clsid) -> Result<(), Error> {
+        self.find_mut(path)?.identifier = *clsid.as_bytes();
+        Ok(())
+    }
+
+    /// Sets the user-defined state bits of a storage or a stream.
+    pub fn set_state_bits(&mut self, path: &str, state_bits: u32) -> Result<(), Error> {
+        self.find_mut(path)?.flags = state_bits;
+        Ok(())
+    }
+
+    /// Sets the creation and last modification times (FILETIME) of a
+    /// storage.
+    pub fn set_times(&mut self, path: &str, creation: u64, modification: u64) -> Result<(), Error> {
+        let node = self.find_mut(path)?;
+        node.creation_time = creation;
+        node.last_modification_time = modification;
+        Ok(())
+    }
+
+    /// Writes the compound file into `path`.
+    pub fn save(&self, path: &str) -> Result<(), Error> {
+        let file = std::fs::File::create(path)?;
+        self.write_to(std::io::BufWriter::new(file))
+    }
+
+    /// Lays out and writes the compound file.
+    pub fn write_to<W: Write>(&self, mut out: W) -> Result<(), Error> {
+        let sec_size = self.version.sector_size();
+        let ids_per_sector = sec_size / constants::U32_SIZE;
+
+        // Small streams go into the mini stream, the others get their own
+        // chain of sectors.
+        let mut mini_fat = Vec::<u32>::new();
+        let mut mini_stream = Vec::<u8>::new();
+        let mut starts = vec![constants::SECID_END_OF_CHAIN; self.nodes.len()];
+        let mut big_streams = Vec::<usize>::new();
+        for (i, node) in self.nodes.iter().enumerate() {
+            if node.entry_type != EntryType::UserStream || node.data.is_empty() {
+                continue;
+            }
+            if node.data.len() < constants::MINI_STREAM_CUTOFF {
+                let short_sec_size = 1 << constants::MINI_SECTOR_SHIFT;
+                starts[i] = chain(&mut mini_fat, node.data.len().div_ceil(short_sec_size));
+                mini_stream.extend_from_slice(&node.data);
+                mini_stream.resize(mini_fat.len() * short_sec_size, 0);
+            } else {
+                big_streams.push(i);
+            }
+        }
+
+        let dir_sectors = (self.nodes.len() * constants::DIRECTORY_ENTRY_SIZE).div_ceil(sec_size);
+        let mini_fat_sectors = mini_fat.len().div_ceil(ids_per_sector);
+        let mini_stream_sectors = mini_stream.len().div_ceil(sec_size);
+        let stream_sectors: usize = big_streams
+            .iter()
+            .map(|&i| self.nodes[i].data.len().div_ceil(sec_size))
+            .sum();
+        let (fat_sectors, difat_sectors) = fat_sector_counts(
+            dir_sectors + mini_fat_sectors + mini_stream_sectors + stream_sectors,
+            ids_per_sector,
+        );
+
+        // The FAT and DIFAT sectors come first, then the directory, the
+        // mini FAT, the mini stream and the streams.
+        let mut fat = Vec::<u32>::with_capacity(fat_sectors * ids_per_sector);
+        fat.resize(fat_sectors, constants::SECID_FAT_SECTOR);
+        fat.resize(fat_sectors + difat_sectors, constants::SECID_DIFAT_SECTOR);
+        let dir_start = chain(&mut fat, dir_sectors);
+        let mini_fat_start = chain(&mut fat, mini_fat_sectors);
+        let mini_stream_start = chain(&mut fat, mini_stream_sectors);
+        for &i in &big_streams {
+            starts[i] = chain(&mut fat, self.nodes[i].data.len().div_ceil(sec_size));
+        }
+        starts[0] = mini_stream_start;
+        fat.resize(fat_sectors * ids_per_sector, constants::SECID_FREE_SECTOR);
+
+        // DIFAT entries which don't fit into the header.
+        let mut difat = Vec::<u32>::with_capacity(difat_sectors * ids_per_sector);
+        for (k, fat_chunk) in (0..fat_sectors as u32)
+            .skip(constants::DIFAT_IN_HEADER)
+            .collect::<Vec<u32>>()
+            .chunks(ids_per_sector - 1)
+            .enumerate()
+        {
+            difat.extend_from_slice(fat_chunk);
+            difat.resize((k + 1) * ids_per_sector - 1, constants::SECID_FREE_SECTOR);
+            difat.push(if k + 1 == difat_sectors {
+                constants::SECID_END_OF_CHAIN
+            } else {
+                (fat_sectors + k + 1) as u32
+            });
+        }
+
+        let mut header = vec![0u8; sec_size.max(constants::HEADER_SIZE)];
+        header[0..8].copy_from_slice(&constants::IDENTIFIER);
+        header[24..26].copy_from_slice(&constants::MINOR_VERSION.to_le_bytes());
+        header[26..28].copy_from_slice(&self.version.major().to_le_bytes());
+        header[28..30].copy_from_slice(&constants::LITTLE_ENDIAN_IDENTIFIER);
+        header[30..32].copy_from_slice(&self.version.sector_shift().to_le_bytes());
+        header[32..34].copy_from_slice(&constants::MINI_SECTOR_SHIFT.to_le_bytes());
+        if self.version == Version::V4 {
+            header[40..44].copy_from_slice(&(dir_sectors as u32).to_le_bytes());
+        }
+        header[44..48].copy_from_slice(&(fat_sectors as u32).to_le_bytes());
+        header[48..52].copy_from_slice(&dir_start.to_le_bytes());
+        header[56..60].copy_from_slice(&(constants::MINI_STREAM_CUTOFF as u32).to_le_bytes());
+        header[60..64].copy_from_slice(&mini_fat_start.to_le_bytes());
+        header[64..68].copy_from_slice(&(mini_fat_sectors as u32).to_le_bytes());
+        let difat_start = if difat_sectors == 0 {
+            constants::SECID_END_OF_CHAIN
+        } else {
+            fat_sectors as u32
+        };
+        header[68..72].copy_from_slice(&difat_start.to_le_bytes());
+        header[72..76].copy_from_slice(&(difat_sectors as u32).to_le_bytes());
+        for (k, dst) in header[76..constants::HEADER_SIZE]
+            .chunks_exact_mut(constants::U32_SIZE)
+            .enumerate()
+        {
+            let sec_id = if k < fat_sectors {
+                k as u32
+            } else {
+                constants::SECID_FREE_SECTOR
+            };
+            dst.copy_from_slice(&sec_id.to_le_bytes());
+        }
+        out.write_all(&header)?;
+
+        write_sec_ids(&mut out, &fat)?;
+        write_sec_ids(&mut out, &difat)?;
+
+        let mut directory = vec![0u8; dir_sectors * sec_size];
+        let entries = self.build_entries(&starts, mini_stream.len());
+        let mut slots = directory.chunks_exact_mut(constants::DIRECTORY_ENTRY_SIZE);
+        for (entry, dst) in entries.iter().zip(slots.by_ref()) {
+            entry.write_to(dst);
+        }
+        slots.for_each(Entry::write_free);
+        out.write_all(&directory)?;
+
+        mini_fat.resize(
+            mini_fat_sectors * ids_per_sector,
+            constants::SECID_FREE_SECTOR,
+        );
+        write_sec_ids(&mut out, &mini_fat)?;
+        write_padded(&mut out, &mini_stream, sec_size)?;
+        for &i in &big_streams {
+            write_padded(&mut out, &self.nodes[i].data, sec_size)?;
+        }
+        out.flush()?;
+
+        Ok(())
+    }
+
+    /// Builds the directory entries: one per node, with the same index.
+    fn build_entries(&self, starts: &[u32], mini_stream_size: usize) -> Vec<Entry> {
+        let mut entries = Vec::<Entry>::with_capacity(self.nodes.len());
+        for (i, node) in self.nodes.iter().enumerate() {
+            let mut entry = Entry::new(i as u32, &node.name, node.entry_type);
+            entry.identifier = node.identifier;
+            entry.flags = node.flags;
+            entry.creation_time = node.creation_time;
+            entry.last_modification_time = node.last_modification_time;
+            entry.size = match node.entry_type {
+                EntryType::RootStorage => mini_stream_size as u64,
+                _ => node.data.len() as u64,
+            };
+            // Storages have no data, their start sector must be zero.
+            match node.entry_type {
+                EntryType::UserStorage => entry.sec_id_chain.push(0),
+                _ if starts[i] != constants::SECID_END_OF_CHAIN => {
+                    entry.sec_id_chain.push(starts[i])
+                }
+                _ => {}
+            }
+            entries.push(entry);
+        }
+
+        for (i, node) in self.nodes.iter().enumerate() {
+            let mut children: Vec<u32> = node.children.iter().map(|&c| c as u32).collect();
+            children.sort_by(|&a, &b| {
+                compare_names(&self.nodes[a as usize].name, &self.nodes[b as usize].name)
+            });
+            entries[i].root_node = build_sibling_tree(&mut entries, &children);
+        }
+
+        entries
+    }
+
+    fn create(&mut self, path: &str, mut node: Node) -> Result<(), Error> {
+        let (parent_path, name) = match path.trim_end_matches('/').rsplit_once('/') {
+            Some((parent_path, name)) => (parent_path, name),
+            None => ("", path),
+        };
+        check_name(name)?;
+
+        let parent = self.find(parent_path)?;
+        if !self.nodes[parent].is_storage() {
+            return Err(Error::NotAStorage(parent_path.to_string()));
+        }
+        if self.child(parent, name).is_some() {
+            return Err(Error::EntryAlreadyExists(path.to_string()));
+        }
+
+        node.name = name.to_string();
+        self.nodes.push(node);
+        let id = self.nodes.len() - 1;
+        self.nodes[parent].children.push(id);
+        Ok(())
+    }
+
+    fn child(&self, parent: usize, name: &str) -> Option<usize> {
+        self.nodes[parent]
+            .children
+            .iter()
+            .copied()
+            .find(|&c| compare_names(&self.nodes[c].name, name) == Ordering::Equal)
+    }
+
+    fn find(&self, path: &str) -> Result<usize, Error> {
+        let mut current = 0usize;
+        for name in path.split('/').filter(|name| !name.is_empty()) {
+            current = self
+                .child(current, name)
+                .ok_or_else(|| Error::EntryNotFound(path.to_string()))?;
+        }
+        Ok(current)
+    }
+
+    fn find_mut(&mut self, path: &str) -> Result<&mut Node, Error> {
+        let i = self.find(path)?;
+        Ok(&mut self.nodes[i])
+    }
+}
+
+/// Appends a chain of `n` consecutive sectors to `table`, and returns the
+/// SecID of its first sector, or `SECID_END_OF_CHAIN` if `n` is null.
+fn chain(table: &mut Vec<u32>, n: usize) -> u32 {
+    if n == 0 {
+        return constants::SECID_END_OF_CHAIN;
+    }
+    let start = table.len() as u32;
+    table.extend((start + 1..start + n as u32).chain([constants::SECID_END_OF_CHAIN]));
+    start
+}
+
+/// Returns how many FAT and DIFAT sectors are needed to describe
+/// `data_sectors` sectors, themselves included.
+fn fat_sector_counts(data_sectors: usize, ids_per_sector: usize) -> (usize, usize) {
+    let mut fat_sectors = data_sectors.div_ceil(ids_per_sector);
+    loop {
+        let difat_sectors = fat_sectors
+            .saturating_sub(constants::DIFAT_IN_HEADER)
+            .div_ceil(ids_per_sector - 1);
+        if fat_sectors * ids_per_sector >= data_sectors + fat_sectors + difat_sectors {
+            return (fat_sectors, difat_sectors);
+        }
+        fat_sectors += 1;
+    }
+}
+
+fn write_sec_ids<W: Write>(out: &mut W, sec_ids: &[u32]) -> Result<(), Error> {
+    let bytes: Vec<u8> = sec_ids.iter().flat_map(|id| id.to_le_bytes()).collect();
+    out.write_all(&bytes)?;
+    Ok(())
+}
+
+fn write_padded<W: Write>(out: &mut W, data: &[u8], sec_size: usize) -> Result<(), Error> {
+    out.write_all(data)?;
+    let padding = data.len().next_multiple_of(sec_size) - data.len();
+    out.write_all(&vec![0u8; padding])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Version, Writer};
+    use crate::{Clsid, Entry, EntryType, Error, Reader};
+    use std::io::Read;
+
+    fn read_all(reader: &Reader, entry: &Entry) -> Vec<u8> {
+        let mut buf = Vec::new();
+        reader
+            .get_entry_slice(entry)
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        buf
+    }
+
+    fn find<'a>(reader: &'a Reader, name: &str) -> &'a Entry {
+        reader.iterate().find(|e| e.name() == name).unwrap()
+    }
+
+    #[test]
+    fn round_trip_v3() {
+        let small: Vec<u8> = (0..100u8).collect();
+        let big: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+
+        let mut writer = Writer::new(Version::V3);
+        writer.create_storage("Storage").unwrap();
+        writer.create_stream("Storage/Small", &small).unwrap();
+        writer.create_stream("Big", &big).unwrap();
+        writer.create_stream("Empty", &[]).unwrap();
+        writer
+            .set_clsid("Storage", Clsid::from_bytes([7u8; 16]))
+            .unwrap();
+
+        let mut buf = Vec::new();
+        writer.write_to(&mut buf).unwrap();
+        assert_eq!(buf.len() % 512, 0);
+
+        let reader = Reader::new(&buf[..]).unwrap();
+        assert_eq!(reader.sec_size, 512);
+        assert_eq!(read_all(&reader, find(&reader, "Small")), small);
+        assert_eq!(read_all(&reader, find(&reader, "Big")), big);
+        assert!(find(&reader, "Empty").is_empty());
+
+        let storage = find(&reader, "Storage");
+        assert_eq!(storage._type(), EntryType::UserStorage);
+        assert_eq!(storage.clsid(), Clsid::from_bytes([7u8; 16]));
+        assert_eq!(storage.children_nodes(), &vec![find(&reader, "Small").id()]);
+        assert_eq!(find(&reader, "Big").parent_node(), Some(0));
+    }
+
+    #[test]
+    fn many_entries() {
+        let mut writer = Writer::new(Version::V3);
+        for i in 0..100 {
+            writer
+                .create_stream(&format!("Stream {i}"), format!("{i}").as_bytes())
+                .unwrap();
+        }
+
+        let mut buf = Vec::new();
+        writer.write_to(&mut buf).unwrap();
+
+        let reader = Reader::new(&buf[..]).unwrap();
+        let root = reader.iterate().next().unwrap();
+        assert_eq!(root.children_nodes().len(), 100);
+        for i in 0..100 {
+            let entry = find(&reader, &format!("Stream {i}"));
+            assert_eq!(read_all(&reader, entry), format!("{i}").as_bytes());
+        }
+    }
+
+    #[test]
+    fn difat_sectors() {
+        // More than 109 FAT sectors are needed to describe this stream.
+        let big = vec![0x42u8; 110 * 128 * 512];
+
+        let mut writer = Writer::new(Version::V3);
+        writer.create_stream("Big", &big).unwrap();
+
+        let mut buf = Vec::new();
+        writer.write_to(&mut buf).unwrap();
+        assert_eq!(u32::from_le_bytes(buf[72..76].try_into().unwrap()), 1);
+
+        let reader = Reader::new(&buf[..]).unwrap();
+        assert!(reader.main_sat.len() > 109);
+        assert_eq!(read_all(&reader, find(&reader, "Big")), big);
+    }
+
+    #[test]
+    fn v4_layout() {
+        let mut writer = Writer::new(Version::V4);
+        writer.create_stream("Stream", &[1u8; 5000]).unwrap();
+
+        let mut buf = Vec::new();
+        writer.write_to(&mut buf).unwrap();
+
+        assert_eq!(buf.len() % 4096, 0);
+        assert_eq!(&buf[26..28], &[4, 0]);
+        assert_eq!(&buf[30..32], &[12, 0]);
+        // Directory sector count.
+        assert_eq!(&buf[40..44], &[1, 0, 0, 0]);
+        assert!(buf[512..4096].iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn unused_entries() {
+        let mut writer = Writer::new(Version::V3);
+        writer.create_stream("Stream", &[1u8; 100]).unwrap();
+        let mut buf = Vec::new();
+        writer.write_to(&mut buf).unwrap();
+
+        // The root entry and `Stream` are followed by two unused entries.
+        let reader = Reader::new(&buf[..]).unwrap();
+        let offset = reader.entry_offset(2).unwrap();
+        let mut free = [0u8; 128];
+        free[68..80].fill(0xFF);
+        assert_eq!(buf[offset..offset + 128], free);
+        assert_eq!(buf[offset + 128..offset + 256], free);
+    }
+
+    #[test]
+    fn bad_paths() {
+        let mut writer = Writer::new(Version::V3);
+        writer.create_stream("Stream", &[1u8]).unwrap();
+        assert!(matches!(
+            writer.create_stream("STREAM", &[1u8]),
+            Err(Error::EntryAlreadyExists(_))
+        ));
+        assert!(matches!(
+            writer.create_stream("Missing/Stream", &[1u8]),
+            Err(Error::EntryNotFound(_))
+        ));
+        assert!(matches!(
+            writer.create_stream("Stream/Child", &[1u8]),
+            Err(Error::NotAStorage(_))
+        ));
+        assert!(matches!(
+            writer.create_storage(&"x".repeat(32)),
+            Err(Error::InvalidEntryName(_))
+        ));
+    }
+}